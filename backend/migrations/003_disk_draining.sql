@@ -0,0 +1,4 @@
+-- Allow disks to be drained (excluded from new uploads, videos relocated) before removal
+ALTER TABLE disk_storage ADD COLUMN IF NOT EXISTS is_draining BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE INDEX IF NOT EXISTS idx_videos_disk_id ON videos(disk_id);
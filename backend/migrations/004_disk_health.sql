@@ -0,0 +1,18 @@
+-- Track disk health so failing disks can be taken out of rotation automatically
+ALTER TABLE disk_storage ADD COLUMN IF NOT EXISTS health_status VARCHAR(20) NOT NULL DEFAULT 'unknown';
+ALTER TABLE disk_storage ADD COLUMN IF NOT EXISTS disabled_by_health BOOLEAN NOT NULL DEFAULT FALSE;
+ALTER TABLE disk_storage ADD COLUMN IF NOT EXISTS last_checked_at TIMESTAMP WITH TIME ZONE;
+
+CREATE TABLE IF NOT EXISTS disk_health_checks (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    disk_id UUID NOT NULL REFERENCES disk_storage(id) ON DELETE CASCADE,
+    status VARCHAR(20) NOT NULL,
+    is_mounted BOOLEAN NOT NULL,
+    is_writable BOOLEAN NOT NULL,
+    total_space BIGINT,
+    available_space BIGINT,
+    message TEXT,
+    checked_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_disk_health_checks_disk_id ON disk_health_checks(disk_id, checked_at DESC);
@@ -0,0 +1,13 @@
+-- Additional copies of a stored file on disks other than its primary disk (media_blobs.disk_id).
+-- Deduplicated videos share one file, so they share its copies too; blob_id gets its foreign key
+-- in 007, which creates media_blobs
+CREATE TABLE IF NOT EXISTS media_replicas (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    blob_id UUID NOT NULL,
+    disk_id UUID NOT NULL REFERENCES disk_storage(id),
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    UNIQUE (blob_id, disk_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_media_replicas_blob_id ON media_replicas(blob_id);
+CREATE INDEX IF NOT EXISTS idx_media_replicas_disk_id ON media_replicas(disk_id);
@@ -0,0 +1,2 @@
+-- SHA-256 of the assembled video file, recorded at upload time for later verification
+ALTER TABLE videos ADD COLUMN IF NOT EXISTS file_sha256 VARCHAR(64);
@@ -0,0 +1,18 @@
+-- Content-addressed registry of uploaded media so identical files are stored only once
+CREATE TABLE IF NOT EXISTS media_blobs (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    kind VARCHAR(50) NOT NULL,
+    sha256 VARCHAR(64) NOT NULL,
+    reference VARCHAR(500) NOT NULL,
+    disk_id UUID REFERENCES disk_storage(id),
+    size_bytes BIGINT NOT NULL,
+    ref_count INTEGER NOT NULL DEFAULT 0,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    UNIQUE (kind, sha256)
+);
+
+CREATE INDEX IF NOT EXISTS idx_media_blobs_reference ON media_blobs(kind, reference);
+
+ALTER TABLE media_replicas
+    ADD CONSTRAINT media_replicas_blob_id_fkey FOREIGN KEY (blob_id) REFERENCES media_blobs(id) ON DELETE CASCADE;
@@ -0,0 +1,23 @@
+-- Upload progress kept in the database instead of JSON files in uploads/temp
+CREATE TABLE IF NOT EXISTS upload_sessions (
+    -- Chosen by the client for chunked uploads, so not a UUID column
+    id VARCHAR(64) PRIMARY KEY,
+    owner VARCHAR(255),
+    kind VARCHAR(50) NOT NULL,
+    protocol VARCHAR(20) NOT NULL DEFAULT 'chunked',
+    file_name VARCHAR(500) NOT NULL,
+    total_size BIGINT NOT NULL,
+    total_chunks INTEGER NOT NULL DEFAULT 0,
+    -- Bit n - 1 is set once chunk n has been stored
+    received_chunks BYTEA NOT NULL DEFAULT '\x'::bytea,
+    received_bytes BIGINT NOT NULL DEFAULT 0,
+    file_sha256 VARCHAR(64),
+    status VARCHAR(20) NOT NULL DEFAULT 'uploading',
+    file_reference VARCHAR(500),
+    disk_id UUID REFERENCES disk_storage(id) ON DELETE SET NULL,
+    expires_at TIMESTAMP WITH TIME ZONE NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    updated_at TIMESTAMP WITH TIME ZONE DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_upload_sessions_status_expires ON upload_sessions(status, expires_at);
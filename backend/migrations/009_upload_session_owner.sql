@@ -0,0 +1,2 @@
+-- Uploads are listed and authorized per admin user
+CREATE INDEX IF NOT EXISTS idx_upload_sessions_owner ON upload_sessions(owner, created_at DESC);
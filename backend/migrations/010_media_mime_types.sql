@@ -0,0 +1,2 @@
+-- MIME type detected from the uploaded file's magic bytes
+ALTER TABLE media_blobs ADD COLUMN IF NOT EXISTS mime_type VARCHAR(100);
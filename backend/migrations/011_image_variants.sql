@@ -0,0 +1,12 @@
+-- Resized, metadata-free renditions of uploaded images for responsive srcsets
+CREATE TABLE IF NOT EXISTS image_variants (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    source_reference VARCHAR(500) NOT NULL,
+    width INTEGER NOT NULL,
+    height INTEGER NOT NULL,
+    mime_type VARCHAR(100) NOT NULL,
+    reference VARCHAR(500) NOT NULL,
+    size_bytes BIGINT NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    UNIQUE (source_reference, width, mime_type)
+);
@@ -0,0 +1,10 @@
+-- Individual counted views; videos.views is the running total of these rows
+CREATE TABLE IF NOT EXISTS video_view_events (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    video_id UUID NOT NULL REFERENCES videos(id) ON DELETE CASCADE,
+    viewer_hash VARCHAR(64) NOT NULL,
+    created_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_video_view_events_viewer ON video_view_events(video_id, viewer_hash, created_at DESC);
+CREATE INDEX IF NOT EXISTS idx_video_view_events_created_at ON video_view_events(created_at);
@@ -0,0 +1,31 @@
+-- Playback sessions built from player heartbeats; watched_buckets is a bitmask of the
+-- twenty 5% segments of the video the session played through
+CREATE TABLE IF NOT EXISTS playback_sessions (
+    id UUID PRIMARY KEY,
+    video_id UUID NOT NULL REFERENCES videos(id) ON DELETE CASCADE,
+    viewer_hash VARCHAR(64) NOT NULL,
+    duration_seconds DOUBLE PRECISION NOT NULL DEFAULT 0,
+    watched_seconds DOUBLE PRECISION NOT NULL DEFAULT 0,
+    max_position DOUBLE PRECISION NOT NULL DEFAULT 0,
+    watched_buckets INTEGER NOT NULL DEFAULT 0,
+    started_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    last_seen_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_playback_sessions_started_at ON playback_sessions(started_at);
+CREATE INDEX IF NOT EXISTS idx_playback_sessions_video_id ON playback_sessions(video_id, started_at);
+
+-- Daily per-video rollup of view events and playback sessions (days are UTC)
+CREATE TABLE IF NOT EXISTS video_daily_stats (
+    video_id UUID NOT NULL REFERENCES videos(id) ON DELETE CASCADE,
+    day DATE NOT NULL,
+    views INTEGER NOT NULL DEFAULT 0,
+    unique_viewers INTEGER NOT NULL DEFAULT 0,
+    sessions INTEGER NOT NULL DEFAULT 0,
+    watch_seconds DOUBLE PRECISION NOT NULL DEFAULT 0,
+    avg_percent_watched DOUBLE PRECISION NOT NULL DEFAULT 0,
+    -- Number of sessions that played each 5% segment
+    retention INTEGER[] NOT NULL DEFAULT '{}',
+    updated_at TIMESTAMP WITH TIME ZONE NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (video_id, day)
+);
@@ -0,0 +1,43 @@
+-- Uploads and views per channel, category and UTC day; refreshed by the analytics task
+CREATE MATERIALIZED VIEW IF NOT EXISTS content_daily_rollup AS
+SELECT v.channel_id, v.category_id, d.day,
+       SUM(d.uploads)::BIGINT AS uploads,
+       SUM(d.views)::BIGINT AS views
+FROM (
+    SELECT id AS video_id, (created_at AT TIME ZONE 'UTC')::DATE AS day, 1 AS uploads, 0 AS views
+    FROM videos
+    UNION ALL
+    SELECT video_id, day, 0, views
+    FROM video_daily_stats
+) d
+JOIN videos v ON v.id = d.video_id
+GROUP BY v.channel_id, v.category_id, d.day;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_content_daily_rollup_key ON content_daily_rollup(channel_id, category_id, day);
+
+-- Current video count, lifetime views and bytes stored (primary copy plus replicas) per
+-- channel and category; a file shared by several videos is counted once per group
+CREATE MATERIALIZED VIEW IF NOT EXISTS content_storage_rollup AS
+WITH video_copies AS (
+    SELECT v.channel_id, v.category_id, v.views, b.id AS blob_id, b.size_bytes,
+           1 + (SELECT COUNT(*) FROM media_replicas r WHERE r.blob_id = b.id) AS copies
+    FROM videos v
+    LEFT JOIN media_blobs b ON b.kind = 'video' AND b.reference = v.video_file
+),
+blob_usage AS (
+    SELECT channel_id, category_id, blob_id, MAX(size_bytes * copies) AS bytes
+    FROM video_copies
+    WHERE blob_id IS NOT NULL
+    GROUP BY channel_id, category_id, blob_id
+)
+SELECT c.channel_id, c.category_id,
+       COUNT(*)::BIGINT AS videos,
+       SUM(COALESCE(c.views, 0))::BIGINT AS views,
+       COALESCE((
+           SELECT SUM(u.bytes) FROM blob_usage u
+           WHERE u.channel_id = c.channel_id AND u.category_id = c.category_id
+       ), 0)::BIGINT AS storage_bytes
+FROM video_copies c
+GROUP BY c.channel_id, c.category_id;
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_content_storage_rollup_key ON content_storage_rollup(channel_id, category_id);
@@ -0,0 +1,11 @@
+-- Text similarity for related video suggestions
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+ALTER TABLE videos ADD COLUMN IF NOT EXISTS search_vector tsvector
+    GENERATED ALWAYS AS (
+        setweight(to_tsvector('turkish', COALESCE(title, '')), 'A') ||
+        setweight(to_tsvector('turkish', COALESCE(description, '')), 'B')
+    ) STORED;
+
+CREATE INDEX IF NOT EXISTS idx_videos_search_vector ON videos USING GIN (search_vector);
+CREATE INDEX IF NOT EXISTS idx_videos_title_trgm ON videos USING GIN (title gin_trgm_ops);
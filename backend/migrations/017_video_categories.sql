@@ -0,0 +1,16 @@
+-- Every category a video belongs to; videos.category_id stays the primary one and is
+-- mirrored here with is_primary set
+CREATE TABLE IF NOT EXISTS video_categories (
+    video_id UUID NOT NULL REFERENCES videos(id) ON DELETE CASCADE,
+    category_id UUID NOT NULL REFERENCES categories(id) ON DELETE CASCADE,
+    is_primary BOOLEAN NOT NULL DEFAULT FALSE,
+    created_at TIMESTAMP WITH TIME ZONE DEFAULT NOW(),
+    PRIMARY KEY (video_id, category_id)
+);
+
+CREATE UNIQUE INDEX IF NOT EXISTS idx_video_categories_primary ON video_categories(video_id) WHERE is_primary;
+CREATE INDEX IF NOT EXISTS idx_video_categories_category_id ON video_categories(category_id);
+
+INSERT INTO video_categories (video_id, category_id, is_primary)
+SELECT id, category_id, TRUE FROM videos
+ON CONFLICT (video_id, category_id) DO UPDATE SET is_primary = TRUE;
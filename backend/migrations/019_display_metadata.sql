@@ -0,0 +1,27 @@
+-- Manual navigation order and display metadata for channels and categories
+ALTER TABLE channels ADD COLUMN IF NOT EXISTS position INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE channels ADD COLUMN IF NOT EXISTS description TEXT;
+ALTER TABLE channels ADD COLUMN IF NOT EXISTS color VARCHAR(7);
+ALTER TABLE channels ADD COLUMN IF NOT EXISTS banner VARCHAR(255);
+ALTER TABLE channels ADD COLUMN IF NOT EXISTS seo_title VARCHAR(70);
+ALTER TABLE channels ADD COLUMN IF NOT EXISTS seo_description VARCHAR(160);
+
+ALTER TABLE categories ADD COLUMN IF NOT EXISTS position INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE categories ADD COLUMN IF NOT EXISTS description TEXT;
+ALTER TABLE categories ADD COLUMN IF NOT EXISTS icon VARCHAR(50);
+ALTER TABLE categories ADD COLUMN IF NOT EXISTS color VARCHAR(7);
+ALTER TABLE categories ADD COLUMN IF NOT EXISTS banner VARCHAR(255);
+ALTER TABLE categories ADD COLUMN IF NOT EXISTS seo_title VARCHAR(70);
+ALTER TABLE categories ADD COLUMN IF NOT EXISTS seo_description VARCHAR(160);
+
+-- Start from the order the lists were shown in until now
+UPDATE channels c SET position = o.position
+FROM (SELECT id, ROW_NUMBER() OVER (ORDER BY created_at DESC) AS position FROM channels) o
+WHERE c.id = o.id;
+
+UPDATE categories c SET position = o.position
+FROM (SELECT id, ROW_NUMBER() OVER (ORDER BY name ASC) AS position FROM categories) o
+WHERE c.id = o.id;
+
+CREATE INDEX IF NOT EXISTS idx_channels_position ON channels(position);
+CREATE INDEX IF NOT EXISTS idx_categories_position ON categories(position);
@@ -0,0 +1,241 @@
+use axum::{
+    body::Bytes,
+    extract::{ConnectInfo, Path, Query, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use chrono::{Duration as DateDuration, NaiveDate, Utc};
+use serde::Deserialize;
+use sqlx::PgPool;
+use std::{collections::HashMap, net::SocketAddr, time::Duration};
+use uuid::Uuid;
+use crate::{
+    config::env_or,
+    database::{
+        get_content_rollups, get_video_by_id, get_video_daily_stats, get_video_duration_by_slug, get_weekly_uploads,
+        prune_playback_sessions, record_playback_heartbeat, refresh_content_rollups, rollup_video_daily_stats,
+        RollupGroup,
+    },
+    models::{ApiResponse, ContentRollup, PlaybackHeartbeat, VideoAnalytics, WatchTimeLimits},
+    views::{is_bot, viewer_hash, ViewState},
+};
+
+/// Retention is tracked in this many equal segments of a video; must fit the session bitmask.
+pub const RETENTION_BUCKETS: i32 = 20;
+/// Upper bound on the playback one heartbeat may add; players report every 15 seconds.
+const MAX_HEARTBEAT_SECONDS: f64 = 60.0;
+const WATCH_TIME_LIMITS: WatchTimeLimits = WatchTimeLimits { slack_seconds: 5.0, max_plays: 3.0 };
+const DEFAULT_RANGE_DAYS: i64 = 30;
+const MAX_RANGE_DAYS: i64 = 366;
+
+#[derive(Clone, Debug)]
+pub struct AnalyticsConfig {
+    pub rollup_interval: Duration,
+    /// How many past UTC days each rollup recomputes besides today, to include late heartbeats.
+    pub rollup_days: i64,
+    pub session_retention_days: i32,
+}
+
+impl AnalyticsConfig {
+    pub fn from_env() -> Self {
+        Self {
+            rollup_interval: Duration::from_secs(env_or("ANALYTICS_ROLLUP_INTERVAL_SECS", 300)),
+            rollup_days: env_or("ANALYTICS_ROLLUP_DAYS", 1),
+            session_retention_days: env_or("ANALYTICS_SESSION_RETENTION_DAYS", 90),
+        }
+    }
+}
+
+/// Bitmask of the retention segments the span from `start` to `end` played through.
+fn bucket_mask(start: f64, end: f64, duration: f64) -> i32 {
+    if duration <= 0.0 || end < start {
+        return 0;
+    }
+    let bucket = |position: f64| {
+        ((position / duration * RETENTION_BUCKETS as f64) as i32).clamp(0, RETENTION_BUCKETS - 1)
+    };
+    (bucket(start)..=bucket(end)).fold(0, |mask, index| mask | (1 << index))
+}
+
+/// Reads a stored video length: whole or fractional seconds, `MM:SS` or `HH:MM:SS`.
+fn parse_duration(text: &str) -> Option<f64> {
+    let seconds = text.trim().split(':').try_fold(0.0, |total, part| {
+        let value = part.parse::<f64>().ok().filter(|value| *value >= 0.0)?;
+        Some(total * 60.0 + value)
+    })?;
+    (seconds.is_finite() && seconds > 0.0).then_some(seconds)
+}
+
+/// Playback heartbeat; parsed regardless of content type like the view beacon.
+pub async fn record_heartbeat_handler(
+    State(state): State<ViewState>,
+    Path(slug): Path<String>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<ApiResponse<String>>, StatusCode> {
+    if is_bot(&headers) {
+        return Ok(Json(ApiResponse::success("Yok sayıldı".to_string())));
+    }
+    let mut heartbeat: PlaybackHeartbeat = serde_json::from_slice(&body).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let values = [heartbeat.start, heartbeat.position, heartbeat.watched];
+    if values.iter().any(|value| !value.is_finite() || *value < 0.0) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let (video_id, duration) = match get_video_duration_by_slug(&state.pool, &slug).await {
+        Ok(Some((id, duration))) => (id, duration.as_deref().and_then(parse_duration)),
+        Ok(None) => return Ok(Json(ApiResponse::error("Video bulunamadı".to_string()))),
+        Err(_) => return Ok(Json(ApiResponse::error("Video alınamadı".to_string()))),
+    };
+    // Without a stored length there is nothing to bound the session's watch time by.
+    let duration = match duration {
+        Some(duration) => duration,
+        None => return Ok(Json(ApiResponse::success("Yok sayıldı".to_string()))),
+    };
+    heartbeat.position = heartbeat.position.min(duration);
+    heartbeat.start = heartbeat.start.min(heartbeat.position);
+    heartbeat.watched = heartbeat.watched.min(MAX_HEARTBEAT_SECONDS);
+
+    let hash = viewer_hash(&state.config, &headers, addr, heartbeat.client_id.as_deref());
+    let buckets = bucket_mask(heartbeat.start, heartbeat.position, duration);
+    let recorded = record_playback_heartbeat(
+        &state.pool, video_id, &hash, &heartbeat, duration, buckets, WATCH_TIME_LIMITS,
+    )
+    .await;
+    match recorded {
+        Ok(true) => Ok(Json(ApiResponse::success("Kaydedildi".to_string()))),
+        Ok(false) => Err(StatusCode::CONFLICT),
+        Err(e) => {
+            tracing::warn!("Failed to record heartbeat for video {}: {}", video_id, e);
+            Ok(Json(ApiResponse::error("İzlenme verisi kaydedilemedi".to_string())))
+        }
+    }
+}
+
+pub async fn run_analytics_rollup(pool: PgPool, config: AnalyticsConfig) {
+    let mut interval = tokio::time::interval(config.rollup_interval);
+    loop {
+        interval.tick().await;
+        let since = Utc::now().date_naive() - DateDuration::days(config.rollup_days);
+        match rollup_video_daily_stats(&pool, since, RETENTION_BUCKETS).await {
+            Ok(rows) => tracing::debug!("Rolled up {} daily video stats since {}", rows, since),
+            Err(e) => tracing::error!("Failed to roll up video analytics: {}", e),
+        }
+        // The channel and category rollups read the daily stats, so they are refreshed after them.
+        if let Err(e) = refresh_content_rollups(&pool).await {
+            tracing::error!("Failed to refresh content rollups: {}", e);
+        }
+        if let Err(e) = prune_playback_sessions(&pool, config.session_retention_days).await {
+            tracing::error!("Failed to prune playback sessions: {}", e);
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AnalyticsRangeQuery {
+    pub from: Option<NaiveDate>,
+    pub to: Option<NaiveDate>,
+}
+
+impl AnalyticsRangeQuery {
+    /// The inclusive day range, defaulting to the last 30 days; None when it is invalid.
+    fn resolve(&self) -> Option<(NaiveDate, NaiveDate)> {
+        let to = self.to.unwrap_or_else(|| Utc::now().date_naive());
+        let from = self.from.unwrap_or(to - DateDuration::days(DEFAULT_RANGE_DAYS - 1));
+        (from <= to && (to - from).num_days() < MAX_RANGE_DAYS).then_some((from, to))
+    }
+}
+
+/// Daily stats and range totals of a video; today's numbers lag by up to one rollup interval.
+pub async fn get_video_analytics_handler(
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+    Query(range): Query<AnalyticsRangeQuery>,
+) -> Result<Json<ApiResponse<VideoAnalytics>>, StatusCode> {
+    let Some((from, to)) = range.resolve() else {
+        return Ok(Json(ApiResponse::error("Geçersiz tarih aralığı".to_string())));
+    };
+
+    let video = match get_video_by_id(&pool, id).await {
+        Ok(Some(video)) => video,
+        Ok(None) => return Ok(Json(ApiResponse::error("Video bulunamadı".to_string()))),
+        Err(_) => return Ok(Json(ApiResponse::error("Video alınamadı".to_string()))),
+    };
+    let daily = match get_video_daily_stats(&pool, id, from, to).await {
+        Ok(daily) => daily,
+        Err(_) => return Ok(Json(ApiResponse::error("Analiz verileri alınamadı".to_string()))),
+    };
+
+    let views = daily.iter().map(|day| day.views as i64).sum();
+    let sessions: i64 = daily.iter().map(|day| day.sessions as i64).sum();
+    let watch_seconds = daily.iter().map(|day| day.watch_seconds).sum();
+    let mut plays = vec![0i64; RETENTION_BUCKETS as usize];
+    for day in &daily {
+        for (total, count) in plays.iter_mut().zip(&day.retention) {
+            *total += *count as i64;
+        }
+    }
+    let percent = |value: f64| if sessions > 0 { value * 100.0 / sessions as f64 } else { 0.0 };
+    let avg_percent_watched = percent(plays.iter().sum::<i64>() as f64 / RETENTION_BUCKETS as f64);
+    let retention = plays.iter().map(|count| percent(*count as f64)).collect();
+
+    Ok(Json(ApiResponse::success(VideoAnalytics {
+        video_id: video.id,
+        from,
+        to,
+        total_views: video.views,
+        views,
+        sessions,
+        watch_seconds,
+        avg_percent_watched,
+        retention,
+        daily,
+    })))
+}
+
+async fn content_rollups(
+    pool: &PgPool,
+    group: RollupGroup,
+    range: &AnalyticsRangeQuery,
+) -> Result<Json<ApiResponse<Vec<ContentRollup>>>, StatusCode> {
+    let Some((from, to)) = range.resolve() else {
+        return Ok(Json(ApiResponse::error("Geçersiz tarih aralığı".to_string())));
+    };
+    let (rollups, weeks) = match tokio::try_join!(
+        get_content_rollups(pool, group, from, to),
+        get_weekly_uploads(pool, group, from, to),
+    ) {
+        Ok(result) => result,
+        Err(_) => return Ok(Json(ApiResponse::error("Analiz verileri alınamadı".to_string()))),
+    };
+
+    let mut weeks_by_id: HashMap<Uuid, Vec<_>> = HashMap::new();
+    for week in weeks {
+        weeks_by_id.entry(week.id).or_default().push(week);
+    }
+    let rollups = rollups
+        .into_iter()
+        .map(|mut rollup| {
+            rollup.uploads_per_week = weeks_by_id.remove(&rollup.id).unwrap_or_default();
+            rollup
+        })
+        .collect();
+    Ok(Json(ApiResponse::success(rollups)))
+}
+
+/// Per-channel totals; refreshed with the daily stats, so they lag by up to one rollup interval.
+pub async fn get_channel_analytics_handler(
+    State(pool): State<PgPool>,
+    Query(range): Query<AnalyticsRangeQuery>,
+) -> Result<Json<ApiResponse<Vec<ContentRollup>>>, StatusCode> {
+    content_rollups(&pool, RollupGroup::Channel, &range).await
+}
+
+/// Per-category totals, computed like the channel ones.
+pub async fn get_category_analytics_handler(
+    State(pool): State<PgPool>,
+    Query(range): Query<AnalyticsRangeQuery>,
+) -> Result<Json<ApiResponse<Vec<ContentRollup>>>, StatusCode> {
+    content_rollups(&pool, RollupGroup::Category, &range).await
+}
@@ -0,0 +1,11 @@
+//! Environment lookups shared by the `from_env` constructors of the service configs.
+
+use std::env;
+
+/// Parses the variable `key`, falling back to `default` when it is unset or does not parse.
+pub(crate) fn env_or<T: std::str::FromStr>(key: &str, default: T) -> T {
+    env::var(key)
+        .ok()
+        .and_then(|value| value.parse().ok())
+        .unwrap_or(default)
+}
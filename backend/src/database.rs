@@ -1,8 +1,9 @@
-use sqlx::{PgPool};
+use sqlx::{PgConnection, PgExecutor, PgPool};
 use anyhow::Result;
 use crate::models::*;
 use uuid::Uuid;
 use slug::slugify;
+use chrono::NaiveDate;
 
 pub async fn create_pool(database_url: &str) -> Result<PgPool> {
     let pool = PgPool::connect(database_url).await?;
@@ -11,7 +12,7 @@ pub async fn create_pool(database_url: &str) -> Result<PgPool> {
 
 pub async fn get_channels(pool: &PgPool) -> Result<Vec<Channel>> {
     let channels = sqlx::query_as::<_, Channel>(
-        "SELECT * FROM channels ORDER BY created_at DESC"
+        "SELECT * FROM channels ORDER BY position ASC, created_at DESC"
     )
     .fetch_all(pool)
     .await?;
@@ -28,44 +29,112 @@ pub async fn get_channel_by_slug(pool: &PgPool, slug: &str) -> Result<Option<Cha
     Ok(channel)
 }
 
-pub async fn create_channel(pool: &PgPool, channel: CreateChannel) -> Result<Channel> {
+pub async fn create_channel<'e>(executor: impl PgExecutor<'e>, channel: CreateChannel) -> Result<Channel> {
     let slug = slugify(&channel.name);
     let new_channel = sqlx::query_as::<_, Channel>(
-        "INSERT INTO channels (name, image, slug) VALUES ($1, $2, $3) RETURNING *"
+        "INSERT INTO channels (name, image, slug, description, color, banner, seo_title, seo_description, position)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, (SELECT COALESCE(MAX(position), 0) + 1 FROM channels))
+         RETURNING *"
     )
     .bind(&channel.name)
     .bind(&channel.image)
     .bind(&slug)
-    .fetch_one(pool)
+    .bind(&channel.description)
+    .bind(&channel.color)
+    .bind(&channel.banner)
+    .bind(&channel.seo_title)
+    .bind(&channel.seo_description)
+    .fetch_one(executor)
     .await?;
     Ok(new_channel)
 }
 
-pub async fn update_channel(pool: &PgPool, id: Uuid, channel: CreateChannel) -> Result<Channel> {
+pub async fn update_channel<'e>(executor: impl PgExecutor<'e>, id: Uuid, channel: CreateChannel) -> Result<Channel> {
     let slug = slugify(&channel.name);
     let updated_channel = sqlx::query_as::<_, Channel>(
-        "UPDATE channels SET name = $1, image = $2, slug = $3, updated_at = NOW() WHERE id = $4 RETURNING *"
+        "UPDATE channels SET name = $1, image = $2, slug = $3, description = $4, color = $5, banner = $6,
+             seo_title = $7, seo_description = $8, updated_at = NOW()
+         WHERE id = $9 RETURNING *"
     )
     .bind(&channel.name)
     .bind(&channel.image)
     .bind(&slug)
+    .bind(&channel.description)
+    .bind(&channel.color)
+    .bind(&channel.banner)
+    .bind(&channel.seo_title)
+    .bind(&channel.seo_description)
     .bind(id)
-    .fetch_one(pool)
+    .fetch_one(executor)
     .await?;
     Ok(updated_channel)
 }
 
-pub async fn delete_channel(pool: &PgPool, id: Uuid) -> Result<()> {
-    sqlx::query("DELETE FROM channels WHERE id = $1")
-        .bind(id)
-        .execute(pool)
+/// Gives the listed rows of `table` positions 1, 2, … in list order.
+///
+/// `ids` must be one whole group of rows that are ordered together: every row sharing the
+/// first row's `group_column`, or the whole table without one. Fails without changing anything
+/// otherwise.
+async fn set_positions(pool: &PgPool, table: &str, group_column: Option<&str>, ids: &[Uuid]) -> Result<()> {
+    let first = ids.first().ok_or_else(|| anyhow::anyhow!("no ids to order in {}", table))?;
+    let group = match group_column {
+        Some(column) => format!("WHERE {0} IS NOT DISTINCT FROM (SELECT {0} FROM {1} WHERE id = $2)", column, table),
+        None => String::new(),
+    };
+
+    let mut tx = pool.begin().await?;
+    // Keeps rows from joining or leaving the group between the check and the update.
+    sqlx::query(&format!("LOCK TABLE {} IN SHARE ROW EXCLUSIVE MODE", table))
+        .execute(&mut *tx)
         .await?;
+    let (listed, total) = sqlx::query_as::<_, (i64, i64)>(&format!(
+        "SELECT COUNT(*) FILTER (WHERE id = ANY($1)), COUNT(*) FROM {} {}",
+        table, group
+    ))
+    .bind(ids)
+    .bind(first)
+    .fetch_one(&mut *tx)
+    .await?;
+    if listed != ids.len() as i64 || total != ids.len() as i64 {
+        anyhow::bail!("{} ids listed for a group of {} in {}", ids.len(), total, table);
+    }
+
+    sqlx::query(&format!(
+        "UPDATE {} t SET position = o.position, updated_at = NOW()
+         FROM UNNEST($1::UUID[]) WITH ORDINALITY AS o(id, position)
+         WHERE t.id = o.id",
+        table
+    ))
+    .bind(ids)
+    .execute(&mut *tx)
+    .await?;
+    tx.commit().await?;
     Ok(())
 }
 
+/// Orders all channels; `ids` must list every channel.
+pub async fn reorder_channels(pool: &PgPool, ids: &[Uuid]) -> Result<()> {
+    set_positions(pool, "channels", None, ids).await
+}
+
+/// Orders the children of one parent; `ids` must list every one of them.
+pub async fn reorder_categories(pool: &PgPool, ids: &[Uuid]) -> Result<()> {
+    set_positions(pool, "categories", Some("parent_id"), ids).await
+}
+
+pub async fn delete_channel<'e>(executor: impl PgExecutor<'e>, id: Uuid) -> Result<Option<Channel>> {
+    let channel = sqlx::query_as::<_, Channel>("DELETE FROM channels WHERE id = $1 RETURNING *")
+        .bind(id)
+        .fetch_optional(executor)
+        .await?;
+    Ok(channel)
+}
+
+/// Top-level categories first, then the children of each parent together, each group in position
+/// order.
 pub async fn get_categories(pool: &PgPool) -> Result<Vec<Category>> {
     let categories = sqlx::query_as::<_, Category>(
-        "SELECT * FROM categories ORDER BY name ASC"
+        "SELECT * FROM categories ORDER BY parent_id ASC NULLS FIRST, position ASC, name ASC"
     )
     .fetch_all(pool)
     .await?;
@@ -82,50 +151,266 @@ pub async fn get_category_by_slug(pool: &PgPool, slug: &str) -> Result<Option<Ca
     Ok(category)
 }
 
-pub async fn create_category(pool: &PgPool, category: CreateCategory) -> Result<Category> {
+pub async fn create_category<'e>(executor: impl PgExecutor<'e>, category: CreateCategory) -> Result<Category> {
     let slug = slugify(&category.name);
     let new_category = sqlx::query_as::<_, Category>(
-        "INSERT INTO categories (name, slug) VALUES ($1, $2) RETURNING *"
+        "INSERT INTO categories (name, slug, parent_id, description, icon, color, banner, seo_title, seo_description, position)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9,
+                 (SELECT COALESCE(MAX(position), 0) + 1 FROM categories WHERE parent_id IS NOT DISTINCT FROM $3))
+         RETURNING *"
     )
     .bind(&category.name)
     .bind(&slug)
-    .fetch_one(pool)
+    .bind(category.parent_id)
+    .bind(&category.description)
+    .bind(&category.icon)
+    .bind(&category.color)
+    .bind(&category.banner)
+    .bind(&category.seo_title)
+    .bind(&category.seo_description)
+    .fetch_one(executor)
     .await?;
     Ok(new_category)
 }
 
-pub async fn update_category(pool: &PgPool, id: Uuid, category: CreateCategory) -> Result<Category> {
+/// Updates a category. Returns None, changing nothing, when the new parent is the category
+/// itself or one of its descendants.
+///
+/// Moves lock the table from the cycle check until the caller's transaction ends, so two
+/// concurrent moves cannot each pass the check and together form a cycle.
+pub async fn update_category(conn: &mut PgConnection, id: Uuid, category: CreateCategory) -> Result<Option<Category>> {
     let slug = slugify(&category.name);
+    if let Some(parent_id) = category.parent_id {
+        sqlx::query("LOCK TABLE categories IN SHARE ROW EXCLUSIVE MODE")
+            .execute(&mut *conn)
+            .await?;
+        if is_category_descendant(&mut *conn, id, parent_id).await? {
+            return Ok(None);
+        }
+    }
     let updated_category = sqlx::query_as::<_, Category>(
-        "UPDATE categories SET name = $1, slug = $2, updated_at = NOW() WHERE id = $3 RETURNING *"
+        "UPDATE categories SET name = $1, slug = $2, parent_id = $3, description = $4, icon = $5, color = $6,
+             banner = $7, seo_title = $8, seo_description = $9, updated_at = NOW(),
+             position = CASE WHEN parent_id IS NOT DISTINCT FROM $3 THEN position
+                 ELSE (SELECT COALESCE(MAX(position), 0) + 1 FROM categories WHERE parent_id IS NOT DISTINCT FROM $3)
+             END
+         WHERE id = $10 RETURNING *"
     )
     .bind(&category.name)
     .bind(&slug)
+    .bind(category.parent_id)
+    .bind(&category.description)
+    .bind(&category.icon)
+    .bind(&category.color)
+    .bind(&category.banner)
+    .bind(&category.seo_title)
+    .bind(&category.seo_description)
+    .bind(id)
+    .fetch_one(&mut *conn)
+    .await?;
+    Ok(Some(updated_category))
+}
+
+/// Whether `ancestor` is `id` itself or one of its descendants, in which case it cannot
+/// become the parent of `id`.
+async fn is_category_descendant<'e>(executor: impl PgExecutor<'e>, id: Uuid, ancestor: Uuid) -> Result<bool> {
+    let found = sqlx::query_scalar::<_, bool>(
+        "WITH RECURSIVE subtree(id) AS (
+             SELECT $1::UUID
+             UNION
+             SELECT c.id FROM categories c JOIN subtree s ON c.parent_id = s.id
+         )
+         SELECT EXISTS (SELECT 1 FROM subtree WHERE id = $2)"
+    )
+    .bind(id)
+    .bind(ancestor)
+    .fetch_one(executor)
+    .await?;
+    Ok(found)
+}
+
+/// The category's ancestors from the top level down, ending with the category itself.
+pub async fn get_category_breadcrumbs(pool: &PgPool, id: Uuid) -> Result<Vec<CategoryCrumb>> {
+    let crumbs = sqlx::query_as::<_, CategoryCrumb>(
+        "WITH RECURSIVE path(id, name, slug, parent_id, depth) AS (
+             SELECT id, name, slug, parent_id, 0 FROM categories WHERE id = $1
+             UNION ALL
+             SELECT c.id, c.name, c.slug, c.parent_id, p.depth + 1
+             FROM categories c JOIN path p ON c.id = p.parent_id
+             WHERE p.depth < 64
+         )
+         SELECT id, name, slug FROM path ORDER BY depth DESC"
+    )
+    .bind(id)
+    .fetch_all(pool)
+    .await?;
+    Ok(crumbs)
+}
+
+pub async fn delete_category<'e>(executor: impl PgExecutor<'e>, id: Uuid) -> Result<Option<Category>> {
+    let category = sqlx::query_as::<_, Category>("DELETE FROM categories WHERE id = $1 RETURNING *")
+        .bind(id)
+        .fetch_optional(executor)
+        .await?;
+    Ok(category)
+}
+
+/// Tag columns plus the number of videos carrying the tag.
+const TAG_SELECT: &str =
+    "SELECT t.*, (SELECT COUNT(*) FROM video_tags vt WHERE vt.tag_id = t.id) AS video_count
+     FROM tags t";
+
+pub async fn get_tags(pool: &PgPool) -> Result<Vec<Tag>> {
+    let tags = sqlx::query_as::<_, Tag>(&format!("{} ORDER BY t.name ASC", TAG_SELECT))
+        .fetch_all(pool)
+        .await?;
+    Ok(tags)
+}
+
+pub async fn get_tag_by_slug(pool: &PgPool, slug: &str) -> Result<Option<Tag>> {
+    let tag = sqlx::query_as::<_, Tag>(&format!("{} WHERE t.slug = $1", TAG_SELECT))
+        .bind(slug)
+        .fetch_optional(pool)
+        .await?;
+    Ok(tag)
+}
+
+pub async fn create_tag(pool: &PgPool, tag: CreateTag) -> Result<Tag> {
+    let new_tag = sqlx::query_as::<_, Tag>(
+        "INSERT INTO tags (name, slug) VALUES ($1, $2) RETURNING *, 0::BIGINT AS video_count"
+    )
+    .bind(tag.name.trim())
+    .bind(slugify(&tag.name))
+    .fetch_one(pool)
+    .await?;
+    Ok(new_tag)
+}
+
+pub async fn update_tag(pool: &PgPool, id: Uuid, tag: CreateTag) -> Result<Tag> {
+    let updated_tag = sqlx::query_as::<_, Tag>(
+        "UPDATE tags SET name = $1, slug = $2, updated_at = NOW() WHERE id = $3
+         RETURNING *, (SELECT COUNT(*) FROM video_tags vt WHERE vt.tag_id = tags.id) AS video_count"
+    )
+    .bind(tag.name.trim())
+    .bind(slugify(&tag.name))
     .bind(id)
     .fetch_one(pool)
     .await?;
-    Ok(updated_category)
+    Ok(updated_tag)
 }
 
-pub async fn delete_category(pool: &PgPool, id: Uuid) -> Result<()> {
-    sqlx::query("DELETE FROM categories WHERE id = $1")
+pub async fn delete_tag(pool: &PgPool, id: Uuid) -> Result<()> {
+    sqlx::query("DELETE FROM tags WHERE id = $1")
         .bind(id)
         .execute(pool)
         .await?;
     Ok(())
 }
 
+/// Replaces a video's tags with the named ones, creating tags that do not exist yet. Runs on
+/// the caller's transaction, together with the video write.
+async fn set_video_tags(conn: &mut PgConnection, video_id: Uuid, names: &[String]) -> Result<()> {
+    let mut names_by_slug = std::collections::BTreeMap::new();
+    for name in names.iter().map(|name| name.trim()) {
+        let slug = slugify(name);
+        if !slug.is_empty() {
+            names_by_slug.entry(slug).or_insert(name);
+        }
+    }
+    let (slugs, names): (Vec<String>, Vec<&str>) = names_by_slug.into_iter().unzip();
+
+    sqlx::query(
+        "INSERT INTO tags (name, slug)
+         SELECT * FROM UNNEST($1::VARCHAR[], $2::VARCHAR[])
+         ON CONFLICT (slug) DO NOTHING"
+    )
+    .bind(&names)
+    .bind(&slugs)
+    .execute(&mut *conn)
+    .await?;
+    sqlx::query(
+        "DELETE FROM video_tags vt USING tags t
+         WHERE vt.video_id = $1 AND vt.tag_id = t.id AND NOT t.slug = ANY($2)"
+    )
+    .bind(video_id)
+    .bind(&slugs)
+    .execute(&mut *conn)
+    .await?;
+    sqlx::query(
+        "INSERT INTO video_tags (video_id, tag_id)
+         SELECT $1, id FROM tags WHERE slug = ANY($2)
+         ON CONFLICT DO NOTHING"
+    )
+    .bind(video_id)
+    .bind(&slugs)
+    .execute(&mut *conn)
+    .await?;
+    Ok(())
+}
+
+pub async fn get_videos_by_tag(pool: &PgPool, tag_slug: &str) -> Result<Vec<VideoWithDetails>> {
+    let videos = sqlx::query_as::<_, VideoWithDetails>(
+        &format!(
+            "{}
+             WHERE EXISTS (
+                 SELECT 1 FROM video_tags vt JOIN tags t ON t.id = vt.tag_id
+                 WHERE vt.video_id = v.id AND t.slug = $1
+             )
+             ORDER BY v.created_at DESC",
+            VIDEO_DETAILS_SELECT
+        )
+    )
+    .bind(tag_slug)
+    .fetch_all(pool)
+    .await?;
+    Ok(videos)
+}
+
+/// Columns and joins shared by every `VideoWithDetails` query.
+const VIDEO_DETAILS_SELECT: &str =
+    "SELECT v.id, v.title, v.description, v.slug, v.thumbnail, v.video_file, v.duration, 
+            v.views, v.is_featured, v.created_at,
+            c.name as channel_name, c.slug as channel_slug, c.image as channel_profile_image,
+            cat.name as category_name, cat.slug as category_slug,
+            COALESCE((
+                SELECT json_agg(json_build_object(
+                    'url', '/uploads/' || iv.reference,
+                    'width', iv.width,
+                    'height', iv.height,
+                    'mime_type', iv.mime_type
+                ) ORDER BY iv.mime_type, iv.width)
+                FROM image_variants iv
+                WHERE iv.source_reference = v.thumbnail
+            ), '[]'::json) as thumbnail_variants,
+            COALESCE((
+                SELECT json_agg(json_build_object('name', t.name, 'slug', t.slug) ORDER BY t.name)
+                FROM video_tags vt
+                JOIN tags t ON t.id = vt.tag_id
+                WHERE vt.video_id = v.id
+            ), '[]'::json) as tags,
+            COALESCE((
+                SELECT json_agg(json_build_object(
+                    'id', vc_cat.id,
+                    'name', vc_cat.name,
+                    'slug', vc_cat.slug,
+                    'is_primary', vc.is_primary
+                ) ORDER BY vc.is_primary DESC, vc_cat.name)
+                FROM video_categories vc
+                JOIN categories vc_cat ON vc_cat.id = vc.category_id
+                WHERE vc.video_id = v.id
+            ), '[]'::json) as categories
+     FROM videos v
+     JOIN channels c ON v.channel_id = c.id
+     JOIN categories cat ON v.category_id = cat.id";
+
 pub async fn get_videos_with_details(pool: &PgPool, limit: Option<i64>, offset: Option<i64>) -> Result<Vec<VideoWithDetails>> {
     let videos = sqlx::query_as::<_, VideoWithDetails>(
-        "SELECT v.id, v.title, v.description, v.slug, v.thumbnail, v.video_file, v.duration, 
-                v.views, v.is_featured, v.created_at,
-                c.name as channel_name, c.slug as channel_slug, c.image as channel_profile_image,
-                cat.name as category_name, cat.slug as category_slug
-         FROM videos v
-         JOIN channels c ON v.channel_id = c.id
-         JOIN categories cat ON v.category_id = cat.id
-         ORDER BY v.created_at DESC
-         LIMIT $1 OFFSET $2"
+        &format!(
+            "{}
+             ORDER BY v.created_at DESC
+             LIMIT $1 OFFSET $2",
+            VIDEO_DETAILS_SELECT
+        )
     )
     .bind(limit.unwrap_or(50))
     .bind(offset.unwrap_or(0))
@@ -136,14 +421,11 @@ pub async fn get_videos_with_details(pool: &PgPool, limit: Option<i64>, offset:
 
 pub async fn get_video_by_slug(pool: &PgPool, slug: &str) -> Result<Option<VideoWithDetails>> {
     let video = sqlx::query_as::<_, VideoWithDetails>(
-        "SELECT v.id, v.title, v.description, v.slug, v.thumbnail, v.video_file, v.duration, 
-                v.views, v.is_featured, v.created_at,
-                c.name as channel_name, c.slug as channel_slug, c.image as channel_profile_image,
-                cat.name as category_name, cat.slug as category_slug
-         FROM videos v
-         JOIN channels c ON v.channel_id = c.id
-         JOIN categories cat ON v.category_id = cat.id
-         WHERE v.slug = $1"
+        &format!(
+            "{}
+             WHERE v.slug = $1",
+            VIDEO_DETAILS_SELECT
+        )
     )
     .bind(slug)
     .fetch_optional(pool)
@@ -153,15 +435,12 @@ pub async fn get_video_by_slug(pool: &PgPool, slug: &str) -> Result<Option<Video
 
 pub async fn get_videos_by_channel(pool: &PgPool, channel_slug: &str) -> Result<Vec<VideoWithDetails>> {
     let videos = sqlx::query_as::<_, VideoWithDetails>(
-        "SELECT v.id, v.title, v.description, v.slug, v.thumbnail, v.video_file, v.duration, 
-                v.views, v.is_featured, v.created_at,
-                c.name as channel_name, c.slug as channel_slug, c.image as channel_profile_image,
-                cat.name as category_name, cat.slug as category_slug
-         FROM videos v
-         JOIN channels c ON v.channel_id = c.id
-         JOIN categories cat ON v.category_id = cat.id
-         WHERE c.slug = $1
-         ORDER BY v.created_at DESC"
+        &format!(
+            "{}
+             WHERE c.slug = $1
+             ORDER BY v.created_at DESC",
+            VIDEO_DETAILS_SELECT
+        )
     )
     .bind(channel_slug)
     .fetch_all(pool)
@@ -169,19 +448,28 @@ pub async fn get_videos_by_channel(pool: &PgPool, channel_slug: &str) -> Result<
     Ok(videos)
 }
 
-pub async fn get_videos_by_category(pool: &PgPool, category_slug: &str) -> Result<Vec<VideoWithDetails>> {
+/// Videos assigned to the category, as their primary category or an additional one, and
+/// with `include_descendants` also those assigned to any of its sub-categories.
+pub async fn get_videos_by_category(pool: &PgPool, category_slug: &str, include_descendants: bool) -> Result<Vec<VideoWithDetails>> {
     let videos = sqlx::query_as::<_, VideoWithDetails>(
-        "SELECT v.id, v.title, v.description, v.slug, v.thumbnail, v.video_file, v.duration, 
-                v.views, v.is_featured, v.created_at,
-                c.name as channel_name, c.slug as channel_slug, c.image as channel_profile_image,
-                cat.name as category_name, cat.slug as category_slug
-         FROM videos v
-         JOIN channels c ON v.channel_id = c.id
-         JOIN categories cat ON v.category_id = cat.id
-         WHERE cat.slug = $1
-         ORDER BY v.created_at DESC"
+        &format!(
+            "WITH RECURSIVE subtree(id, depth) AS (
+                 SELECT id, 0 FROM categories WHERE slug = $1
+                 UNION
+                 SELECT c.id, s.depth + 1 FROM categories c JOIN subtree s ON c.parent_id = s.id
+                 WHERE $2 AND s.depth < 64
+             )
+             {}
+             WHERE EXISTS (
+                 SELECT 1 FROM video_categories vc
+                 WHERE vc.video_id = v.id AND vc.category_id IN (SELECT id FROM subtree)
+             )
+             ORDER BY v.created_at DESC",
+            VIDEO_DETAILS_SELECT
+        )
     )
     .bind(category_slug)
+    .bind(include_descendants)
     .fetch_all(pool)
     .await?;
     Ok(videos)
@@ -189,28 +477,115 @@ pub async fn get_videos_by_category(pool: &PgPool, category_slug: &str) -> Resul
 
 pub async fn get_featured_videos(pool: &PgPool) -> Result<Vec<VideoWithDetails>> {
     let videos = sqlx::query_as::<_, VideoWithDetails>(
-        "SELECT v.id, v.title, v.description, v.slug, v.thumbnail, v.video_file, v.duration, 
-                v.views, v.is_featured, v.created_at,
-                c.name as channel_name, c.slug as channel_slug, c.image as channel_profile_image,
-                cat.name as category_name, cat.slug as category_slug
-         FROM videos v
-         JOIN channels c ON v.channel_id = c.id
-         JOIN categories cat ON v.category_id = cat.id
-         WHERE v.is_featured = true
-         ORDER BY v.created_at DESC
-         LIMIT 10"
+        &format!(
+            "{}
+             WHERE v.is_featured = true
+             ORDER BY v.created_at DESC
+             LIMIT 10",
+            VIDEO_DETAILS_SELECT
+        )
     )
     .fetch_all(pool)
     .await?;
     Ok(videos)
 }
 
-pub async fn create_video(pool: &PgPool, video: CreateVideo) -> Result<Video> {
+/// Videos ranked by their views within the last `window_hours`, each view weighted by
+/// `0.5 ^ (age / half_life_hours)` so recent views count more.
+pub async fn get_ranked_videos(pool: &PgPool, window_hours: i32, half_life_hours: f64, limit: i64) -> Result<Vec<VideoWithDetails>> {
+    let videos = sqlx::query_as::<_, VideoWithDetails>(
+        &format!(
+            "{}
+             JOIN (
+                 SELECT video_id,
+                        SUM(POWER(0.5, EXTRACT(EPOCH FROM NOW() - created_at)::DOUBLE PRECISION / ($2 * 3600.0))) AS score
+                 FROM video_view_events
+                 WHERE created_at > NOW() - make_interval(hours => $1)
+                 GROUP BY video_id
+             ) ranked ON ranked.video_id = v.id
+             ORDER BY ranked.score DESC, v.created_at DESC
+             LIMIT $3",
+            VIDEO_DETAILS_SELECT
+        )
+    )
+    .bind(window_hours)
+    .bind(half_life_hours)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+    Ok(videos)
+}
+
+/// Videos similar to the given one, scored by shared channel, categories and tags plus title
+/// trigram and full-text similarity. Copies of the same file are suggested only once.
+pub async fn get_related_videos(pool: &PgPool, video_id: Uuid, limit: i64) -> Result<Vec<VideoWithDetails>> {
+    let videos = sqlx::query_as::<_, VideoWithDetails>(
+        &format!(
+            "{}
+             JOIN (
+                 SELECT DISTINCT ON (scored.video_file) scored.id, scored.score
+                 FROM (
+                     SELECT o.id, o.video_file,
+                            CASE WHEN o.channel_id = cur.channel_id THEN 2.0 ELSE 0 END
+                            + 1.5 * (
+                                SELECT COUNT(*) FROM video_categories oc
+                                JOIN video_categories cc ON cc.category_id = oc.category_id
+                                WHERE oc.video_id = o.id AND cc.video_id = cur.id
+                            )
+                            + 1.0 * (
+                                SELECT COUNT(*) FROM video_tags ot
+                                JOIN video_tags ct ON ct.tag_id = ot.tag_id
+                                WHERE ot.video_id = o.id AND ct.video_id = cur.id
+                            )
+                            + 3.0 * similarity(o.title, cur.title)
+                            + 2.0 * COALESCE(ts_rank(o.search_vector, cur.terms), 0) AS score
+                     FROM videos o
+                     CROSS JOIN (
+                         SELECT c.id, c.channel_id, c.title,
+                                to_tsquery('simple', (
+                                    SELECT string_agg(quote_literal(lexeme), ' | ')
+                                    FROM unnest(c.search_vector)
+                                )) AS terms
+                         FROM videos c
+                         WHERE c.id = $1
+                     ) cur
+                     WHERE o.id <> cur.id
+                       AND (o.channel_id = cur.channel_id
+                            OR EXISTS (
+                                SELECT 1 FROM video_categories oc
+                                JOIN video_categories cc ON cc.category_id = oc.category_id
+                                WHERE oc.video_id = o.id AND cc.video_id = cur.id
+                            )
+                            OR EXISTS (
+                                SELECT 1 FROM video_tags ot
+                                JOIN video_tags ct ON ct.tag_id = ot.tag_id
+                                WHERE ot.video_id = o.id AND ct.video_id = cur.id
+                            )
+                            OR o.title % cur.title
+                            OR o.search_vector @@ cur.terms)
+                 ) scored
+                 ORDER BY scored.video_file, scored.score DESC
+             ) related ON related.id = v.id
+             ORDER BY related.score DESC, v.views DESC, v.created_at DESC
+             LIMIT $2",
+            VIDEO_DETAILS_SELECT
+        )
+    )
+    .bind(video_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+    Ok(videos)
+}
+
+/// Inserts a video with its categories and tags on the caller's transaction.
+pub async fn create_video(conn: &mut PgConnection, video: CreateVideo) -> Result<Video> {
     let slug = slugify(&video.title);
-    let video_file = video.video_file.unwrap_or_else(|| "default.mp4".to_string());
+    let video_file = video.video_file.clone().unwrap_or_else(|| "default.mp4".to_string());
     let new_video = sqlx::query_as::<_, Video>(
-        "INSERT INTO videos (title, description, slug, channel_id, category_id, is_featured, video_file, thumbnail, disk_id) 
-         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9) RETURNING *"
+        "INSERT INTO videos (title, description, slug, channel_id, category_id, is_featured, video_file, thumbnail, disk_id, duration, file_sha256) 
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, (SELECT sha256 FROM media_blobs WHERE kind = 'video' AND reference = $7))
+         RETURNING *"
     )
     .bind(&video.title)
     .bind(&video.description)
@@ -221,35 +596,350 @@ pub async fn create_video(pool: &PgPool, video: CreateVideo) -> Result<Video> {
     .bind(&video_file)
     .bind(&video.thumbnail)
     .bind(video.disk_id)
-    .fetch_one(pool)
+    .bind(video.duration.map(|seconds| seconds.to_string()))
+    .fetch_one(&mut *conn)
     .await?;
+    set_video_categories(conn, new_video.id, new_video.category_id, video.category_ids.as_deref()).await?;
+    if let Some(tags) = &video.tags {
+        set_video_tags(conn, new_video.id, tags).await?;
+    }
     Ok(new_video)
 }
 
-pub async fn update_video_view_count(pool: &PgPool, id: Uuid) -> Result<()> {
-    sqlx::query("UPDATE videos SET views = views + 1 WHERE id = $1")
-        .bind(id)
+/// Mirrors the primary category into `video_categories` and, when `additional` is given,
+/// replaces the video's other categories with it. Runs on the caller's transaction, together
+/// with the video write.
+async fn set_video_categories(conn: &mut PgConnection, video_id: Uuid, primary: Uuid, additional: Option<&[Uuid]>) -> Result<()> {
+    sqlx::query("DELETE FROM video_categories WHERE video_id = $1 AND is_primary AND category_id <> $2")
+        .bind(video_id)
+        .bind(primary)
+        .execute(&mut *conn)
+        .await?;
+    sqlx::query(
+        "INSERT INTO video_categories (video_id, category_id, is_primary) VALUES ($1, $2, TRUE)
+         ON CONFLICT (video_id, category_id) DO UPDATE SET is_primary = TRUE"
+    )
+    .bind(video_id)
+    .bind(primary)
+    .execute(&mut *conn)
+    .await?;
+    if let Some(additional) = additional {
+        sqlx::query(
+            "DELETE FROM video_categories
+             WHERE video_id = $1 AND NOT is_primary AND NOT category_id = ANY($2)"
+        )
+        .bind(video_id)
+        .bind(additional)
+        .execute(&mut *conn)
+        .await?;
+        sqlx::query(
+            "INSERT INTO video_categories (video_id, category_id)
+             SELECT $1, id FROM categories WHERE id = ANY($2)
+             ON CONFLICT DO NOTHING"
+        )
+        .bind(video_id)
+        .bind(additional)
+        .execute(&mut *conn)
+        .await?;
+    }
+    Ok(())
+}
+
+/// The id and stored `duration` text of a video.
+pub async fn get_video_duration_by_slug(pool: &PgPool, slug: &str) -> Result<Option<(Uuid, Option<String>)>> {
+    let video = sqlx::query_as::<_, (Uuid, Option<String>)>("SELECT id, duration FROM videos WHERE slug = $1")
+        .bind(slug)
+        .fetch_optional(pool)
+        .await?;
+    Ok(video)
+}
+
+pub async fn get_video_id_by_slug(pool: &PgPool, slug: &str) -> Result<Option<Uuid>> {
+    let id = sqlx::query_scalar::<_, Uuid>("SELECT id FROM videos WHERE slug = $1")
+        .bind(slug)
+        .fetch_optional(pool)
+        .await?;
+    Ok(id)
+}
+
+/// Records a view unless the same viewer was already counted for the video within the window.
+/// Returns whether the view was counted; the caller adds counted views to the video's total.
+pub async fn record_video_view(pool: &PgPool, video_id: Uuid, viewer_hash: &str, window_minutes: i32) -> Result<bool> {
+    let mut tx = pool.begin().await?;
+    // Serializes concurrent beacons of the same viewer so a double-fired one counts once.
+    sqlx::query("SELECT pg_advisory_xact_lock(hashtextextended($1 || ':' || $2, 0))")
+        .bind(video_id.to_string())
+        .bind(viewer_hash)
+        .execute(&mut *tx)
+        .await?;
+    let inserted = sqlx::query(
+        "INSERT INTO video_view_events (video_id, viewer_hash)
+         SELECT $1, $2
+         WHERE NOT EXISTS (
+             SELECT 1 FROM video_view_events
+             WHERE video_id = $1 AND viewer_hash = $2
+               AND created_at > NOW() - make_interval(mins => $3)
+         )"
+    )
+    .bind(video_id)
+    .bind(viewer_hash)
+    .bind(window_minutes)
+    .execute(&mut *tx)
+    .await?
+    .rows_affected() > 0;
+    tx.commit().await?;
+    Ok(inserted)
+}
+
+/// Adds batched view increments to the video totals in a single statement.
+pub async fn add_video_views(pool: &PgPool, increments: &[(Uuid, i64)]) -> Result<()> {
+    let (ids, counts): (Vec<Uuid>, Vec<i64>) = increments.iter().copied().unzip();
+    sqlx::query(
+        "UPDATE videos v SET views = COALESCE(v.views, 0) + d.count::INTEGER
+         FROM UNNEST($1::UUID[], $2::BIGINT[]) AS d(id, count)
+         WHERE v.id = d.id"
+    )
+    .bind(&ids)
+    .bind(&counts)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+/// Adds a heartbeat to its playback session, creating the session on the first one.
+/// Returns false when the session id is already taken by another video or viewer.
+///
+/// A heartbeat adds no more than `limits` allow, counted against `duration`, the video's
+/// stored length rather than the one the player reports.
+pub async fn record_playback_heartbeat(
+    pool: &PgPool,
+    video_id: Uuid,
+    viewer_hash: &str,
+    heartbeat: &PlaybackHeartbeat,
+    duration: f64,
+    buckets: i32,
+    limits: WatchTimeLimits,
+) -> Result<bool> {
+    let result = sqlx::query(
+        "INSERT INTO playback_sessions
+             (id, video_id, viewer_hash, duration_seconds, watched_seconds, max_position, watched_buckets)
+         VALUES ($1, $2, $3, $4, LEAST($5, $4 * $9), $6, $7)
+         ON CONFLICT (id) DO UPDATE SET
+             duration_seconds = EXCLUDED.duration_seconds,
+             watched_seconds = LEAST(
+                 playback_sessions.watched_seconds + LEAST(
+                     $5,
+                     EXTRACT(EPOCH FROM NOW() - playback_sessions.last_seen_at)::DOUBLE PRECISION + $8
+                 ),
+                 EXCLUDED.duration_seconds * $9
+             ),
+             max_position = GREATEST(playback_sessions.max_position, EXCLUDED.max_position),
+             watched_buckets = playback_sessions.watched_buckets | EXCLUDED.watched_buckets,
+             last_seen_at = NOW()
+         WHERE playback_sessions.video_id = EXCLUDED.video_id
+           AND playback_sessions.viewer_hash = EXCLUDED.viewer_hash"
+    )
+    .bind(heartbeat.session_id)
+    .bind(video_id)
+    .bind(viewer_hash)
+    .bind(duration)
+    .bind(heartbeat.watched)
+    .bind(heartbeat.position)
+    .bind(buckets)
+    .bind(limits.slack_seconds)
+    .bind(limits.max_plays)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Recomputes the daily stats of every video for the UTC days since `since` from view events
+/// and playback sessions. Older days keep their stored rollup after the raw rows are pruned.
+pub async fn rollup_video_daily_stats(pool: &PgPool, since: NaiveDate, bucket_count: i32) -> Result<u64> {
+    let result = sqlx::query(
+        "WITH view_days AS (
+             SELECT video_id, (created_at AT TIME ZONE 'UTC')::DATE AS day,
+                    COUNT(*) AS views, COUNT(DISTINCT viewer_hash) AS unique_viewers
+             FROM video_view_events
+             WHERE created_at >= $1::TIMESTAMP AT TIME ZONE 'UTC'
+             GROUP BY 1, 2
+         ),
+         session_days AS (
+             SELECT video_id, (started_at AT TIME ZONE 'UTC')::DATE AS day, watched_seconds, watched_buckets
+             FROM playback_sessions
+             WHERE started_at >= $1::TIMESTAMP AT TIME ZONE 'UTC'
+         ),
+         session_totals AS (
+             SELECT video_id, day, COUNT(*) AS sessions, SUM(watched_seconds) AS watch_seconds
+             FROM session_days
+             GROUP BY 1, 2
+         ),
+         bucket_plays AS (
+             SELECT s.video_id, s.day, b.bucket,
+                    COUNT(*) FILTER (WHERE s.watched_buckets & (1 << b.bucket) <> 0) AS plays
+             FROM session_days s CROSS JOIN generate_series(0, $2 - 1) AS b(bucket)
+             GROUP BY 1, 2, 3
+         ),
+         retention AS (
+             SELECT video_id, day, array_agg(plays::INTEGER ORDER BY bucket) AS retention, SUM(plays) AS plays
+             FROM bucket_plays
+             GROUP BY 1, 2
+         )
+         INSERT INTO video_daily_stats
+             (video_id, day, views, unique_viewers, sessions, watch_seconds, avg_percent_watched, retention, updated_at)
+         SELECT COALESCE(v.video_id, t.video_id), COALESCE(v.day, t.day),
+                COALESCE(v.views, 0), COALESCE(v.unique_viewers, 0),
+                COALESCE(t.sessions, 0), COALESCE(t.watch_seconds, 0),
+                COALESCE(r.plays * 100.0 / ($2 * t.sessions), 0),
+                COALESCE(r.retention, '{}'), NOW()
+         FROM view_days v
+         FULL JOIN session_totals t ON t.video_id = v.video_id AND t.day = v.day
+         LEFT JOIN retention r ON r.video_id = t.video_id AND r.day = t.day
+         ON CONFLICT (video_id, day) DO UPDATE SET
+             views = EXCLUDED.views,
+             unique_viewers = EXCLUDED.unique_viewers,
+             sessions = EXCLUDED.sessions,
+             watch_seconds = EXCLUDED.watch_seconds,
+             avg_percent_watched = EXCLUDED.avg_percent_watched,
+             retention = EXCLUDED.retention,
+             updated_at = NOW()"
+    )
+    .bind(since)
+    .bind(bucket_count)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+pub async fn get_video_daily_stats(pool: &PgPool, video_id: Uuid, from: NaiveDate, to: NaiveDate) -> Result<Vec<VideoDailyStats>> {
+    let stats = sqlx::query_as::<_, VideoDailyStats>(
+        "SELECT video_id, day, views, unique_viewers, sessions, watch_seconds, avg_percent_watched, retention
+         FROM video_daily_stats
+         WHERE video_id = $1 AND day BETWEEN $2 AND $3
+         ORDER BY day"
+    )
+    .bind(video_id)
+    .bind(from)
+    .bind(to)
+    .fetch_all(pool)
+    .await?;
+    Ok(stats)
+}
+
+pub async fn prune_playback_sessions(pool: &PgPool, keep_days: i32) -> Result<u64> {
+    let result = sqlx::query(
+        "DELETE FROM playback_sessions WHERE started_at < NOW() - make_interval(days => $1)"
+    )
+    .bind(keep_days)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+/// Grouping of the channel and category rollups: the rollup column and the table it refers to.
+#[derive(Clone, Copy, Debug)]
+pub enum RollupGroup {
+    Channel,
+    Category,
+}
+
+impl RollupGroup {
+    fn column_and_table(self) -> (&'static str, &'static str) {
+        match self {
+            RollupGroup::Channel => ("channel_id", "channels"),
+            RollupGroup::Category => ("category_id", "categories"),
+        }
+    }
+}
+
+pub async fn refresh_content_rollups(pool: &PgPool) -> Result<()> {
+    sqlx::query("REFRESH MATERIALIZED VIEW CONCURRENTLY content_daily_rollup")
+        .execute(pool)
+        .await?;
+    sqlx::query("REFRESH MATERIALIZED VIEW CONCURRENTLY content_storage_rollup")
         .execute(pool)
         .await?;
     Ok(())
 }
 
-pub async fn update_video(pool: &PgPool, id: Uuid, video: CreateVideo) -> Result<Video> {
+pub async fn get_content_rollups(pool: &PgPool, group: RollupGroup, from: NaiveDate, to: NaiveDate) -> Result<Vec<ContentRollup>> {
+    let (column, table) = group.column_and_table();
+    let rollups = sqlx::query_as::<_, ContentRollup>(
+        &format!(
+            "SELECT g.id, g.name, g.slug,
+                    COALESCE(s.videos, 0)::BIGINT AS videos,
+                    COALESCE(s.views, 0)::BIGINT AS total_views,
+                    COALESCE(s.storage_bytes, 0)::BIGINT AS storage_bytes,
+                    COALESCE(d.uploads, 0)::BIGINT AS uploads,
+                    COALESCE(d.views, 0)::BIGINT AS views
+             FROM {table} g
+             LEFT JOIN (
+                 SELECT {column} AS id, SUM(videos) AS videos, SUM(views) AS views, SUM(storage_bytes) AS storage_bytes
+                 FROM content_storage_rollup
+                 GROUP BY 1
+             ) s ON s.id = g.id
+             LEFT JOIN (
+                 SELECT {column} AS id, SUM(uploads) AS uploads, SUM(views) AS views
+                 FROM content_daily_rollup
+                 WHERE day BETWEEN $1 AND $2
+                 GROUP BY 1
+             ) d ON d.id = g.id
+             ORDER BY views DESC, g.name"
+        )
+    )
+    .bind(from)
+    .bind(to)
+    .fetch_all(pool)
+    .await?;
+    Ok(rollups)
+}
+
+pub async fn get_weekly_uploads(pool: &PgPool, group: RollupGroup, from: NaiveDate, to: NaiveDate) -> Result<Vec<WeeklyUploads>> {
+    let (column, _) = group.column_and_table();
+    let weeks = sqlx::query_as::<_, WeeklyUploads>(
+        &format!(
+            "SELECT {column} AS id, date_trunc('week', day)::DATE AS week_start, SUM(uploads)::BIGINT AS uploads
+             FROM content_daily_rollup
+             WHERE day BETWEEN $1 AND $2 AND uploads > 0
+             GROUP BY 1, 2
+             ORDER BY 1, 2"
+        )
+    )
+    .bind(from)
+    .bind(to)
+    .fetch_all(pool)
+    .await?;
+    Ok(weeks)
+}
+
+pub async fn prune_video_view_events(pool: &PgPool, keep_days: i32) -> Result<u64> {
+    let result = sqlx::query(
+        "DELETE FROM video_view_events WHERE created_at < NOW() - make_interval(days => $1)"
+    )
+    .bind(keep_days)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+/// Updates a video with its categories and tags on the caller's transaction.
+pub async fn update_video(conn: &mut PgConnection, id: Uuid, video: CreateVideo) -> Result<Video> {
     let slug = slugify(&video.title);
-    let mut query = "UPDATE videos SET title = $1, description = $2, slug = $3, channel_id = $4, category_id = $5, is_featured = $6, updated_at = NOW()";
-    let mut bind_count = 6;
+    let mut query = "UPDATE videos SET title = $1, description = $2, slug = $3, channel_id = $4, category_id = $5, is_featured = $6, duration = COALESCE($7, duration), updated_at = NOW()";
+    let mut bind_count = 7;
     
+    // The checksum is the one computed when the file was uploaded, never the client's.
     if video.video_file.is_some() {
         bind_count += 1;
-        query = "UPDATE videos SET title = $1, description = $2, slug = $3, channel_id = $4, category_id = $5, is_featured = $6, video_file = $7, updated_at = NOW()";
+        query = "UPDATE videos SET title = $1, description = $2, slug = $3, channel_id = $4, category_id = $5, is_featured = $6, duration = COALESCE($7, duration), video_file = $8, file_sha256 = (SELECT sha256 FROM media_blobs WHERE kind = 'video' AND reference = $8), updated_at = NOW()";
     }
     
     if video.thumbnail.is_some() {
         bind_count += 1;
         if video.video_file.is_some() {
-            query = "UPDATE videos SET title = $1, description = $2, slug = $3, channel_id = $4, category_id = $5, is_featured = $6, video_file = $7, thumbnail = $8, updated_at = NOW()";
+            query = "UPDATE videos SET title = $1, description = $2, slug = $3, channel_id = $4, category_id = $5, is_featured = $6, duration = COALESCE($7, duration), video_file = $8, file_sha256 = (SELECT sha256 FROM media_blobs WHERE kind = 'video' AND reference = $8), thumbnail = $9, updated_at = NOW()";
         } else {
-            query = "UPDATE videos SET title = $1, description = $2, slug = $3, channel_id = $4, category_id = $5, is_featured = $6, thumbnail = $7, updated_at = NOW()";
+            query = "UPDATE videos SET title = $1, description = $2, slug = $3, channel_id = $4, category_id = $5, is_featured = $6, duration = COALESCE($7, duration), thumbnail = $8, updated_at = NOW()";
         }
     }
     
@@ -261,7 +951,8 @@ pub async fn update_video(pool: &PgPool, id: Uuid, video: CreateVideo) -> Result
         .bind(&slug)
         .bind(video.channel_id)
         .bind(video.category_id)
-        .bind(video.is_featured.unwrap_or(false));
+        .bind(video.is_featured.unwrap_or(false))
+        .bind(video.duration.map(|seconds| seconds.to_string()));
     
     if video.video_file.is_some() {
         query_builder = query_builder.bind(&video.video_file);
@@ -273,22 +964,26 @@ pub async fn update_video(pool: &PgPool, id: Uuid, video: CreateVideo) -> Result
     
     let updated_video = query_builder
         .bind(id)
-        .fetch_one(pool)
+        .fetch_one(&mut *conn)
         .await?;
+    set_video_categories(conn, id, updated_video.category_id, video.category_ids.as_deref()).await?;
+    if let Some(tags) = &video.tags {
+        set_video_tags(conn, id, tags).await?;
+    }
     Ok(updated_video)
 }
 
-pub async fn delete_video(pool: &PgPool, id: Uuid) -> Result<()> {
-    sqlx::query("DELETE FROM videos WHERE id = $1")
+pub async fn delete_video<'e>(executor: impl PgExecutor<'e>, id: Uuid) -> Result<Option<Video>> {
+    let video = sqlx::query_as::<_, Video>("DELETE FROM videos WHERE id = $1 RETURNING *")
         .bind(id)
-        .execute(pool)
+        .fetch_optional(executor)
         .await?;
-    Ok(())
+    Ok(video)
 }
 
 pub async fn get_available_disk(pool: &PgPool) -> Result<Option<DiskStorage>> {
     let disk = sqlx::query_as::<_, DiskStorage>(
-        "SELECT * FROM disk_storage WHERE is_active = true AND used_space < total_space ORDER BY used_space ASC LIMIT 1"
+        "SELECT * FROM disk_storage WHERE is_active = true AND is_draining = false AND used_space < total_space ORDER BY used_space ASC LIMIT 1"
     )
     .fetch_optional(pool)
     .await?;
@@ -341,9 +1036,766 @@ pub async fn update_disk_storage(pool: &PgPool, id: Uuid, disk: CreateDiskStorag
 }
 
 pub async fn delete_disk_storage(pool: &PgPool, id: Uuid) -> Result<()> {
-    sqlx::query("DELETE FROM disk_storage WHERE id = $1")
+    let result = sqlx::query(
+        "DELETE FROM disk_storage WHERE id = $1
+         AND NOT EXISTS (SELECT 1 FROM videos WHERE disk_id = $1)
+         AND NOT EXISTS (SELECT 1 FROM media_replicas WHERE disk_id = $1)"
+    )
         .bind(id)
         .execute(pool)
         .await?;
+    if result.rows_affected() == 0 {
+        anyhow::bail!("disk {} not found or still referenced by videos", id);
+    }
     Ok(())
-}
\ No newline at end of file
+}
+
+pub async fn get_disk_by_id(pool: &PgPool, id: Uuid) -> Result<Option<DiskStorage>> {
+    let disk = sqlx::query_as::<_, DiskStorage>(
+        "SELECT * FROM disk_storage WHERE id = $1"
+    )
+    .bind(id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(disk)
+}
+
+pub async fn count_videos_on_disk(pool: &PgPool, disk_id: Uuid) -> Result<i64> {
+    let (count,) = sqlx::query_as::<_, (i64,)>(
+        "SELECT (SELECT COUNT(*) FROM videos WHERE disk_id = $1)
+              + (SELECT COUNT(*) FROM media_replicas WHERE disk_id = $1)"
+    )
+    .bind(disk_id)
+    .fetch_one(pool)
+    .await?;
+    Ok(count)
+}
+
+pub async fn get_videos_on_disk(pool: &PgPool, disk_id: Uuid) -> Result<Vec<Video>> {
+    let videos = sqlx::query_as::<_, Video>(
+        "SELECT * FROM videos WHERE disk_id = $1 ORDER BY created_at ASC"
+    )
+    .bind(disk_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(videos)
+}
+
+pub async fn get_draining_disks(pool: &PgPool) -> Result<Vec<DiskStorage>> {
+    let disks = sqlx::query_as::<_, DiskStorage>(
+        "SELECT * FROM disk_storage WHERE is_draining = true"
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(disks)
+}
+
+pub async fn set_disk_draining(pool: &PgPool, id: Uuid, is_draining: bool) -> Result<DiskStorage> {
+    let disk = sqlx::query_as::<_, DiskStorage>(
+        "UPDATE disk_storage SET is_draining = $1, updated_at = NOW() WHERE id = $2 RETURNING *"
+    )
+    .bind(is_draining)
+    .bind(id)
+    .fetch_one(pool)
+    .await?;
+    Ok(disk)
+}
+
+/// Points every video stored as `video_file` on one disk at a new disk and moves the file's size
+/// between the two disks' usage counters. Deduplicated videos share a file, so they move together.
+///
+/// Returns false, changing nothing, when no video is stored there any more.
+pub async fn move_video_to_disk(pool: &PgPool, video_file: &str, from_disk: Uuid, to_disk: Uuid, size_bytes: i64) -> Result<bool> {
+    let mut tx = pool.begin().await?;
+    let moved = sqlx::query("UPDATE videos SET disk_id = $1, updated_at = NOW() WHERE video_file = $2 AND disk_id = $3")
+        .bind(to_disk)
+        .bind(video_file)
+        .bind(from_disk)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+    if moved == 0 {
+        return Ok(false);
+    }
+    sqlx::query("UPDATE media_blobs SET disk_id = $1, updated_at = NOW() WHERE kind = 'video' AND reference = $2 AND disk_id = $3")
+        .bind(to_disk)
+        .bind(video_file)
+        .bind(from_disk)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("UPDATE disk_storage SET used_space = GREATEST(used_space - $1, 0) WHERE id = $2")
+        .bind(size_bytes)
+        .bind(from_disk)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("UPDATE disk_storage SET used_space = used_space + $1 WHERE id = $2")
+        .bind(size_bytes)
+        .bind(to_disk)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(true)
+}
+
+/// Stores a health check result and takes the disk out of (or back into) rotation.
+///
+/// Only disks that were deactivated by a failed health check are re-activated when they
+/// recover; disks an admin switched off stay off.
+pub async fn record_disk_health_check(pool: &PgPool, check: &CreateDiskHealthCheck) -> Result<DiskStorage> {
+    let mut tx = pool.begin().await?;
+
+    sqlx::query(
+        "INSERT INTO disk_health_checks (disk_id, status, is_mounted, is_writable, total_space, available_space, message)
+         VALUES ($1, $2, $3, $4, $5, $6, $7)"
+    )
+    .bind(check.disk_id)
+    .bind(&check.status)
+    .bind(check.is_mounted)
+    .bind(check.is_writable)
+    .bind(check.total_space)
+    .bind(check.available_space)
+    .bind(&check.message)
+    .execute(&mut *tx)
+    .await?;
+
+    let disk = if check.status == "unhealthy" {
+        sqlx::query_as::<_, DiskStorage>(
+            "UPDATE disk_storage
+             SET health_status = $1, last_checked_at = NOW(),
+                 disabled_by_health = disabled_by_health OR is_active,
+                 is_active = false
+             WHERE id = $2 RETURNING *"
+        )
+    } else {
+        sqlx::query_as::<_, DiskStorage>(
+            "UPDATE disk_storage
+             SET health_status = $1, last_checked_at = NOW(),
+                 is_active = is_active OR disabled_by_health,
+                 disabled_by_health = false
+             WHERE id = $2 RETURNING *"
+        )
+    }
+    .bind(&check.status)
+    .bind(check.disk_id)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    tx.commit().await?;
+    Ok(disk)
+}
+
+pub async fn get_disk_health_history(pool: &PgPool, disk_id: Uuid, limit: i64) -> Result<Vec<DiskHealthCheck>> {
+    let checks = sqlx::query_as::<_, DiskHealthCheck>(
+        "SELECT * FROM disk_health_checks WHERE disk_id = $1 ORDER BY checked_at DESC LIMIT $2"
+    )
+    .bind(disk_id)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+    Ok(checks)
+}
+
+pub async fn prune_disk_health_history(pool: &PgPool, keep_days: i32) -> Result<u64> {
+    let result = sqlx::query(
+        "DELETE FROM disk_health_checks WHERE checked_at < NOW() - make_interval(days => $1)"
+    )
+    .bind(keep_days)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+pub async fn get_video_by_id(pool: &PgPool, id: Uuid) -> Result<Option<Video>> {
+    let video = sqlx::query_as::<_, Video>(
+        "SELECT * FROM videos WHERE id = $1"
+    )
+    .bind(id)
+    .fetch_optional(pool)
+    .await?;
+    Ok(video)
+}
+
+/// Reads a video and locks its row until the transaction ends, so concurrent updates see each
+/// other's media references.
+pub async fn lock_video<'e>(executor: impl PgExecutor<'e>, id: Uuid) -> Result<Option<Video>> {
+    let video = sqlx::query_as::<_, Video>("SELECT * FROM videos WHERE id = $1 FOR UPDATE")
+        .bind(id)
+        .fetch_optional(executor)
+        .await?;
+    Ok(video)
+}
+
+pub async fn get_media_blob(pool: &PgPool, kind: &str, reference: &str) -> Result<Option<MediaBlob>> {
+    let blob = sqlx::query_as::<_, MediaBlob>(
+        "SELECT * FROM media_blobs WHERE kind = $1 AND reference = $2"
+    )
+    .bind(kind)
+    .bind(reference)
+    .fetch_optional(pool)
+    .await?;
+    Ok(blob)
+}
+
+/// Video files that are stored on a disk and still used by at least one video.
+pub async fn get_stored_video_blobs(pool: &PgPool) -> Result<Vec<MediaBlob>> {
+    let blobs = sqlx::query_as::<_, MediaBlob>(
+        "SELECT * FROM media_blobs WHERE kind = 'video' AND disk_id IS NOT NULL AND ref_count > 0
+         ORDER BY created_at ASC"
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(blobs)
+}
+
+pub async fn get_media_replicas(pool: &PgPool, blob_id: Uuid) -> Result<Vec<MediaReplica>> {
+    let replicas = sqlx::query_as::<_, MediaReplica>(
+        "SELECT * FROM media_replicas WHERE blob_id = $1 ORDER BY created_at ASC"
+    )
+    .bind(blob_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(replicas)
+}
+
+/// Returns (replica id, file name) for every replica stored on a disk.
+pub async fn get_replicas_on_disk(pool: &PgPool, disk_id: Uuid) -> Result<Vec<(Uuid, String)>> {
+    let replicas = sqlx::query_as::<_, (Uuid, String)>(
+        "SELECT r.id, b.reference FROM media_replicas r
+         JOIN media_blobs b ON b.id = r.blob_id
+         WHERE r.disk_id = $1 ORDER BY r.created_at ASC"
+    )
+    .bind(disk_id)
+    .fetch_all(pool)
+    .await?;
+    Ok(replicas)
+}
+
+/// Claims a disk for a new replica before its file is written, so concurrent writers never pick
+/// the same target. Returns None when the disk already holds or is receiving a copy.
+pub async fn reserve_media_replica(pool: &PgPool, blob_id: Uuid, disk_id: Uuid, size_bytes: i64) -> Result<Option<MediaReplica>> {
+    let mut tx = pool.begin().await?;
+    let replica = sqlx::query_as::<_, MediaReplica>(
+        "INSERT INTO media_replicas (blob_id, disk_id) VALUES ($1, $2)
+         ON CONFLICT (blob_id, disk_id) DO NOTHING RETURNING *"
+    )
+    .bind(blob_id)
+    .bind(disk_id)
+    .fetch_optional(&mut *tx)
+    .await?;
+    if replica.is_some() {
+        sqlx::query("UPDATE disk_storage SET used_space = used_space + $1 WHERE id = $2")
+            .bind(size_bytes)
+            .bind(disk_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+    Ok(replica)
+}
+
+pub async fn delete_media_replica(pool: &PgPool, id: Uuid, size_bytes: i64) -> Result<()> {
+    let mut tx = pool.begin().await?;
+    let disk_id = sqlx::query_as::<_, (Uuid,)>(
+        "DELETE FROM media_replicas WHERE id = $1 RETURNING disk_id"
+    )
+    .bind(id)
+    .fetch_optional(&mut *tx)
+    .await?;
+    if let Some((disk_id,)) = disk_id {
+        sqlx::query("UPDATE disk_storage SET used_space = GREATEST(used_space - $1, 0) WHERE id = $2")
+            .bind(size_bytes)
+            .bind(disk_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Makes a replica the file's primary copy, for the blob and every video using it; the former
+/// primary disk becomes a replica.
+pub async fn promote_media_replica(pool: &PgPool, blob_id: Uuid, replica_id: Uuid) -> Result<()> {
+    let mut tx = pool.begin().await?;
+    let (reference, old_primary, new_primary) = sqlx::query_as::<_, (String, Option<Uuid>, Uuid)>(
+        "SELECT b.reference, b.disk_id, r.disk_id FROM media_blobs b JOIN media_replicas r ON r.blob_id = b.id
+         WHERE b.id = $1 AND r.id = $2 FOR UPDATE"
+    )
+    .bind(blob_id)
+    .bind(replica_id)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    sqlx::query("UPDATE media_blobs SET disk_id = $1, updated_at = NOW() WHERE id = $2")
+        .bind(new_primary)
+        .bind(blob_id)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("UPDATE videos SET disk_id = $1, updated_at = NOW() WHERE video_file = $2")
+        .bind(new_primary)
+        .bind(&reference)
+        .execute(&mut *tx)
+        .await?;
+    match old_primary {
+        Some(old_primary) => {
+            sqlx::query("UPDATE media_replicas SET disk_id = $1 WHERE id = $2")
+                .bind(old_primary)
+                .bind(replica_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+        None => {
+            sqlx::query("DELETE FROM media_replicas WHERE id = $1")
+                .bind(replica_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+    }
+    tx.commit().await?;
+    Ok(())
+}
+
+/// Picks the least used active disk that does not already hold a copy of the video file.
+pub async fn get_available_disk_for_replica(pool: &PgPool, video_file: &str, required_space: i64) -> Result<Option<DiskStorage>> {
+    let disk = sqlx::query_as::<_, DiskStorage>(
+        "SELECT * FROM disk_storage ds
+         WHERE ds.is_active = true AND ds.is_draining = false AND ds.total_space - ds.used_space >= $2
+           AND NOT EXISTS (SELECT 1 FROM videos v WHERE v.video_file = $1 AND v.disk_id = ds.id)
+           AND NOT EXISTS (
+               SELECT 1 FROM media_replicas r JOIN media_blobs b ON b.id = r.blob_id
+               WHERE b.kind = 'video' AND b.reference = $1 AND r.disk_id = ds.id
+           )
+         ORDER BY ds.used_space ASC LIMIT 1"
+    )
+    .bind(video_file)
+    .bind(required_space)
+    .fetch_optional(pool)
+    .await?;
+    Ok(disk)
+}
+
+/// Returns the paths of every replica of a video file stored on an active disk.
+pub async fn get_replica_paths(pool: &PgPool, video_file: &str) -> Result<Vec<String>> {
+    let paths = sqlx::query_as::<_, (String,)>(
+        "SELECT ds.path FROM media_replicas r
+         JOIN media_blobs b ON r.blob_id = b.id
+         JOIN disk_storage ds ON r.disk_id = ds.id
+         WHERE b.kind = 'video' AND b.reference = $1 AND ds.is_active = true
+         ORDER BY ds.used_space ASC"
+    )
+    .bind(video_file)
+    .fetch_all(pool)
+    .await?;
+    Ok(paths.into_iter().map(|(path,)| path).collect())
+}
+
+/// Like `move_video_to_disk`, for a replica; returns false when the replica is not on `from_disk`.
+pub async fn move_replica_to_disk(pool: &PgPool, replica_id: Uuid, from_disk: Uuid, to_disk: Uuid, size_bytes: i64) -> Result<bool> {
+    let mut tx = pool.begin().await?;
+    let moved = sqlx::query("UPDATE media_replicas SET disk_id = $1 WHERE id = $2 AND disk_id = $3")
+        .bind(to_disk)
+        .bind(replica_id)
+        .bind(from_disk)
+        .execute(&mut *tx)
+        .await?
+        .rows_affected();
+    if moved == 0 {
+        return Ok(false);
+    }
+    sqlx::query("UPDATE disk_storage SET used_space = GREATEST(used_space - $1, 0) WHERE id = $2")
+        .bind(size_bytes)
+        .bind(from_disk)
+        .execute(&mut *tx)
+        .await?;
+    sqlx::query("UPDATE disk_storage SET used_space = used_space + $1 WHERE id = $2")
+        .bind(size_bytes)
+        .bind(to_disk)
+        .execute(&mut *tx)
+        .await?;
+    tx.commit().await?;
+    Ok(true)
+}
+
+/// Locks a channel's row until the transaction ends; see `lock_video`.
+pub async fn lock_channel<'e>(executor: impl PgExecutor<'e>, id: Uuid) -> Result<Option<Channel>> {
+    let channel = sqlx::query_as::<_, Channel>("SELECT * FROM channels WHERE id = $1 FOR UPDATE")
+        .bind(id)
+        .fetch_optional(executor)
+        .await?;
+    Ok(channel)
+}
+
+/// Locks a category's row until the transaction ends; see `lock_video`.
+pub async fn lock_category<'e>(executor: impl PgExecutor<'e>, id: Uuid) -> Result<Option<Category>> {
+    let category = sqlx::query_as::<_, Category>("SELECT * FROM categories WHERE id = $1 FOR UPDATE")
+        .bind(id)
+        .fetch_optional(executor)
+        .await?;
+    Ok(category)
+}
+
+/// Deletes a channel's videos ahead of the channel, returning them so their media can be
+/// released in the same transaction.
+pub async fn delete_videos_by_channel_id<'e>(executor: impl PgExecutor<'e>, channel_id: Uuid) -> Result<Vec<Video>> {
+    let videos = sqlx::query_as::<_, Video>(
+        "DELETE FROM videos WHERE channel_id = $1 RETURNING *"
+    )
+    .bind(channel_id)
+    .fetch_all(executor)
+    .await?;
+    Ok(videos)
+}
+
+/// Deletes the videos whose primary category is `category_id`; see `delete_videos_by_channel_id`.
+pub async fn delete_videos_by_category_id<'e>(executor: impl PgExecutor<'e>, category_id: Uuid) -> Result<Vec<Video>> {
+    let videos = sqlx::query_as::<_, Video>(
+        "DELETE FROM videos WHERE category_id = $1 RETURNING *"
+    )
+    .bind(category_id)
+    .fetch_all(executor)
+    .await?;
+    Ok(videos)
+}
+
+/// Registers a newly stored file, or returns the existing blob with the same content.
+pub async fn create_media_blob(
+    pool: &PgPool,
+    kind: &str,
+    sha256: &str,
+    reference: &str,
+    disk_id: Option<Uuid>,
+    size_bytes: i64,
+) -> Result<MediaBlob> {
+    let blob = sqlx::query_as::<_, MediaBlob>(
+        "INSERT INTO media_blobs (kind, sha256, reference, disk_id, size_bytes)
+         VALUES ($1, $2, $3, $4, $5)
+         ON CONFLICT (kind, sha256) DO UPDATE SET updated_at = NOW()
+         RETURNING *"
+    )
+    .bind(kind)
+    .bind(sha256)
+    .bind(reference)
+    .bind(disk_id)
+    .bind(size_bytes)
+    .fetch_one(pool)
+    .await?;
+    Ok(blob)
+}
+
+/// Records the sniffed MIME type; identical content always has the same type, so a blob that
+/// already has one keeps it.
+pub async fn set_media_blob_mime_type(pool: &PgPool, kind: &str, sha256: &str, mime_type: &str) -> Result<()> {
+    sqlx::query(
+        "UPDATE media_blobs SET mime_type = $3 WHERE kind = $1 AND sha256 = $2 AND mime_type IS NULL"
+    )
+    .bind(kind)
+    .bind(sha256)
+    .bind(mime_type)
+    .execute(pool)
+    .await?;
+    Ok(())
+}
+
+pub async fn get_media_mime_type(pool: &PgPool, kind: &str, reference: &str) -> Result<Option<String>> {
+    let mime_type = sqlx::query_scalar::<_, Option<String>>(
+        "SELECT mime_type FROM media_blobs WHERE kind = $1 AND reference = $2"
+    )
+    .bind(kind)
+    .bind(reference)
+    .fetch_optional(pool)
+    .await?;
+    Ok(mime_type.flatten())
+}
+
+pub async fn delete_media_blob(pool: &PgPool, id: Uuid) -> Result<()> {
+    sqlx::query("DELETE FROM media_blobs WHERE id = $1")
+        .bind(id)
+        .execute(pool)
+        .await?;
+    Ok(())
+}
+
+pub async fn acquire_media_blob<'e>(executor: impl PgExecutor<'e>, kind: &str, reference: &str) -> Result<()> {
+    sqlx::query(
+        "UPDATE media_blobs SET ref_count = ref_count + 1, updated_at = NOW() WHERE kind = $1 AND reference = $2"
+    )
+    .bind(kind)
+    .bind(reference)
+    .execute(executor)
+    .await?;
+    Ok(())
+}
+
+/// Drops one reference to a blob on the caller's transaction. Returns the blob and its replicas
+/// if that was the last reference, in which case the rows have been removed and the caller is
+/// responsible for deleting the bytes once the transaction commits.
+pub async fn release_media_blob(
+    conn: &mut PgConnection,
+    kind: &str,
+    reference: &str,
+) -> Result<Option<(MediaBlob, Vec<MediaReplica>)>> {
+    let blob = sqlx::query_as::<_, MediaBlob>(
+        "UPDATE media_blobs SET ref_count = GREATEST(ref_count - 1, 0), updated_at = NOW()
+         WHERE kind = $1 AND reference = $2 RETURNING *"
+    )
+    .bind(kind)
+    .bind(reference)
+    .fetch_optional(&mut *conn)
+    .await?;
+
+    let freed = match blob {
+        Some(blob) if blob.ref_count == 0 => {
+            // Replica rows go with the blob row, so they are read first.
+            let replicas = sqlx::query_as::<_, MediaReplica>("SELECT * FROM media_replicas WHERE blob_id = $1")
+                .bind(blob.id)
+                .fetch_all(&mut *conn)
+                .await?;
+            sqlx::query("DELETE FROM media_blobs WHERE id = $1")
+                .bind(blob.id)
+                .execute(&mut *conn)
+                .await?;
+            if let Some(disk_id) = blob.disk_id {
+                sqlx::query("UPDATE disk_storage SET used_space = GREATEST(used_space - $1, 0) WHERE id = $2")
+                    .bind(blob.size_bytes)
+                    .bind(disk_id)
+                    .execute(&mut *conn)
+                    .await?;
+            }
+            Some((blob, replicas))
+        }
+        _ => None,
+    };
+    Ok(freed)
+}
+
+/// Removes blobs that were uploaded but never referenced by any row.
+pub async fn take_unreferenced_media_blobs(pool: &PgPool, older_than_hours: i32) -> Result<Vec<MediaBlob>> {
+    let mut tx = pool.begin().await?;
+    let blobs = sqlx::query_as::<_, MediaBlob>(
+        "DELETE FROM media_blobs
+         WHERE ref_count = 0 AND updated_at < NOW() - make_interval(hours => $1)
+         RETURNING *"
+    )
+    .bind(older_than_hours)
+    .fetch_all(&mut *tx)
+    .await?;
+    for blob in &blobs {
+        if let Some(disk_id) = blob.disk_id {
+            sqlx::query("UPDATE disk_storage SET used_space = GREATEST(used_space - $1, 0) WHERE id = $2")
+                .bind(blob.size_bytes)
+                .bind(disk_id)
+                .execute(&mut *tx)
+                .await?;
+        }
+    }
+    tx.commit().await?;
+    Ok(blobs)
+}
+
+/// Creates an upload session, or returns the existing one when the client resends its id.
+pub async fn get_or_create_upload_session(pool: &PgPool, session: &CreateUploadSession) -> Result<UploadSession> {
+    let bitmap = vec![0u8; (session.total_chunks.max(0) as usize).div_ceil(8)];
+    let session = sqlx::query_as::<_, UploadSession>(
+        "INSERT INTO upload_sessions
+            (id, owner, kind, protocol, file_name, total_size, total_chunks, received_chunks, file_sha256, expires_at)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, NOW() + make_interval(hours => $10))
+         ON CONFLICT (id) DO UPDATE
+            SET file_sha256 = COALESCE(upload_sessions.file_sha256, EXCLUDED.file_sha256),
+                updated_at = NOW()
+         RETURNING *"
+    )
+    .bind(&session.id)
+    .bind(&session.owner)
+    .bind(&session.kind)
+    .bind(session.protocol)
+    .bind(&session.file_name)
+    .bind(session.total_size)
+    .bind(session.total_chunks)
+    .bind(bitmap)
+    .bind(&session.file_sha256)
+    .bind(session.expires_in_hours)
+    .fetch_one(pool)
+    .await?;
+    Ok(session)
+}
+
+pub async fn get_upload_session(pool: &PgPool, id: &str) -> Result<Option<UploadSession>> {
+    let session = sqlx::query_as::<_, UploadSession>("SELECT * FROM upload_sessions WHERE id = $1")
+        .bind(id)
+        .fetch_optional(pool)
+        .await?;
+    Ok(session)
+}
+
+/// Locks an uploading session's row until `executor`'s transaction ends, so a chunk can be moved
+/// into place and recorded without racing the switch to `finalizing`.
+pub async fn lock_uploading_session<'e>(executor: impl PgExecutor<'e>, id: &str) -> Result<Option<UploadSession>> {
+    let session = sqlx::query_as::<_, UploadSession>(
+        "SELECT * FROM upload_sessions WHERE id = $1 AND status = 'uploading' FOR UPDATE"
+    )
+    .bind(id)
+    .fetch_optional(executor)
+    .await?;
+    Ok(session)
+}
+
+/// Sets the chunk's bit, counting its bytes only the first time it arrives.
+pub async fn mark_upload_chunk_received<'e>(
+    executor: impl PgExecutor<'e>,
+    id: &str,
+    chunk_number: u32,
+    size: i64,
+) -> Result<UploadSession> {
+    let session = sqlx::query_as::<_, UploadSession>(
+        "UPDATE upload_sessions
+         SET received_chunks = set_bit(received_chunks, $2, 1),
+             received_bytes = received_bytes + CASE WHEN get_bit(received_chunks, $2) = 0 THEN $3 ELSE 0 END,
+             updated_at = NOW()
+         WHERE id = $1 AND status = 'uploading'
+         RETURNING *"
+    )
+    .bind(id)
+    .bind(chunk_number as i32 - 1)
+    .bind(size)
+    .fetch_one(executor)
+    .await?;
+    Ok(session)
+}
+
+/// Advances a streamed upload's offset if nobody else moved it since `from`.
+pub async fn advance_upload_offset<'e>(executor: impl PgExecutor<'e>, id: &str, from: i64, to: i64) -> Result<Option<UploadSession>> {
+    let session = sqlx::query_as::<_, UploadSession>(
+        "UPDATE upload_sessions SET received_bytes = $3, updated_at = NOW()
+         WHERE id = $1 AND status = 'uploading' AND received_bytes = $2
+         RETURNING *"
+    )
+    .bind(id)
+    .bind(from)
+    .bind(to)
+    .fetch_optional(executor)
+    .await?;
+    Ok(session)
+}
+
+pub async fn set_upload_session_status<'e>(
+    executor: impl PgExecutor<'e>,
+    id: &str,
+    from: &str,
+    to: &str,
+) -> Result<bool> {
+    let result = sqlx::query(
+        "UPDATE upload_sessions SET status = $3, updated_at = NOW() WHERE id = $1 AND status = $2"
+    )
+    .bind(id)
+    .bind(from)
+    .bind(to)
+    .execute(executor)
+    .await?;
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn complete_upload_session(
+    pool: &PgPool,
+    id: &str,
+    file_reference: &str,
+    disk_id: Option<Uuid>,
+    file_sha256: &str,
+) -> Result<UploadSession> {
+    let session = sqlx::query_as::<_, UploadSession>(
+        "UPDATE upload_sessions
+         SET status = 'completed', file_reference = $2, disk_id = $3, file_sha256 = $4, updated_at = NOW()
+         WHERE id = $1
+         RETURNING *"
+    )
+    .bind(id)
+    .bind(file_reference)
+    .bind(disk_id)
+    .bind(file_sha256)
+    .fetch_one(pool)
+    .await?;
+    Ok(session)
+}
+
+/// Marks unfinished sessions past their expiry as expired and returns them.
+pub async fn take_expired_upload_sessions(pool: &PgPool) -> Result<Vec<UploadSession>> {
+    let sessions = sqlx::query_as::<_, UploadSession>(
+        "UPDATE upload_sessions SET status = 'expired', updated_at = NOW()
+         WHERE status IN ('uploading', 'finalizing') AND expires_at < NOW()
+         RETURNING *"
+    )
+    .fetch_all(pool)
+    .await?;
+    Ok(sessions)
+}
+
+pub async fn prune_upload_sessions(pool: &PgPool, older_than_days: i32) -> Result<u64> {
+    let result = sqlx::query(
+        "DELETE FROM upload_sessions
+         WHERE status NOT IN ('uploading', 'finalizing')
+           AND updated_at < NOW() - make_interval(days => $1)"
+    )
+    .bind(older_than_days)
+    .execute(pool)
+    .await?;
+    Ok(result.rows_affected())
+}
+
+/// Lists upload sessions, newest first. `statuses` and `owner` narrow the result when given.
+pub async fn list_upload_sessions(
+    pool: &PgPool,
+    statuses: Option<Vec<String>>,
+    owner: Option<&str>,
+    limit: i64,
+) -> Result<Vec<UploadSession>> {
+    let sessions = sqlx::query_as::<_, UploadSession>(
+        "SELECT * FROM upload_sessions
+         WHERE ($1::text[] IS NULL OR status = ANY($1))
+           AND ($2::text IS NULL OR owner = $2)
+         ORDER BY created_at DESC
+         LIMIT $3"
+    )
+    .bind(statuses)
+    .bind(owner)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+    Ok(sessions)
+}
+
+pub async fn create_image_variant(pool: &PgPool, variant: &CreateImageVariant) -> Result<ImageVariant> {
+    let variant = sqlx::query_as::<_, ImageVariant>(
+        "INSERT INTO image_variants (source_reference, width, height, mime_type, reference, size_bytes)
+         VALUES ($1, $2, $3, $4, $5, $6)
+         ON CONFLICT (source_reference, width, mime_type) DO UPDATE
+            SET height = EXCLUDED.height, reference = EXCLUDED.reference, size_bytes = EXCLUDED.size_bytes
+         RETURNING *"
+    )
+    .bind(&variant.source_reference)
+    .bind(variant.width)
+    .bind(variant.height)
+    .bind(&variant.mime_type)
+    .bind(&variant.reference)
+    .bind(variant.size_bytes)
+    .fetch_one(pool)
+    .await?;
+    Ok(variant)
+}
+
+pub async fn get_image_variants(pool: &PgPool, source_reference: &str) -> Result<Vec<ImageVariant>> {
+    let variants = sqlx::query_as::<_, ImageVariant>(
+        "SELECT * FROM image_variants WHERE source_reference = $1 ORDER BY mime_type, width"
+    )
+    .bind(source_reference)
+    .fetch_all(pool)
+    .await?;
+    Ok(variants)
+}
+
+pub async fn delete_image_variants(pool: &PgPool, source_reference: &str) -> Result<Vec<ImageVariant>> {
+    let variants = sqlx::query_as::<_, ImageVariant>(
+        "DELETE FROM image_variants WHERE source_reference = $1 RETURNING *"
+    )
+    .bind(source_reference)
+    .fetch_all(pool)
+    .await?;
+    Ok(variants)
+}
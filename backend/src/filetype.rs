@@ -0,0 +1,172 @@
+use std::path::Path;
+use tokio::{fs, io::AsyncReadExt};
+
+/// How many leading bytes are inspected; enough for the Matroska doc type and MP4 brand.
+const SNIFF_LEN: usize = 64;
+
+/// A file format recognised from its leading bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FileType {
+    pub mime_type: &'static str,
+    pub extension: &'static str,
+}
+
+pub const MP4: FileType = FileType { mime_type: "video/mp4", extension: "mp4" };
+pub const QUICKTIME: FileType = FileType { mime_type: "video/quicktime", extension: "mov" };
+pub const WEBM: FileType = FileType { mime_type: "video/webm", extension: "webm" };
+pub const MATROSKA: FileType = FileType { mime_type: "video/x-matroska", extension: "mkv" };
+pub const JPEG: FileType = FileType { mime_type: "image/jpeg", extension: "jpg" };
+pub const PNG: FileType = FileType { mime_type: "image/png", extension: "png" };
+pub const WEBP: FileType = FileType { mime_type: "image/webp", extension: "webp" };
+
+pub const VIDEO_TYPES: &[FileType] = &[MP4, QUICKTIME, WEBM, MATROSKA];
+pub const IMAGE_TYPES: &[FileType] = &[JPEG, PNG, WEBP];
+
+/// ISO base media brands of MP4 video.
+const MP4_BRANDS: &[&[u8]] = &[b"isom", b"iso2", b"mp41", b"mp42", b"avc1", b"dash", b"M4V "];
+
+/// Major brands of files that use the MP4 container for something other than video; they list
+/// MP4 brands as compatible, so they are ruled out first.
+const NON_VIDEO_BRANDS: &[&[u8]] = &[
+    b"heic", b"heix", b"hevc", b"mif1", b"msf1", b"avif", b"avis", b"M4A ", b"M4B ", b"M4P ",
+];
+
+/// Tells MP4 and QuickTime apart from other `ftyp` files (HEIC, AVIF, M4A, 3GP) by the major
+/// brand, falling back to the compatible brands that follow the minor version.
+fn detect_ftyp(header: &[u8]) -> Option<FileType> {
+    let major = &header[8..12];
+    if major == b"qt  " {
+        return Some(QUICKTIME);
+    }
+    if MP4_BRANDS.contains(&major) {
+        return Some(MP4);
+    }
+    if NON_VIDEO_BRANDS.contains(&major) || major.starts_with(b"3g") {
+        return None;
+    }
+
+    // A size below 16 is 0 (box runs to the end of the file) or 1 (64-bit size follows).
+    let box_size = u32::from_be_bytes([header[0], header[1], header[2], header[3]]) as usize;
+    let end = if box_size >= 16 { box_size.min(header.len()) } else { header.len() };
+    let compatible = header.get(16..end).unwrap_or_default();
+    compatible
+        .chunks_exact(4)
+        .any(|brand| MP4_BRANDS.contains(&brand))
+        .then_some(MP4)
+}
+
+/// Identifies a file format from its magic bytes.
+pub fn detect(header: &[u8]) -> Option<FileType> {
+    if header.len() >= 12 && &header[4..8] == b"ftyp" {
+        return detect_ftyp(header);
+    }
+    if header.starts_with(&[0x1A, 0x45, 0xDF, 0xA3]) {
+        // EBML container; the DocType element tells WebM from other Matroska files.
+        return Some(if contains(header, b"webm") { WEBM } else { MATROSKA });
+    }
+    if header.starts_with(&[0xFF, 0xD8, 0xFF]) {
+        return Some(JPEG);
+    }
+    if header.starts_with(&[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A]) {
+        return Some(PNG);
+    }
+    if header.len() >= 12 && header.starts_with(b"RIFF") && &header[8..12] == b"WEBP" {
+        return Some(WEBP);
+    }
+    None
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|window| window == needle)
+}
+
+/// Reads the start of a file and identifies its format.
+pub async fn detect_file(path: &Path) -> std::io::Result<Option<FileType>> {
+    let mut file = fs::File::open(path).await?;
+    let mut header = [0u8; SNIFF_LEN];
+    let mut filled = 0;
+    while filled < SNIFF_LEN {
+        let read = file.read(&mut header[filled..]).await?;
+        if read == 0 {
+            break;
+        }
+        filled += read;
+    }
+    Ok(detect(&header[..filled]))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ftyp(major: &[u8; 4], compatible: &[&[u8; 4]]) -> Vec<u8> {
+        let size = 16 + 4 * compatible.len() as u32;
+        let mut header = size.to_be_bytes().to_vec();
+        header.extend_from_slice(b"ftyp");
+        header.extend_from_slice(major);
+        header.extend_from_slice(&[0, 0, 2, 0]);
+        for brand in compatible {
+            header.extend_from_slice(*brand);
+        }
+        header
+    }
+
+    #[test]
+    fn detects_mp4_brands() {
+        for brand in MP4_BRANDS {
+            let major: &[u8; 4] = (*brand).try_into().unwrap();
+            assert_eq!(detect(&ftyp(major, &[b"isom"])), Some(MP4));
+        }
+        assert_eq!(detect(&ftyp(b"XAVC", &[b"XAVC", b"mp42", b"iso2"])), Some(MP4));
+    }
+
+    #[test]
+    fn detects_quicktime() {
+        assert_eq!(detect(&ftyp(b"qt  ", &[b"qt  "])), Some(QUICKTIME));
+        assert!(VIDEO_TYPES.contains(&QUICKTIME));
+    }
+
+    #[test]
+    fn rejects_non_video_ftyp_files() {
+        assert_eq!(detect(&ftyp(b"heic", &[b"mif1", b"heic"])), None);
+        assert_eq!(detect(&ftyp(b"avif", &[b"avif", b"mif1", b"miaf"])), None);
+        assert_eq!(detect(&ftyp(b"M4A ", &[b"M4A ", b"mp42", b"isom"])), None);
+        assert_eq!(detect(&ftyp(b"3gp4", &[b"isom", b"3gp4"])), None);
+        assert_eq!(detect(&ftyp(b"crx ", &[b"crx "])), None);
+    }
+
+    #[test]
+    fn ignores_brands_past_the_ftyp_box() {
+        let mut header = ftyp(b"abcd", &[b"abcd"]);
+        header.extend_from_slice(&[0, 0, 0, 8]);
+        header.extend_from_slice(b"isom");
+        assert_eq!(detect(&header), None);
+    }
+
+    #[test]
+    fn detects_matroska_and_webm() {
+        let mut webm = vec![0x1A, 0x45, 0xDF, 0xA3, 0x9F, 0x42, 0x82, 0x84];
+        webm.extend_from_slice(b"webm");
+        assert_eq!(detect(&webm), Some(WEBM));
+
+        let mut mkv = vec![0x1A, 0x45, 0xDF, 0xA3, 0x9F, 0x42, 0x82, 0x88];
+        mkv.extend_from_slice(b"matroska");
+        assert_eq!(detect(&mkv), Some(MATROSKA));
+    }
+
+    #[test]
+    fn detects_images() {
+        assert_eq!(detect(&[0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x10]), Some(JPEG));
+        assert_eq!(detect(&[0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, 0x00]), Some(PNG));
+        assert_eq!(detect(b"RIFF\x24\x00\x00\x00WEBPVP8 "), Some(WEBP));
+    }
+
+    #[test]
+    fn rejects_unknown_and_truncated_headers() {
+        assert_eq!(detect(b""), None);
+        assert_eq!(detect(b"\x00\x00\x00\x18ftyp"), None);
+        assert_eq!(detect(b"RIFF\x24\x00\x00\x00WAVEfmt "), None);
+        assert_eq!(detect(&[0xFF, 0xD8]), None);
+        assert_eq!(detect(b"<html><body>"), None);
+    }
+}
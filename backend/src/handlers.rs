@@ -4,13 +4,20 @@ use axum::{
     Json, response::Response,
 };
 use serde::Deserialize;
-use sqlx::PgPool;
+use sqlx::{PgConnection, PgPool};
 use uuid::Uuid;
 use tokio::fs;
 use std::path::PathBuf;
 use crate::{
     database::*,
+    filetype,
+    media::{
+        self, banner_reference, channel_image_reference, release_media, release_video_media, remove_freed_media,
+        replace_media, retain_media, retain_video_media, FreedMedia,
+    },
     models::*,
+    replication::{replicate_video, ReplicationConfig},
+    storage::{drain_disk, scan_mounts, sha256_file},
 };
 
 #[derive(Deserialize)]
@@ -19,6 +26,14 @@ pub struct PaginationQuery {
     pub limit: Option<u32>,
 }
 
+/// Releases the media of videos deleted on the same connection.
+async fn release_deleted_media(conn: &mut PgConnection, deleted: &[Video], freed: &mut Vec<FreedMedia>) -> anyhow::Result<()> {
+    for video in deleted {
+        release_video_media(conn, video, freed).await?;
+    }
+    Ok(())
+}
+
 pub async fn get_videos_handler(
     State(pool): State<PgPool>,
     Query(params): Query<PaginationQuery>,
@@ -38,17 +53,35 @@ pub async fn get_video_handler(
     Path(slug): Path<String>,
 ) -> Result<Json<ApiResponse<VideoWithDetails>>, StatusCode> {
     match get_video_by_slug(&pool, &slug).await {
-        Ok(Some(video)) => {
-            if let Err(_) = update_video_view_count(&pool, video.id).await {
-                tracing::warn!("Failed to update view count for video: {}", video.id);
-            }
-            Ok(Json(ApiResponse::success(video)))
-        }
+        Ok(Some(video)) => Ok(Json(ApiResponse::success(video))),
         Ok(None) => Ok(Json(ApiResponse::error("Video bulunamadı".to_string()))),
         Err(_) => Ok(Json(ApiResponse::error("Video alınamadı".to_string()))),
     }
 }
 
+#[derive(Deserialize)]
+pub struct RelatedVideosQuery {
+    pub limit: Option<i64>,
+}
+
+/// Suggestions for what to watch after a video, best match first.
+pub async fn get_related_videos_handler(
+    State(pool): State<PgPool>,
+    Path(slug): Path<String>,
+    Query(params): Query<RelatedVideosQuery>,
+) -> Result<Json<ApiResponse<Vec<VideoWithDetails>>>, StatusCode> {
+    let video_id = match get_video_id_by_slug(&pool, &slug).await {
+        Ok(Some(id)) => id,
+        Ok(None) => return Ok(Json(ApiResponse::error("Video bulunamadı".to_string()))),
+        Err(_) => return Ok(Json(ApiResponse::error("Video alınamadı".to_string()))),
+    };
+    let limit = params.limit.unwrap_or(12).clamp(1, 50);
+    match get_related_videos(&pool, video_id, limit).await {
+        Ok(videos) => Ok(Json(ApiResponse::success(videos))),
+        Err(_) => Ok(Json(ApiResponse::error("İlgili videolar alınamadı".to_string()))),
+    }
+}
+
 pub async fn get_featured_videos_handler(
     State(pool): State<PgPool>,
 ) -> Result<Json<ApiResponse<Vec<VideoWithDetails>>>, StatusCode> {
@@ -62,8 +95,27 @@ pub async fn create_video_handler(
     State(pool): State<PgPool>,
     Json(video_data): Json<CreateVideo>,
 ) -> Result<Json<ApiResponse<Video>>, StatusCode> {
-    match create_video(&pool, video_data).await {
-        Ok(video) => Ok(Json(ApiResponse::success(video))),
+    let created = async {
+        let mut tx = pool.begin().await?;
+        let video = create_video(&mut tx, video_data).await?;
+        retain_video_media(&mut tx, &video).await?;
+        tx.commit().await?;
+        anyhow::Ok(video)
+    };
+    match created.await {
+        Ok(video) => {
+            let config = ReplicationConfig::from_env();
+            if config.is_enabled() && video.disk_id.is_some() {
+                let replication_pool = pool.clone();
+                let video_id = video.id;
+                tokio::spawn(async move {
+                    if let Err(e) = replicate_video(&replication_pool, video_id, &config).await {
+                        tracing::error!("Failed to replicate video {}: {}", video_id, e);
+                    }
+                });
+            }
+            Ok(Json(ApiResponse::success(video)))
+        }
         Err(_) => Ok(Json(ApiResponse::error("Video oluşturulamadı".to_string()))),
     }
 }
@@ -73,8 +125,21 @@ pub async fn update_video_handler(
     Path(id): Path<Uuid>,
     Json(video_data): Json<CreateVideo>,
 ) -> Result<Json<ApiResponse<Video>>, StatusCode> {
-    match update_video(&pool, id, video_data).await {
-        Ok(video) => Ok(Json(ApiResponse::success(video))),
+    let updated = async {
+        let mut tx = pool.begin().await?;
+        let previous = lock_video(&mut *tx, id).await?.ok_or_else(|| anyhow::anyhow!("video {} not found", id))?;
+        let video = update_video(&mut tx, id, video_data).await?;
+        let mut freed = Vec::new();
+        replace_media(&mut tx, media::VIDEO, Some(&previous.video_file), Some(&video.video_file), &mut freed).await?;
+        replace_media(&mut tx, media::THUMBNAIL, previous.thumbnail.as_deref(), video.thumbnail.as_deref(), &mut freed).await?;
+        tx.commit().await?;
+        anyhow::Ok((video, freed))
+    };
+    match updated.await {
+        Ok((video, freed)) => {
+            remove_freed_media(&pool, freed).await;
+            Ok(Json(ApiResponse::success(video)))
+        }
         Err(_) => Ok(Json(ApiResponse::error("Video güncellenemedi".to_string()))),
     }
 }
@@ -83,12 +148,54 @@ pub async fn delete_video_handler(
     State(pool): State<PgPool>,
     Path(id): Path<Uuid>,
 ) -> Result<Json<ApiResponse<String>>, StatusCode> {
-    match delete_video(&pool, id).await {
-        Ok(_) => Ok(Json(ApiResponse::success("Video silindi".to_string()))),
+    let deleted = async {
+        let mut tx = pool.begin().await?;
+        let videos: Vec<Video> = delete_video(&mut *tx, id).await?.into_iter().collect();
+        let mut freed = Vec::new();
+        release_deleted_media(&mut tx, &videos, &mut freed).await?;
+        tx.commit().await?;
+        anyhow::Ok(freed)
+    };
+    match deleted.await {
+        Ok(freed) => {
+            remove_freed_media(&pool, freed).await;
+            Ok(Json(ApiResponse::success("Video silindi".to_string())))
+        }
         Err(_) => Ok(Json(ApiResponse::error("Video silinemedi".to_string()))),
     }
 }
 
+pub async fn verify_video_handler(
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<VideoIntegrityReport>>, StatusCode> {
+    let video = match get_video_by_id(&pool, id).await {
+        Ok(Some(video)) => video,
+        Ok(None) => return Ok(Json(ApiResponse::error("Video bulunamadı".to_string()))),
+        Err(_) => return Ok(Json(ApiResponse::error("Video alınamadı".to_string()))),
+    };
+
+    let video_path = match get_video_file_path(&pool, &video.video_file).await {
+        Ok(Some(path)) => path,
+        Ok(None) => return Ok(Json(ApiResponse::error("Video dosyası bulunamadı".to_string()))),
+        Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR),
+    };
+
+    match sha256_file(&video_path).await {
+        Ok(actual_sha256) => {
+            let matches = video.file_sha256.as_deref()
+                .is_some_and(|expected| expected.eq_ignore_ascii_case(&actual_sha256));
+            Ok(Json(ApiResponse::success(VideoIntegrityReport {
+                video_id: video.id,
+                expected_sha256: video.file_sha256,
+                actual_sha256,
+                matches,
+            })))
+        }
+        Err(_) => Ok(Json(ApiResponse::error("Video dosyası okunamadı".to_string()))),
+    }
+}
+
 pub async fn get_channels_handler(
     State(pool): State<PgPool>,
 ) -> Result<Json<ApiResponse<Vec<Channel>>>, StatusCode> {
@@ -119,11 +226,59 @@ pub async fn get_channel_videos_handler(
     }
 }
 
+/// Checks the display fields channels and categories share, returning the message to show.
+fn invalid_display_fields(
+    color: Option<&str>,
+    icon: Option<&str>,
+    seo_title: Option<&str>,
+    seo_description: Option<&str>,
+) -> Option<&'static str> {
+    let is_hex_color = |color: &str| {
+        color.len() == 7 && color.starts_with('#') && color[1..].chars().all(|c| c.is_ascii_hexdigit())
+    };
+    let longer_than = |value: Option<&str>, max: usize| value.is_some_and(|value| value.chars().count() > max);
+    if color.is_some_and(|color| !is_hex_color(color)) {
+        Some("Renk #rrggbb biçiminde olmalıdır")
+    } else if longer_than(icon, 50) {
+        Some("İkon adı en fazla 50 karakter olabilir")
+    } else if longer_than(seo_title, 70) {
+        Some("SEO başlığı en fazla 70 karakter olabilir")
+    } else if longer_than(seo_description, 160) {
+        Some("SEO açıklaması en fazla 160 karakter olabilir")
+    } else {
+        None
+    }
+}
+
+/// Rejects empty orders and ids listed twice, which would leave positions ambiguous.
+fn is_valid_order(ids: &[Uuid]) -> bool {
+    let unique: std::collections::HashSet<&Uuid> = ids.iter().collect();
+    !ids.is_empty() && unique.len() == ids.len()
+}
+
 pub async fn create_channel_handler(
     State(pool): State<PgPool>,
     Json(channel_data): Json<CreateChannel>,
 ) -> Result<Json<ApiResponse<Channel>>, StatusCode> {
-    match create_channel(&pool, channel_data).await {
+    if let Some(message) = invalid_display_fields(
+        channel_data.color.as_deref(),
+        None,
+        channel_data.seo_title.as_deref(),
+        channel_data.seo_description.as_deref(),
+    ) {
+        return Ok(Json(ApiResponse::error(message.to_string())));
+    }
+    let created = async {
+        let mut tx = pool.begin().await?;
+        let channel = create_channel(&mut *tx, channel_data).await?;
+        let image = channel.image.as_deref().map(channel_image_reference);
+        retain_media(&mut tx, media::CHANNEL_IMAGE, image.as_deref()).await?;
+        let banner = channel.banner.as_deref().map(banner_reference);
+        retain_media(&mut tx, media::BANNER, banner.as_deref()).await?;
+        tx.commit().await?;
+        anyhow::Ok(channel)
+    };
+    match created.await {
         Ok(channel) => Ok(Json(ApiResponse::success(channel))),
         Err(_) => Ok(Json(ApiResponse::error("Kanal oluşturulamadı".to_string()))),
     }
@@ -134,8 +289,33 @@ pub async fn update_channel_handler(
     Path(id): Path<Uuid>,
     Json(channel_data): Json<CreateChannel>,
 ) -> Result<Json<ApiResponse<Channel>>, StatusCode> {
-    match update_channel(&pool, id, channel_data).await {
-        Ok(channel) => Ok(Json(ApiResponse::success(channel))),
+    if let Some(message) = invalid_display_fields(
+        channel_data.color.as_deref(),
+        None,
+        channel_data.seo_title.as_deref(),
+        channel_data.seo_description.as_deref(),
+    ) {
+        return Ok(Json(ApiResponse::error(message.to_string())));
+    }
+    let updated = async {
+        let mut tx = pool.begin().await?;
+        let previous = lock_channel(&mut *tx, id).await?.ok_or_else(|| anyhow::anyhow!("channel {} not found", id))?;
+        let channel = update_channel(&mut *tx, id, channel_data).await?;
+        let mut freed = Vec::new();
+        let old_image = previous.image.as_deref().map(channel_image_reference);
+        let new_image = channel.image.as_deref().map(channel_image_reference);
+        replace_media(&mut tx, media::CHANNEL_IMAGE, old_image.as_deref(), new_image.as_deref(), &mut freed).await?;
+        let old_banner = previous.banner.as_deref().map(banner_reference);
+        let new_banner = channel.banner.as_deref().map(banner_reference);
+        replace_media(&mut tx, media::BANNER, old_banner.as_deref(), new_banner.as_deref(), &mut freed).await?;
+        tx.commit().await?;
+        anyhow::Ok((channel, freed))
+    };
+    match updated.await {
+        Ok((channel, freed)) => {
+            remove_freed_media(&pool, freed).await;
+            Ok(Json(ApiResponse::success(channel)))
+        }
         Err(_) => Ok(Json(ApiResponse::error("Kanal güncellenemedi".to_string()))),
     }
 }
@@ -144,17 +324,86 @@ pub async fn delete_channel_handler(
     State(pool): State<PgPool>,
     Path(id): Path<Uuid>,
 ) -> Result<Json<ApiResponse<String>>, StatusCode> {
-    match delete_channel(&pool, id).await {
-        Ok(_) => Ok(Json(ApiResponse::success("Kanal silindi".to_string()))),
+    let deleted = async {
+        let mut tx = pool.begin().await?;
+        let videos = delete_videos_by_channel_id(&mut *tx, id).await?;
+        let channel = delete_channel(&mut *tx, id).await?;
+        let mut freed = Vec::new();
+        release_deleted_media(&mut tx, &videos, &mut freed).await?;
+        if let Some(channel) = channel {
+            let image = channel.image.as_deref().map(channel_image_reference);
+            release_media(&mut tx, media::CHANNEL_IMAGE, image.as_deref(), &mut freed).await?;
+            let banner = channel.banner.as_deref().map(banner_reference);
+            release_media(&mut tx, media::BANNER, banner.as_deref(), &mut freed).await?;
+        }
+        tx.commit().await?;
+        anyhow::Ok(freed)
+    };
+    match deleted.await {
+        Ok(freed) => {
+            remove_freed_media(&pool, freed).await;
+            Ok(Json(ApiResponse::success("Kanal silindi".to_string())))
+        }
         Err(_) => Ok(Json(ApiResponse::error("Kanal silinemedi".to_string()))),
     }
 }
 
+/// Sets the navigation order of channels to the given list.
+pub async fn reorder_channels_handler(
+    State(pool): State<PgPool>,
+    Json(order): Json<ReorderRequest>,
+) -> Result<Json<ApiResponse<String>>, StatusCode> {
+    if !is_valid_order(&order.ids) {
+        return Ok(Json(ApiResponse::error("Geçersiz sıralama".to_string())));
+    }
+    match reorder_channels(&pool, &order.ids).await {
+        Ok(_) => Ok(Json(ApiResponse::success("Kanal sırası güncellendi".to_string()))),
+        Err(_) => Ok(Json(ApiResponse::error("Kanal sırası güncellenemedi".to_string()))),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct CategoriesQuery {
+    pub tree: Option<bool>,
+}
+
+/// Nests categories under their parents, keeping the order they were listed in.
+fn build_category_tree(categories: Vec<Category>) -> Vec<CategoryNode> {
+    let ids: std::collections::HashSet<Uuid> = categories.iter().map(|category| category.id).collect();
+    let mut children: std::collections::HashMap<Option<Uuid>, Vec<Category>> = std::collections::HashMap::new();
+    for category in categories {
+        // A parent missing from the list would hide the whole branch, so list it at the top.
+        let parent = category.parent_id.filter(|parent| ids.contains(parent));
+        children.entry(parent).or_default().push(category);
+    }
+
+    fn attach(
+        parent: Option<Uuid>,
+        children: &mut std::collections::HashMap<Option<Uuid>, Vec<Category>>,
+    ) -> Vec<CategoryNode> {
+        children
+            .remove(&parent)
+            .unwrap_or_default()
+            .into_iter()
+            .map(|category| {
+                let nested = attach(Some(category.id), children);
+                CategoryNode { category, children: nested }
+            })
+            .collect()
+    }
+    attach(None, &mut children)
+}
+
+/// All categories as a flat list, or nested with `?tree=true`.
 pub async fn get_categories_handler(
     State(pool): State<PgPool>,
-) -> Result<Json<ApiResponse<Vec<Category>>>, StatusCode> {
+    Query(params): Query<CategoriesQuery>,
+) -> Result<Json<ApiResponse<CategoryList>>, StatusCode> {
     match get_categories(&pool).await {
-        Ok(categories) => Ok(Json(ApiResponse::success(categories))),
+        Ok(categories) if params.tree.unwrap_or(false) => {
+            Ok(Json(ApiResponse::success(CategoryList::Tree(build_category_tree(categories)))))
+        }
+        Ok(categories) => Ok(Json(ApiResponse::success(CategoryList::Flat(categories)))),
         Err(_) => Ok(Json(ApiResponse::error("Kategoriler alınamadı".to_string()))),
     }
 }
@@ -162,29 +411,60 @@ pub async fn get_categories_handler(
 pub async fn get_category_handler(
     State(pool): State<PgPool>,
     Path(slug): Path<String>,
-) -> Result<Json<ApiResponse<Category>>, StatusCode> {
-    match get_category_by_slug(&pool, &slug).await {
-        Ok(Some(category)) => Ok(Json(ApiResponse::success(category))),
-        Ok(None) => Ok(Json(ApiResponse::error("Kategori bulunamadı".to_string()))),
+) -> Result<Json<ApiResponse<CategoryDetails>>, StatusCode> {
+    let category = match get_category_by_slug(&pool, &slug).await {
+        Ok(Some(category)) => category,
+        Ok(None) => return Ok(Json(ApiResponse::error("Kategori bulunamadı".to_string()))),
+        Err(_) => return Ok(Json(ApiResponse::error("Kategori alınamadı".to_string()))),
+    };
+    match get_category_breadcrumbs(&pool, category.id).await {
+        Ok(breadcrumbs) => Ok(Json(ApiResponse::success(CategoryDetails { category, breadcrumbs }))),
         Err(_) => Ok(Json(ApiResponse::error("Kategori alınamadı".to_string()))),
     }
 }
 
+#[derive(Deserialize)]
+pub struct CategoryVideosQuery {
+    /// Also list videos of sub-categories, at any depth.
+    pub descendants: Option<bool>,
+}
+
 pub async fn get_category_videos_handler(
     State(pool): State<PgPool>,
     Path(slug): Path<String>,
+    Query(params): Query<CategoryVideosQuery>,
 ) -> Result<Json<ApiResponse<Vec<VideoWithDetails>>>, StatusCode> {
-    match get_videos_by_category(&pool, &slug).await {
+    match get_videos_by_category(&pool, &slug, params.descendants.unwrap_or(false)).await {
         Ok(videos) => Ok(Json(ApiResponse::success(videos))),
         Err(_) => Ok(Json(ApiResponse::error("Kategori videoları alınamadı".to_string()))),
     }
 }
 
+fn invalid_category_fields(category: &CreateCategory) -> Option<&'static str> {
+    invalid_display_fields(
+        category.color.as_deref(),
+        category.icon.as_deref(),
+        category.seo_title.as_deref(),
+        category.seo_description.as_deref(),
+    )
+}
+
 pub async fn create_category_handler(
     State(pool): State<PgPool>,
     Json(category_data): Json<CreateCategory>,
 ) -> Result<Json<ApiResponse<Category>>, StatusCode> {
-    match create_category(&pool, category_data).await {
+    if let Some(message) = invalid_category_fields(&category_data) {
+        return Ok(Json(ApiResponse::error(message.to_string())));
+    }
+    let created = async {
+        let mut tx = pool.begin().await?;
+        let category = create_category(&mut *tx, category_data).await?;
+        let banner = category.banner.as_deref().map(banner_reference);
+        retain_media(&mut tx, media::BANNER, banner.as_deref()).await?;
+        tx.commit().await?;
+        anyhow::Ok(category)
+    };
+    match created.await {
         Ok(category) => Ok(Json(ApiResponse::success(category))),
         Err(_) => Ok(Json(ApiResponse::error("Kategori oluşturulamadı".to_string()))),
     }
@@ -195,8 +475,31 @@ pub async fn update_category_handler(
     Path(id): Path<Uuid>,
     Json(category_data): Json<CreateCategory>,
 ) -> Result<Json<ApiResponse<Category>>, StatusCode> {
-    match update_category(&pool, id, category_data).await {
-        Ok(category) => Ok(Json(ApiResponse::success(category))),
+    if let Some(message) = invalid_category_fields(&category_data) {
+        return Ok(Json(ApiResponse::error(message.to_string())));
+    }
+    let updated = async {
+        let mut tx = pool.begin().await?;
+        let previous = lock_category(&mut *tx, id).await?.ok_or_else(|| anyhow::anyhow!("category {} not found", id))?;
+        let category = match update_category(&mut tx, id, category_data).await? {
+            Some(category) => category,
+            None => return anyhow::Ok(None),
+        };
+        let mut freed = Vec::new();
+        let old_banner = previous.banner.as_deref().map(banner_reference);
+        let new_banner = category.banner.as_deref().map(banner_reference);
+        replace_media(&mut tx, media::BANNER, old_banner.as_deref(), new_banner.as_deref(), &mut freed).await?;
+        tx.commit().await?;
+        anyhow::Ok(Some((category, freed)))
+    };
+    match updated.await {
+        Ok(None) => Ok(Json(ApiResponse::error(
+            "Kategori kendisinin veya alt kategorisinin altına taşınamaz".to_string(),
+        ))),
+        Ok(Some((category, freed))) => {
+            remove_freed_media(&pool, freed).await;
+            Ok(Json(ApiResponse::success(category)))
+        }
         Err(_) => Ok(Json(ApiResponse::error("Kategori güncellenemedi".to_string()))),
     }
 }
@@ -205,12 +508,115 @@ pub async fn delete_category_handler(
     State(pool): State<PgPool>,
     Path(id): Path<Uuid>,
 ) -> Result<Json<ApiResponse<String>>, StatusCode> {
-    match delete_category(&pool, id).await {
-        Ok(_) => Ok(Json(ApiResponse::success("Kategori silindi".to_string()))),
+    let deleted = async {
+        let mut tx = pool.begin().await?;
+        let videos = delete_videos_by_category_id(&mut *tx, id).await?;
+        let category = delete_category(&mut *tx, id).await?;
+        let mut freed = Vec::new();
+        release_deleted_media(&mut tx, &videos, &mut freed).await?;
+        if let Some(category) = category {
+            let banner = category.banner.as_deref().map(banner_reference);
+            release_media(&mut tx, media::BANNER, banner.as_deref(), &mut freed).await?;
+        }
+        tx.commit().await?;
+        anyhow::Ok(freed)
+    };
+    match deleted.await {
+        Ok(freed) => {
+            remove_freed_media(&pool, freed).await;
+            Ok(Json(ApiResponse::success("Kategori silindi".to_string())))
+        }
         Err(_) => Ok(Json(ApiResponse::error("Kategori silinemedi".to_string()))),
     }
 }
 
+/// Sets the navigation order of the children of one parent category. The list must hold every
+/// one of them, or every top-level category; a partial list is rejected.
+pub async fn reorder_categories_handler(
+    State(pool): State<PgPool>,
+    Json(order): Json<ReorderRequest>,
+) -> Result<Json<ApiResponse<String>>, StatusCode> {
+    if !is_valid_order(&order.ids) {
+        return Ok(Json(ApiResponse::error("Geçersiz sıralama".to_string())));
+    }
+    match reorder_categories(&pool, &order.ids).await {
+        Ok(_) => Ok(Json(ApiResponse::success("Kategori sırası güncellendi".to_string()))),
+        Err(_) => Ok(Json(ApiResponse::error("Kategori sırası güncellenemedi".to_string()))),
+    }
+}
+
+pub async fn get_tags_handler(
+    State(pool): State<PgPool>,
+) -> Result<Json<ApiResponse<Vec<Tag>>>, StatusCode> {
+    match get_tags(&pool).await {
+        Ok(tags) => Ok(Json(ApiResponse::success(tags))),
+        Err(_) => Ok(Json(ApiResponse::error("Etiketler alınamadı".to_string()))),
+    }
+}
+
+pub async fn get_tag_handler(
+    State(pool): State<PgPool>,
+    Path(slug): Path<String>,
+) -> Result<Json<ApiResponse<Tag>>, StatusCode> {
+    match get_tag_by_slug(&pool, &slug).await {
+        Ok(Some(tag)) => Ok(Json(ApiResponse::success(tag))),
+        Ok(None) => Ok(Json(ApiResponse::error("Etiket bulunamadı".to_string()))),
+        Err(_) => Ok(Json(ApiResponse::error("Etiket alınamadı".to_string()))),
+    }
+}
+
+pub async fn get_tag_videos_handler(
+    State(pool): State<PgPool>,
+    Path(slug): Path<String>,
+) -> Result<Json<ApiResponse<Vec<VideoWithDetails>>>, StatusCode> {
+    match get_videos_by_tag(&pool, &slug).await {
+        Ok(videos) => Ok(Json(ApiResponse::success(videos))),
+        Err(_) => Ok(Json(ApiResponse::error("Etiket videoları alınamadı".to_string()))),
+    }
+}
+
+/// Tags are addressed by slug, so a name must keep some letters or digits once slugified.
+fn is_valid_tag_name(name: &str) -> bool {
+    !slug::slugify(name).is_empty()
+}
+
+pub async fn create_tag_handler(
+    State(pool): State<PgPool>,
+    Json(tag_data): Json<CreateTag>,
+) -> Result<Json<ApiResponse<Tag>>, StatusCode> {
+    if !is_valid_tag_name(&tag_data.name) {
+        return Ok(Json(ApiResponse::error("Etiket adı harf veya rakam içermeli".to_string())));
+    }
+    match create_tag(&pool, tag_data).await {
+        Ok(tag) => Ok(Json(ApiResponse::success(tag))),
+        Err(_) => Ok(Json(ApiResponse::error("Etiket oluşturulamadı".to_string()))),
+    }
+}
+
+pub async fn update_tag_handler(
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+    Json(tag_data): Json<CreateTag>,
+) -> Result<Json<ApiResponse<Tag>>, StatusCode> {
+    if !is_valid_tag_name(&tag_data.name) {
+        return Ok(Json(ApiResponse::error("Etiket adı harf veya rakam içermeli".to_string())));
+    }
+    match update_tag(&pool, id, tag_data).await {
+        Ok(tag) => Ok(Json(ApiResponse::success(tag))),
+        Err(_) => Ok(Json(ApiResponse::error("Etiket güncellenemedi".to_string()))),
+    }
+}
+
+pub async fn delete_tag_handler(
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<String>>, StatusCode> {
+    match delete_tag(&pool, id).await {
+        Ok(_) => Ok(Json(ApiResponse::success("Etiket silindi".to_string()))),
+        Err(_) => Ok(Json(ApiResponse::error("Etiket silinemedi".to_string()))),
+    }
+}
+
 pub async fn get_disks_handler(
     State(pool): State<PgPool>,
 ) -> Result<Json<ApiResponse<Vec<DiskStorage>>>, StatusCode> {
@@ -249,12 +655,68 @@ pub async fn delete_disk_handler(
     State(pool): State<PgPool>,
     Path(id): Path<Uuid>,
 ) -> Result<Json<ApiResponse<String>>, StatusCode> {
+    match count_videos_on_disk(&pool, id).await {
+        Ok(0) => {}
+        Ok(count) => {
+            return Ok(Json(ApiResponse::error(format!(
+                "Disk üzerinde {} video bulunuyor, önce diski boşaltın",
+                count
+            ))))
+        }
+        Err(_) => return Ok(Json(ApiResponse::error("Disk silinemedi".to_string()))),
+    }
+
     match delete_disk_storage(&pool, id).await {
         Ok(_) => Ok(Json(ApiResponse::success("Disk silindi".to_string()))),
         Err(_) => Ok(Json(ApiResponse::error("Disk silinemedi".to_string()))),
     }
 }
 
+pub async fn drain_disk_handler(
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<DiskStorage>>, StatusCode> {
+    match set_disk_draining(&pool, id, true).await {
+        Ok(disk) => {
+            let drain_pool = pool.clone();
+            tokio::spawn(async move {
+                if let Err(e) = drain_disk(&drain_pool, id).await {
+                    tracing::error!("Failed to drain disk {}: {}", id, e);
+                }
+            });
+            Ok(Json(ApiResponse::success(disk)))
+        }
+        Err(_) => Ok(Json(ApiResponse::error("Disk boşaltılamadı".to_string()))),
+    }
+}
+
+pub async fn cancel_disk_drain_handler(
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+) -> Result<Json<ApiResponse<DiskStorage>>, StatusCode> {
+    match set_disk_draining(&pool, id, false).await {
+        Ok(disk) => Ok(Json(ApiResponse::success(disk))),
+        Err(_) => Ok(Json(ApiResponse::error("Disk boşaltma iptal edilemedi".to_string()))),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct HealthHistoryQuery {
+    pub limit: Option<i64>,
+}
+
+pub async fn get_disk_health_handler(
+    State(pool): State<PgPool>,
+    Path(id): Path<Uuid>,
+    Query(params): Query<HealthHistoryQuery>,
+) -> Result<Json<ApiResponse<Vec<DiskHealthCheck>>>, StatusCode> {
+    let limit = params.limit.unwrap_or(100).clamp(1, 1000);
+    match get_disk_health_history(&pool, id, limit).await {
+        Ok(checks) => Ok(Json(ApiResponse::success(checks))),
+        Err(_) => Ok(Json(ApiResponse::error("Disk sağlık geçmişi alınamadı".to_string()))),
+    }
+}
+
 pub async fn scan_system_disks_handler() -> Result<Json<ApiResponse<Vec<SystemDiskInfo>>>, StatusCode> {
     match scan_system_disks().await {
         Ok(disks) => Ok(Json(ApiResponse::success(disks))),
@@ -262,6 +724,51 @@ pub async fn scan_system_disks_handler() -> Result<Json<ApiResponse<Vec<SystemDi
     }
 }
 
+pub async fn register_system_disk_handler(
+    State(pool): State<PgPool>,
+    Json(request): Json<RegisterSystemDisk>,
+) -> Result<Json<ApiResponse<DiskStorage>>, StatusCode> {
+    let system_disk = match scan_system_disks().await {
+        Ok(disks) => disks.into_iter().find(|disk| disk.mount_point == request.mount_point),
+        Err(_) => return Ok(Json(ApiResponse::error("Sistem diskleri taranamadı".to_string()))),
+    };
+
+    let system_disk = match system_disk {
+        Some(disk) => disk,
+        None => return Ok(Json(ApiResponse::error("Bağlama noktası bulunamadı".to_string()))),
+    };
+
+    if system_disk.is_read_only {
+        return Ok(Json(ApiResponse::error("Salt okunur disk kaydedilemez".to_string())));
+    }
+
+    let path = match request.subdirectory.as_deref().map(|dir| dir.trim_matches('/')) {
+        Some(dir) if !dir.is_empty() => {
+            if dir.split('/').any(|part| part == "..") {
+                return Ok(Json(ApiResponse::error("Geçersiz alt dizin".to_string())));
+            }
+            PathBuf::from(&system_disk.mount_point).join(dir).to_string_lossy().to_string()
+        }
+        _ => system_disk.mount_point.clone(),
+    };
+
+    if let Err(e) = fs::create_dir_all(&path).await {
+        return Ok(Json(ApiResponse::error(format!("Disk dizini oluşturulamadı: {}", e))));
+    }
+
+    let disk_data = CreateDiskStorage {
+        name: request.name.unwrap_or(system_disk.name),
+        path,
+        total_space: system_disk.total_space as i64,
+        is_active: Some(true),
+    };
+
+    match create_disk_storage(&pool, disk_data).await {
+        Ok(disk) => Ok(Json(ApiResponse::success(disk))),
+        Err(e) => Ok(Json(ApiResponse::error(format!("Disk oluşturulamadı: {}", e)))),
+    }
+}
+
 async fn scan_system_disks() -> Result<Vec<SystemDiskInfo>, Box<dyn std::error::Error>> {
     let mut disks = Vec::new();
     
@@ -277,9 +784,17 @@ async fn scan_system_disks() -> Result<Vec<SystemDiskInfo>, Box<dyn std::error::
                     available_space: space_info.available,
                     used_space: space_info.total - space_info.available,
                     mount_point: drive,
+                    fs_type: None,
+                    device: None,
+                    total_inodes: None,
+                    free_inodes: None,
+                    is_read_only: false,
                 });
             }
         }
+    } else if cfg!(target_os = "linux") {
+        // Reading mountinfo and calling statvfs block, and a hung network mount blocks for long.
+        disks = tokio::task::spawn_blocking(scan_mounts).await??;
     } else {
         let home_dir = if cfg!(target_os = "windows") {
             std::env::var("USERPROFILE").unwrap_or_else(|_| "C:\\Users".to_string())
@@ -298,12 +813,17 @@ async fn scan_system_disks() -> Result<Vec<SystemDiskInfo>, Box<dyn std::error::
                 if metadata.is_dir() {
                     if let Ok(space_info) = get_disk_space(path).await {
                         disks.push(SystemDiskInfo {
-                            name: path.split('/').last().unwrap_or(path).to_string(),
+                            name: path.rsplit('/').next().unwrap_or(path).to_string(),
                             path: path.to_string(),
                             total_space: space_info.total,
                             available_space: space_info.available,
                             used_space: space_info.total - space_info.available,
                             mount_point: path.to_string(),
+                            fs_type: None,
+                            device: None,
+                            total_inodes: None,
+                            free_inodes: None,
+                            is_read_only: false,
                         });
                     }
                 }
@@ -395,6 +915,33 @@ async fn get_disk_space(path: &str) -> Result<DiskSpaceInfo, Box<dyn std::error:
     }
 }
 
+#[derive(Deserialize)]
+pub struct ImageVariantsQuery {
+    pub path: String,
+}
+
+/// Lists the responsive variants of an uploaded image, e.g. `?path=thumbnails/x.jpg`.
+pub async fn get_image_variants_handler(
+    State(pool): State<PgPool>,
+    Query(params): Query<ImageVariantsQuery>,
+) -> Result<Json<ApiResponse<Vec<ImageSource>>>, StatusCode> {
+    let reference = params.path.trim_start_matches("/uploads/").trim_start_matches('/');
+    match get_image_variants(&pool, reference).await {
+        Ok(variants) => Ok(Json(ApiResponse::success(
+            variants
+                .into_iter()
+                .map(|variant| ImageSource {
+                    url: format!("/uploads/{}", variant.reference),
+                    width: variant.width,
+                    height: variant.height,
+                    mime_type: variant.mime_type,
+                })
+                .collect(),
+        ))),
+        Err(_) => Ok(Json(ApiResponse::error("Görsel varyantları alınamadı".to_string()))),
+    }
+}
+
 pub async fn serve_video_handler(
     State(pool): State<PgPool>,
     Path(filename): Path<String>,
@@ -410,7 +957,13 @@ pub async fn serve_video_handler(
         Err(_) => return Err(StatusCode::NOT_FOUND),
     };
 
-    let content_type = "video/mp4";
+    let content_type = match get_media_mime_type(&pool, media::VIDEO, &filename).await {
+        Ok(Some(mime_type)) => mime_type,
+        _ => match filetype::detect_file(&video_path).await {
+            Ok(Some(file_type)) => file_type.mime_type.to_string(),
+            _ => "application/octet-stream".to_string(),
+        },
+    };
     
     Ok(Response::builder()
         .status(StatusCode::OK)
@@ -421,7 +974,7 @@ pub async fn serve_video_handler(
         .unwrap())
 }
 
-async fn get_video_file_path(pool: &PgPool, filename: &str) -> Result<Option<PathBuf>, sqlx::Error> {
+async fn get_video_file_path(pool: &PgPool, filename: &str) -> anyhow::Result<Option<PathBuf>> {
     let result = sqlx::query_as::<_, (String, String)>(
         "SELECT ds.path, v.video_file 
          FROM videos v 
@@ -434,6 +987,18 @@ async fn get_video_file_path(pool: &PgPool, filename: &str) -> Result<Option<Pat
 
     if let Some((disk_path, video_file)) = result {
         let full_path = PathBuf::from(&disk_path).join(&video_file);
+        if fs::metadata(&full_path).await.is_ok() {
+            return Ok(Some(full_path));
+        }
+
+        for replica_path in get_replica_paths(pool, &video_file).await? {
+            let replica_file = PathBuf::from(&replica_path).join(&video_file);
+            if fs::metadata(&replica_file).await.is_ok() {
+                tracing::warn!("Serving {} from replica at {}", video_file, replica_path);
+                return Ok(Some(replica_file));
+            }
+        }
+
         Ok(Some(full_path))
     } else {
         let fallback_path = PathBuf::from("uploads/videos").join(filename);
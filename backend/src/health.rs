@@ -0,0 +1,365 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use lettre::{
+    message::Mailbox, AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor,
+};
+use serde::Serialize;
+use sqlx::PgPool;
+use std::{env, path::PathBuf, sync::Arc, time::Duration};
+use tokio::fs;
+use uuid::Uuid;
+use crate::{
+    config::env_or,
+    database::{get_all_disks, prune_disk_health_history, record_disk_health_check},
+    models::{CreateDiskHealthCheck, DiskStorage},
+    storage::{mount_of, statvfs},
+};
+
+const HEALTH_PROBE_FILE: &str = ".portalyus_health_probe";
+
+#[derive(Clone, Debug)]
+pub struct HealthConfig {
+    pub interval: Duration,
+    /// Below this share of free space a disk is marked unhealthy and taken out of rotation.
+    pub critical_free_percent: f64,
+    /// Below this share of free space a warning alert is sent but the disk stays active.
+    pub warning_free_percent: f64,
+    /// Absolute floor of free bytes; a disk with less is unhealthy regardless of its size.
+    pub min_free_bytes: u64,
+    pub history_days: i32,
+    /// Whether a disk may live on the root filesystem. Off by default, because a disk path on
+    /// `/` usually means its own disk is not mounted and uploads would fill the system disk.
+    pub allow_root_filesystem: bool,
+}
+
+impl HealthConfig {
+    pub fn from_env() -> Self {
+        Self {
+            interval: Duration::from_secs(env_or("DISK_HEALTH_INTERVAL_SECS", 300)),
+            critical_free_percent: env_or("DISK_CRITICAL_FREE_PERCENT", 2.0),
+            warning_free_percent: env_or("DISK_WARNING_FREE_PERCENT", 10.0),
+            min_free_bytes: env_or("DISK_MIN_FREE_BYTES", 1024 * 1024 * 1024),
+            history_days: env_or("DISK_HEALTH_HISTORY_DAYS", 30),
+            allow_root_filesystem: env_or("DISK_ALLOW_ROOT_FILESYSTEM", false),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HealthStatus {
+    Healthy,
+    Warning,
+    Unhealthy,
+}
+
+impl HealthStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HealthStatus::Healthy => "healthy",
+            HealthStatus::Warning => "warning",
+            HealthStatus::Unhealthy => "unhealthy",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct DiskAlert {
+    pub disk_id: Uuid,
+    pub disk_name: String,
+    pub path: String,
+    pub status: HealthStatus,
+    pub previous_status: String,
+    pub message: String,
+    pub checked_at: DateTime<Utc>,
+}
+
+/// Receives alerts when a disk's health status changes.
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    async fn notify(&self, alert: &DiskAlert) -> Result<()>;
+}
+
+pub struct LogNotifier;
+
+#[async_trait]
+impl Notifier for LogNotifier {
+    async fn notify(&self, alert: &DiskAlert) -> Result<()> {
+        match alert.status {
+            HealthStatus::Healthy => tracing::info!(
+                "Disk {} ({}) recovered: {}", alert.disk_name, alert.path, alert.message
+            ),
+            HealthStatus::Warning => tracing::warn!(
+                "Disk {} ({}) warning: {}", alert.disk_name, alert.path, alert.message
+            ),
+            HealthStatus::Unhealthy => tracing::error!(
+                "Disk {} ({}) unhealthy: {}", alert.disk_name, alert.path, alert.message
+            ),
+        }
+        Ok(())
+    }
+}
+
+/// Posts each alert as JSON to a webhook URL.
+pub struct WebhookNotifier {
+    url: String,
+    client: reqwest::Client,
+}
+
+impl WebhookNotifier {
+    pub fn new(url: String) -> Self {
+        Self {
+            url,
+            client: reqwest::Client::builder()
+                .timeout(Duration::from_secs(10))
+                .build()
+                .unwrap_or_default(),
+        }
+    }
+}
+
+#[async_trait]
+impl Notifier for WebhookNotifier {
+    async fn notify(&self, alert: &DiskAlert) -> Result<()> {
+        self.client
+            .post(&self.url)
+            .json(alert)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
+
+/// Sends alerts by mail over plain SMTP.
+///
+/// Intended for a local relay or a development sink such as the `mailpit` service in
+/// `docker-compose.dev.yml`; TLS and authentication are left to the relay.
+pub struct SmtpNotifier {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+    to: Vec<Mailbox>,
+}
+
+impl SmtpNotifier {
+    pub fn new(host: &str, port: u16, from: &str, to: &str) -> Result<Self> {
+        let transport = AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(host)
+            .port(port)
+            .timeout(Some(Duration::from_secs(10)))
+            .build();
+        let to = to
+            .split(',')
+            .map(|address| address.trim().parse())
+            .collect::<Result<Vec<Mailbox>, _>>()?;
+        if to.is_empty() {
+            return Err(anyhow!("SMTP notifier needs at least one recipient"));
+        }
+
+        Ok(Self {
+            transport,
+            from: from.parse()?,
+            to,
+        })
+    }
+}
+
+#[async_trait]
+impl Notifier for SmtpNotifier {
+    async fn notify(&self, alert: &DiskAlert) -> Result<()> {
+        let mut builder = Message::builder()
+            .from(self.from.clone())
+            .subject(format!("[Portalyus] Disk {} is {}", alert.disk_name, alert.status.as_str()));
+        for recipient in &self.to {
+            builder = builder.to(recipient.clone());
+        }
+
+        let body = format!(
+            "Disk: {}\nPath: {}\nStatus: {} (was {})\nChecked at: {}\n\n{}\n",
+            alert.disk_name,
+            alert.path,
+            alert.status.as_str(),
+            alert.previous_status,
+            alert.checked_at.to_rfc3339(),
+            alert.message,
+        );
+
+        self.transport.send(builder.body(body)?).await?;
+        Ok(())
+    }
+}
+
+/// Fans an alert out to several notifiers; one failing does not stop the others.
+pub struct CompositeNotifier {
+    notifiers: Vec<Box<dyn Notifier>>,
+}
+
+#[async_trait]
+impl Notifier for CompositeNotifier {
+    async fn notify(&self, alert: &DiskAlert) -> Result<()> {
+        for notifier in &self.notifiers {
+            if let Err(e) = notifier.notify(alert).await {
+                tracing::error!("Failed to deliver disk alert for {}: {}", alert.disk_name, e);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Builds the notifier chain from `DISK_ALERT_NOTIFIERS` (comma separated: `log`, `webhook`, `smtp`).
+pub fn notifier_from_env() -> Arc<dyn Notifier> {
+    let kinds = env::var("DISK_ALERT_NOTIFIERS").unwrap_or_else(|_| "log".to_string());
+    let mut notifiers: Vec<Box<dyn Notifier>> = Vec::new();
+
+    for kind in kinds.split(',').map(str::trim).filter(|kind| !kind.is_empty()) {
+        match kind {
+            "log" => notifiers.push(Box::new(LogNotifier)),
+            "webhook" => match env::var("DISK_ALERT_WEBHOOK_URL") {
+                Ok(url) => notifiers.push(Box::new(WebhookNotifier::new(url))),
+                Err(_) => tracing::warn!("DISK_ALERT_WEBHOOK_URL is not set, webhook alerts disabled"),
+            },
+            "smtp" => {
+                let host = env::var("SMTP_HOST").unwrap_or_else(|_| "localhost".to_string());
+                let port = env_or("SMTP_PORT", 1025);
+                let from = env::var("DISK_ALERT_EMAIL_FROM")
+                    .unwrap_or_else(|_| "portalyus@localhost".to_string());
+                let to = env::var("DISK_ALERT_EMAIL_TO").unwrap_or_default();
+                match SmtpNotifier::new(&host, port, &from, &to) {
+                    Ok(notifier) => notifiers.push(Box::new(notifier)),
+                    Err(e) => tracing::warn!("SMTP alerts disabled: {}", e),
+                }
+            }
+            other => tracing::warn!("Unknown disk alert notifier: {}", other),
+        }
+    }
+
+    Arc::new(CompositeNotifier { notifiers })
+}
+
+/// Checks that a disk's path is present, writable and has enough free space.
+pub async fn check_disk(disk: &DiskStorage, config: &HealthConfig) -> CreateDiskHealthCheck {
+    let path = PathBuf::from(&disk.path);
+    let mut check = CreateDiskHealthCheck {
+        disk_id: disk.id,
+        status: HealthStatus::Unhealthy.as_str().to_string(),
+        is_mounted: false,
+        is_writable: false,
+        total_space: None,
+        available_space: None,
+        message: None,
+    };
+
+    match fs::metadata(&path).await {
+        Ok(metadata) if metadata.is_dir() => {}
+        Ok(_) => {
+            check.message = Some(format!("{} is not a directory", disk.path));
+            return check;
+        }
+        Err(e) => {
+            check.message = Some(format!("{} is not accessible: {}", disk.path, e));
+            return check;
+        }
+    }
+
+    // statvfs and mountinfo block, and on a hung network mount they block for long.
+    let probe_path = disk.path.clone();
+    let probed = tokio::task::spawn_blocking(move || {
+        statvfs(&probe_path).map(|stats| (stats, mount_of(std::path::Path::new(&probe_path)).ok()))
+    })
+    .await
+    .unwrap_or_else(|e| Err(e.into()));
+    let (stats, mount) = match probed {
+        Ok(probed) => probed,
+        Err(e) => {
+            check.message = Some(format!("statvfs failed: {}", e));
+            return check;
+        }
+    };
+    check.total_space = Some(stats.total as i64);
+    check.available_space = Some(stats.available as i64);
+
+    // Without mountinfo (non-Linux hosts) there is nothing to compare against.
+    check.is_mounted = match &mount {
+        Some(mount) => mount.mount_point != "/" || config.allow_root_filesystem,
+        None => true,
+    };
+    if !check.is_mounted {
+        check.message = Some(format!("{} is on the root filesystem; its disk is not mounted", disk.path));
+        return check;
+    }
+
+    let probe = path.join(HEALTH_PROBE_FILE);
+    check.is_writable = !stats.is_read_only
+        && fs::write(&probe, Utc::now().to_rfc3339()).await.is_ok()
+        && fs::remove_file(&probe).await.is_ok();
+    if !check.is_writable {
+        check.message = Some(format!("{} is not writable", disk.path));
+        return check;
+    }
+
+    let free_percent = if stats.total > 0 {
+        stats.available as f64 / stats.total as f64 * 100.0
+    } else {
+        0.0
+    };
+
+    let status = if free_percent < config.critical_free_percent || stats.available < config.min_free_bytes {
+        HealthStatus::Unhealthy
+    } else if free_percent < config.warning_free_percent {
+        HealthStatus::Warning
+    } else {
+        HealthStatus::Healthy
+    };
+
+    check.status = status.as_str().to_string();
+    check.message = Some(format!(
+        "{:.1}% free ({} of {} bytes available)",
+        free_percent, stats.available, stats.total
+    ));
+    check
+}
+
+/// Runs one health check pass over every registered disk, alerting on status changes.
+pub async fn run_health_checks(pool: &PgPool, config: &HealthConfig, notifier: &dyn Notifier) -> Result<()> {
+    for disk in get_all_disks(pool).await? {
+        let check = check_disk(&disk, config).await;
+        let updated = record_disk_health_check(pool, &check).await?;
+
+        let first_check_healthy = disk.health_status == "unknown" && check.status == "healthy";
+        if updated.health_status == disk.health_status || first_check_healthy {
+            continue;
+        }
+
+        let status = match check.status.as_str() {
+            "healthy" => HealthStatus::Healthy,
+            "warning" => HealthStatus::Warning,
+            _ => HealthStatus::Unhealthy,
+        };
+        let alert = DiskAlert {
+            disk_id: disk.id,
+            disk_name: disk.name.clone(),
+            path: disk.path.clone(),
+            status,
+            previous_status: disk.health_status.clone(),
+            message: check.message.unwrap_or_default(),
+            checked_at: Utc::now(),
+        };
+
+        if let Err(e) = notifier.notify(&alert).await {
+            tracing::error!("Failed to send alert for disk {}: {}", disk.id, e);
+        }
+    }
+
+    prune_disk_health_history(pool, config.history_days).await?;
+    Ok(())
+}
+
+pub async fn run_health_monitor(pool: PgPool, config: HealthConfig, notifier: Arc<dyn Notifier>) {
+    let mut interval = tokio::time::interval(config.interval);
+    loop {
+        interval.tick().await;
+        if let Err(e) = run_health_checks(&pool, &config, notifier.as_ref()).await {
+            tracing::error!("Disk health check failed: {}", e);
+        }
+    }
+}
@@ -0,0 +1,302 @@
+use anyhow::Result;
+use axum::{
+    extract::{Path as UrlPath, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+};
+use image::imageops::FilterType;
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tokio::{fs, sync::Mutex};
+use uuid::Uuid;
+use crate::{
+    config::env_or,
+    filetype::{self, FileType},
+    images::{decode_oriented, write_image},
+    media::MEDIA_ROOT,
+};
+
+/// Widths and heights a client may ask for; anything else would let callers fill the cache.
+pub const ALLOWED_SIZES: &[u32] = &[64, 120, 160, 240, 320, 480, 640, 960, 1280, 1920];
+/// Upload directories whose images may be resized.
+const SOURCE_DIRS: &[&str] = &["thumbnails", "channels", "banners"];
+/// URLs name the source path, which stays the same when the image behind it is replaced, so
+/// clients keep a result briefly and then revalidate it with its ETag.
+const CACHE_CONTROL: &str = "public, max-age=300, must-revalidate";
+/// Eviction trims the cache to this share of its limit so it does not run on every write.
+const EVICTION_LOW_WATER_PERCENT: u64 = 90;
+
+#[derive(Clone, Debug)]
+pub struct ImageProxyConfig {
+    pub cache_dir: PathBuf,
+    pub max_cache_bytes: u64,
+}
+
+impl ImageProxyConfig {
+    pub fn from_env() -> Self {
+        Self {
+            cache_dir: PathBuf::from(env_or("IMAGE_CACHE_DIR", "cache/img".to_string())),
+            max_cache_bytes: env_or("IMAGE_CACHE_MAX_BYTES", 512 * 1024 * 1024),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Fit {
+    /// Scales the image to fit inside the box, keeping its aspect ratio.
+    #[default]
+    Contain,
+    /// Scales and crops the image to fill the box exactly.
+    Cover,
+    /// Stretches the image to the box, ignoring its aspect ratio.
+    Fill,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResizeQuery {
+    pub w: Option<u32>,
+    pub h: Option<u32>,
+    #[serde(default)]
+    pub fit: Fit,
+    /// webp, jpeg or png; the source format when omitted.
+    pub format: Option<String>,
+}
+
+/// Disk cache of resized images, evicting the least recently used entries past its limit.
+///
+/// A hit touches the entry's modification time, so eviction can order entries by it.
+#[derive(Clone)]
+pub struct ImageCache {
+    config: Arc<ImageProxyConfig>,
+    usage: Arc<AtomicU64>,
+    eviction: Arc<Mutex<()>>,
+}
+
+impl ImageCache {
+    pub async fn open(config: ImageProxyConfig) -> Result<Self> {
+        fs::create_dir_all(&config.cache_dir).await?;
+        let usage = cache_entries(&config.cache_dir)
+            .await?
+            .iter()
+            .map(|entry| entry.size)
+            .sum();
+        Ok(Self {
+            config: Arc::new(config),
+            usage: Arc::new(AtomicU64::new(usage)),
+            eviction: Arc::new(Mutex::new(())),
+        })
+    }
+
+    fn entry_path(&self, key: &str, file_type: FileType) -> PathBuf {
+        self.config.cache_dir.join(format!("{}.{}", key, file_type.extension))
+    }
+
+    async fn get(&self, path: &Path) -> Option<Vec<u8>> {
+        let bytes = fs::read(path).await.ok()?;
+        if let Ok(file) = fs::OpenOptions::new().write(true).open(path).await {
+            let _ = file.into_std().await.set_modified(SystemTime::now());
+        }
+        Some(bytes)
+    }
+
+    async fn record_write(&self, size: u64) {
+        let usage = self.usage.fetch_add(size, Ordering::Relaxed) + size;
+        if usage > self.config.max_cache_bytes {
+            if let Err(e) = self.evict().await {
+                tracing::error!("Failed to evict image cache entries: {}", e);
+            }
+        }
+    }
+
+    /// Deletes the least recently used entries until the cache is under its low-water mark.
+    async fn evict(&self) -> Result<()> {
+        // A concurrent eviction is already freeing space.
+        let Ok(_guard) = self.eviction.try_lock() else {
+            return Ok(());
+        };
+
+        let mut entries = cache_entries(&self.config.cache_dir).await?;
+        entries.sort_by_key(|entry| entry.modified);
+        let target = self.config.max_cache_bytes / 100 * EVICTION_LOW_WATER_PERCENT;
+        let mut usage: u64 = entries.iter().map(|entry| entry.size).sum();
+        let mut evicted = 0;
+        for entry in entries {
+            if usage <= target {
+                break;
+            }
+            if fs::remove_file(&entry.path).await.is_ok() {
+                usage -= entry.size;
+                evicted += 1;
+            }
+        }
+        self.usage.store(usage, Ordering::Relaxed);
+        tracing::info!("Evicted {} image cache entries, {} bytes remain", evicted, usage);
+        Ok(())
+    }
+}
+
+struct CacheEntry {
+    path: PathBuf,
+    size: u64,
+    modified: SystemTime,
+}
+
+async fn cache_entries(dir: &Path) -> Result<Vec<CacheEntry>> {
+    let mut entries = Vec::new();
+    let mut reader = fs::read_dir(dir).await?;
+    while let Some(entry) = reader.next_entry().await? {
+        let metadata = entry.metadata().await?;
+        if metadata.is_file() {
+            entries.push(CacheEntry {
+                path: entry.path(),
+                size: metadata.len(),
+                modified: metadata.modified().unwrap_or(UNIX_EPOCH),
+            });
+        }
+    }
+    Ok(entries)
+}
+
+/// Maps a request path to a file under one of the image upload directories.
+fn resolve_source(path: &str) -> Option<PathBuf> {
+    let segments: Vec<&str> = path.split('/').collect();
+    if segments.len() < 2
+        || !SOURCE_DIRS.contains(&segments[0])
+        || segments.iter().any(|segment| segment.is_empty() || *segment == "." || *segment == "..")
+    {
+        return None;
+    }
+    Some(segments.iter().fold(PathBuf::from(MEDIA_ROOT), |path, segment| path.join(segment)))
+}
+
+fn output_type(format: Option<&str>, source_type: FileType) -> Option<FileType> {
+    match format {
+        None => Some(source_type),
+        Some("webp") => Some(filetype::WEBP),
+        Some("jpeg") | Some("jpg") => Some(filetype::JPEG),
+        Some("png") => Some(filetype::PNG),
+        Some(_) => None,
+    }
+}
+
+fn render(
+    source: &Path,
+    output: &Path,
+    width: Option<u32>,
+    height: Option<u32>,
+    fit: Fit,
+    file_type: FileType,
+) -> Result<()> {
+    let image = decode_oriented(source)?;
+    let resized = match (fit, width, height) {
+        (Fit::Cover, Some(w), Some(h)) => image.resize_to_fill(w, h, FilterType::Lanczos3),
+        (Fit::Fill, Some(w), Some(h)) => image.resize_exact(w, h, FilterType::Lanczos3),
+        _ => {
+            // Never upscale when only bounding the image.
+            let w = width.unwrap_or(u32::MAX).min(image.width());
+            let h = height.unwrap_or(u32::MAX).min(image.height());
+            if w == image.width() && h == image.height() {
+                image
+            } else {
+                image.resize(w, h, FilterType::Lanczos3)
+            }
+        }
+    };
+    write_image(&resized, output, file_type)
+}
+
+/// Serves an uploaded image resized on demand, e.g. `/img/thumbnails/x.jpg?w=320&h=180&fit=cover`.
+pub async fn resize_image_handler(
+    State(cache): State<ImageCache>,
+    UrlPath(path): UrlPath<String>,
+    Query(params): Query<ResizeQuery>,
+    headers: HeaderMap,
+) -> Result<Response, StatusCode> {
+    let sizes = [params.w, params.h];
+    if sizes.iter().all(Option::is_none)
+        || sizes.iter().flatten().any(|size| !ALLOWED_SIZES.contains(size))
+    {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    if matches!(params.fit, Fit::Cover | Fit::Fill) && sizes.iter().any(Option::is_none) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let source = resolve_source(&path).ok_or(StatusCode::NOT_FOUND)?;
+    let metadata = fs::metadata(&source).await.map_err(|_| StatusCode::NOT_FOUND)?;
+    let source_type = filetype::detect_file(&source)
+        .await
+        .map_err(|_| StatusCode::NOT_FOUND)?
+        .filter(|file_type| filetype::IMAGE_TYPES.contains(file_type))
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let file_type = output_type(params.format.as_deref(), source_type).ok_or(StatusCode::BAD_REQUEST)?;
+
+    // Replacing the source changes its size or modification time, and with it the key.
+    let modified = metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|time| time.as_nanos())
+        .unwrap_or_default();
+    let key = hex::encode(Sha256::digest(format!(
+        "{}|{}|{}|{:?}|{:?}|{:?}|{}",
+        path, metadata.len(), modified, params.w, params.h, params.fit, file_type.extension
+    )));
+    let etag = format!("\"{}\"", &key[..32]);
+
+    if headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.split(',').any(|tag| tag.trim() == etag))
+    {
+        return Ok((
+            StatusCode::NOT_MODIFIED,
+            [(header::ETAG, etag), (header::CACHE_CONTROL, CACHE_CONTROL.to_string())],
+        )
+            .into_response());
+    }
+
+    let entry = cache.entry_path(&key, file_type);
+    let bytes = match cache.get(&entry).await {
+        Some(bytes) => bytes,
+        None => {
+            let partial = cache.config.cache_dir.join(format!(".{}.tmp", Uuid::new_v4()));
+            let output = partial.clone();
+            let (w, h, fit) = (params.w, params.h, params.fit);
+            let rendered = tokio::task::spawn_blocking(move || render(&source, &output, w, h, fit, file_type))
+                .await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+            if let Err(e) = rendered {
+                let _ = fs::remove_file(&partial).await;
+                tracing::warn!("Failed to resize {}: {}", path, e);
+                return Err(StatusCode::UNPROCESSABLE_ENTITY);
+            }
+            // Concurrent renders of the same key write identical bytes, so the last rename wins.
+            fs::rename(&partial, &entry)
+                .await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+            let bytes = fs::read(&entry).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+            cache.record_write(bytes.len() as u64).await;
+            bytes
+        }
+    };
+
+    Ok((
+        [
+            (header::CONTENT_TYPE, file_type.mime_type.to_string()),
+            (header::CACHE_CONTROL, CACHE_CONTROL.to_string()),
+            (header::ETAG, etag),
+        ],
+        bytes,
+    )
+        .into_response())
+}
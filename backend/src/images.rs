@@ -0,0 +1,182 @@
+use anyhow::{anyhow, Result};
+use async_trait::async_trait;
+use image::{
+    codecs::{jpeg::JpegEncoder, png::PngEncoder, webp::WebPEncoder},
+    imageops::FilterType,
+    DynamicImage, ImageDecoder, ImageReader,
+};
+use sqlx::PgPool;
+use std::{
+    fs::File,
+    io::BufWriter,
+    path::{Path, PathBuf},
+};
+use tokio::fs;
+use crate::{
+    database::{create_image_variant, delete_image_variants, get_image_variants},
+    filetype::{self, FileType},
+    media::MEDIA_ROOT,
+    models::CreateImageVariant,
+    upload::{CompletedUpload, UploadHook},
+};
+
+/// Widths rendered for every uploaded image; larger ones than the original are skipped.
+pub const VARIANT_WIDTHS: &[u32] = &[320, 640, 1280];
+/// Formats each width is encoded in; the frontend's `<picture>` offers WebP to browsers that
+/// accept it and falls back to JPEG.
+const VARIANT_TYPES: &[FileType] = &[filetype::WEBP, filetype::JPEG];
+const JPEG_QUALITY: u8 = 82;
+/// Variants live next to their source, in this sub-directory.
+const VARIANT_DIR: &str = "variants";
+
+struct RenderedVariant {
+    width: u32,
+    height: u32,
+    file_type: FileType,
+    file_name: String,
+    size_bytes: u64,
+}
+
+/// Decodes an image and applies its EXIF orientation to the pixels.
+pub fn decode_oriented(path: &Path) -> Result<DynamicImage> {
+    let mut decoder = ImageReader::open(path)?.with_guessed_format()?.into_decoder()?;
+    let orientation = decoder.orientation()?;
+    let mut image = DynamicImage::from_decoder(decoder)?;
+    image.apply_orientation(orientation);
+    Ok(image)
+}
+
+/// Encodes an image without any metadata, which is how EXIF data gets stripped.
+pub fn write_image(image: &DynamicImage, path: &Path, file_type: FileType) -> Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    match file_type {
+        filetype::JPEG => DynamicImage::ImageRgb8(image.to_rgb8())
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut writer, JPEG_QUALITY))?,
+        filetype::PNG => image.write_with_encoder(PngEncoder::new(&mut writer))?,
+        filetype::WEBP => DynamicImage::ImageRgba8(image.to_rgba8())
+            .write_with_encoder(WebPEncoder::new_lossless(&mut writer))?,
+        other => return Err(anyhow!("cannot encode {}", other.mime_type)),
+    }
+    Ok(())
+}
+
+/// Rewrites an image upright and without metadata, in place.
+fn strip_metadata(source: &Path, source_type: FileType) -> Result<()> {
+    let image = decode_oriented(source)?;
+    let cleaned = source.with_extension("clean");
+    if let Err(e) = write_image(&image, &cleaned, source_type) {
+        let _ = std::fs::remove_file(&cleaned);
+        return Err(e);
+    }
+    std::fs::rename(&cleaned, source)?;
+    Ok(())
+}
+
+/// Renders the responsive variants of an image next to it.
+fn render_variants(source: &Path) -> Result<Vec<RenderedVariant>> {
+    let image = decode_oriented(source)?;
+
+    let output_dir = source
+        .parent()
+        .ok_or_else(|| anyhow!("{:?} has no parent directory", source))?
+        .join(VARIANT_DIR);
+    std::fs::create_dir_all(&output_dir)?;
+    let stem = source
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .ok_or_else(|| anyhow!("{:?} has no file name", source))?;
+
+    let mut widths: Vec<u32> = VARIANT_WIDTHS
+        .iter()
+        .copied()
+        .filter(|&width| width <= image.width())
+        .collect();
+    if widths.is_empty() {
+        widths.push(image.width());
+    }
+
+    let mut variants = Vec::new();
+    for width in widths {
+        let resized = if width == image.width() {
+            image.clone()
+        } else {
+            image.resize(width, u32::MAX, FilterType::Lanczos3)
+        };
+        for &file_type in VARIANT_TYPES {
+            let file_name = format!("{}_{}.{}", stem, width, file_type.extension);
+            let path = output_dir.join(&file_name);
+            write_image(&resized, &path, file_type)?;
+            variants.push(RenderedVariant {
+                width: resized.width(),
+                height: resized.height(),
+                file_type,
+                file_name,
+                size_bytes: std::fs::metadata(&path)?.len(),
+            });
+        }
+    }
+    Ok(variants)
+}
+
+/// Processes a stored image and records its variants, unless identical content was already
+/// processed under the same reference.
+pub async fn process_image(pool: &PgPool, path: &Path, reference: &str) -> Result<usize> {
+    if !get_image_variants(pool, reference).await?.is_empty() {
+        return Ok(0);
+    }
+
+    filetype::detect_file(path)
+        .await?
+        .filter(|file_type| filetype::IMAGE_TYPES.contains(file_type))
+        .ok_or_else(|| anyhow!("{} is not a supported image", reference))?;
+
+    let source = path.to_path_buf();
+    let rendered = tokio::task::spawn_blocking(move || render_variants(&source)).await??;
+
+    let reference_dir = Path::new(reference)
+        .parent()
+        .map(|dir| dir.join(VARIANT_DIR))
+        .unwrap_or_else(|| PathBuf::from(VARIANT_DIR));
+    for variant in &rendered {
+        create_image_variant(pool, &CreateImageVariant {
+            source_reference: reference.to_string(),
+            width: variant.width as i32,
+            height: variant.height as i32,
+            mime_type: variant.file_type.mime_type.to_string(),
+            reference: reference_dir.join(&variant.file_name).to_string_lossy().into_owned(),
+            size_bytes: variant.size_bytes as i64,
+        })
+        .await?;
+    }
+    Ok(rendered.len())
+}
+
+/// Deletes the variants of an image whose source file is being removed.
+pub async fn remove_image_variants(pool: &PgPool, reference: &str) -> Result<()> {
+    for variant in delete_image_variants(pool, reference).await? {
+        let _ = fs::remove_file(PathBuf::from(MEDIA_ROOT).join(&variant.reference)).await;
+    }
+    Ok(())
+}
+
+/// Upload hook that runs the image pipeline on thumbnails and channel images.
+pub struct ImageVariantsHook;
+
+#[async_trait]
+impl UploadHook for ImageVariantsHook {
+    /// EXIF data is stripped before the file is hashed, so the stored hash and size describe
+    /// the bytes that are served.
+    async fn before_store(&self, path: &Path, file_type: FileType) -> Result<bool> {
+        let source = path.to_path_buf();
+        tokio::task::spawn_blocking(move || strip_metadata(&source, file_type)).await??;
+        Ok(true)
+    }
+
+    async fn after_store(&self, pool: &PgPool, upload: &CompletedUpload) -> Result<()> {
+        let count = process_image(pool, &upload.path, &upload.reference).await?;
+        if count > 0 {
+            tracing::info!("Rendered {} variants of {}", count, upload.reference);
+        }
+        Ok(())
+    }
+}
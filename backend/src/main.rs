@@ -1,31 +1,56 @@
+mod analytics;
 mod auth;
+mod config;
 mod database;
+mod filetype;
 mod handlers;
+mod health;
+mod image_proxy;
+mod images;
+mod media;
 mod models;
+mod ranking;
+mod replication;
+mod storage;
+mod tus;
 mod upload;
+mod views;
 
 use axum::{
     extract::DefaultBodyLimit,
     middleware,
-    routing::{delete, get, post, put},
+    routing::{delete, get, head, options, patch, post, put},
     Router,
 };
 use dotenv::dotenv;
-use std::env;
+use std::{env, net::SocketAddr, sync::Arc};
 use tower::ServiceBuilder;
 use tower_http::{
     cors::{Any, CorsLayer},
     services::ServeDir,
 };
-use tracing_subscriber;
 
+use analytics::{
+    get_category_analytics_handler, get_channel_analytics_handler, get_video_analytics_handler,
+    record_heartbeat_handler, run_analytics_rollup, AnalyticsConfig,
+};
 use auth::{auth_middleware, login, AuthConfig, AuthState};
-use database::create_pool;
+use database::{create_pool, prune_video_view_events};
 use handlers::*;
+use health::{notifier_from_env, run_health_monitor, HealthConfig};
+use image_proxy::{resize_image_handler, ImageCache, ImageProxyConfig};
+use media::purge_unreferenced_media;
+use ranking::{
+    get_popular_videos_handler, get_trending_videos_handler, run_feed_refresh, FeedCache, FeedConfig, FeedState,
+};
+use replication::{run_replica_repair, ReplicationConfig};
+use storage::resume_draining_disks;
+use tus::{tus_create, tus_head, tus_options, tus_patch, tus_protocol, tus_terminate};
 use upload::{
-    upload_video_chunk, upload_thumbnail_chunk, upload_channel_image_chunk,
-    get_upload_status, cancel_upload, cleanup_expired_uploads, UploadConfig, AppState
+    upload_chunk, get_upload_status, cancel_upload, cleanup_expired_uploads, list_uploads, UploadConfig,
+    AppState,
 };
+use views::{record_view_handler, run_view_counter_flush, ViewConfig, ViewCounter, ViewState};
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -37,25 +62,56 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let auth_config = AuthConfig::from_env();
     let upload_config = UploadConfig::default();
+    let image_cache = ImageCache::open(ImageProxyConfig::from_env()).await?;
+    let view_config = Arc::new(ViewConfig::from_env());
+    let view_counter = ViewCounter::default();
+    let feed_cache = FeedCache::default();
 
     let cors = CorsLayer::new()
         .allow_origin(Any)
         .allow_methods(Any)
-        .allow_headers(Any);
+        .allow_headers(Any)
+        .expose_headers(Any);
 
     let public_routes = Router::new()
         .route("/api/videos", get(get_videos_handler))
         .route("/api/videos/featured", get(get_featured_videos_handler))
         .route("/api/videos/:slug", get(get_video_handler))
+        .route("/api/videos/:slug/related", get(get_related_videos_handler))
         .route("/api/channels", get(get_channels_handler))
         .route("/api/channels/:slug", get(get_channel_handler))
         .route("/api/channels/:slug/videos", get(get_channel_videos_handler))
         .route("/api/categories", get(get_categories_handler))
         .route("/api/categories/:slug", get(get_category_handler))
         .route("/api/categories/:slug/videos", get(get_category_videos_handler))
+        .route("/api/tags", get(get_tags_handler))
+        .route("/api/tags/:slug", get(get_tag_handler))
+        .route("/api/tags/:slug/videos", get(get_tag_videos_handler))
+        .route("/api/images/variants", get(get_image_variants_handler))
         .route("/uploads/videos/:filename", get(serve_video_handler))
         .with_state(pool.clone());
 
+    let image_routes = Router::new()
+        .route("/img/*path", get(resize_image_handler))
+        .with_state(image_cache);
+
+    let view_routes = Router::new()
+        .route("/api/videos/:slug/view", post(record_view_handler))
+        .route("/api/videos/:slug/heartbeat", post(record_heartbeat_handler))
+        .with_state(ViewState {
+            pool: pool.clone(),
+            config: view_config.clone(),
+            counter: view_counter.clone(),
+        });
+
+    let feed_routes = Router::new()
+        .route("/api/videos/trending", get(get_trending_videos_handler))
+        .route("/api/videos/popular", get(get_popular_videos_handler))
+        .with_state(FeedState {
+            pool: pool.clone(),
+            cache: feed_cache.clone(),
+        });
+
     let auth_routes = Router::new()
         .route("/api/auth/login", post(login))
         .with_state(AuthState {
@@ -67,17 +123,30 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .route("/api/admin/videos", post(create_video_handler))
         .route("/api/admin/videos/:id", put(update_video_handler))
         .route("/api/admin/videos/:id", delete(delete_video_handler))
+        .route("/api/admin/videos/:id/verify", post(verify_video_handler))
         .route("/api/admin/channels", post(create_channel_handler))
         .route("/api/admin/channels/:id", put(update_channel_handler))
         .route("/api/admin/channels/:id", delete(delete_channel_handler))
+        .route("/api/admin/channels/order", put(reorder_channels_handler))
         .route("/api/admin/categories", post(create_category_handler))
         .route("/api/admin/categories/:id", put(update_category_handler))
         .route("/api/admin/categories/:id", delete(delete_category_handler))
+        .route("/api/admin/categories/order", put(reorder_categories_handler))
+        .route("/api/admin/tags", post(create_tag_handler))
+        .route("/api/admin/tags/:id", put(update_tag_handler))
+        .route("/api/admin/tags/:id", delete(delete_tag_handler))
         .route("/api/admin/disks", get(get_disks_handler))
         .route("/api/admin/disks", post(create_disk_handler))
         .route("/api/admin/disks/:id", put(update_disk_handler))
         .route("/api/admin/disks/:id", delete(delete_disk_handler))
+        .route("/api/admin/disks/:id/drain", post(drain_disk_handler))
+        .route("/api/admin/disks/:id/drain", delete(cancel_disk_drain_handler))
+        .route("/api/admin/disks/:id/health", get(get_disk_health_handler))
         .route("/api/admin/disks/scan", get(scan_system_disks_handler))
+        .route("/api/admin/disks/register", post(register_system_disk_handler))
+        .route("/api/admin/analytics/videos/:id", get(get_video_analytics_handler))
+        .route("/api/admin/analytics/channels", get(get_channel_analytics_handler))
+        .route("/api/admin/analytics/categories", get(get_category_analytics_handler))
         .layer(middleware::from_fn_with_state(
             auth_config.clone(),
             auth_middleware,
@@ -85,16 +154,37 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         .with_state(pool.clone());
 
     let upload_routes = Router::new()
-        .route("/api/admin/upload/video/chunk", post(upload_video_chunk))
-        .route("/api/admin/upload/thumbnail/chunk", post(upload_thumbnail_chunk))
-        .route("/api/admin/upload/channel-image/chunk", post(upload_channel_image_chunk))
+        .route("/api/admin/upload/:kind/chunk", post(upload_chunk))
         .route("/api/admin/upload/status", get(get_upload_status))
         .route("/api/admin/upload/cancel", delete(cancel_upload))
+        .route("/api/admin/uploads", get(list_uploads))
         .layer(DefaultBodyLimit::max(50 * 1024 * 1024))
         .layer(middleware::from_fn_with_state(
             auth_config.clone(),
             auth_middleware,
         ))
+        .with_state(AppState {
+            pool: pool.clone(),
+            upload_config: upload_config.clone(),
+        });
+
+    // tus clients probe the server with OPTIONS before authenticating.
+    let tus_discovery_routes = Router::new()
+        .route("/api/admin/tus/:kind", options(tus_options))
+        .route("/api/admin/tus/:kind/:id", options(tus_options))
+        .layer(middleware::from_fn(tus_protocol))
+        .with_state(upload_config.clone());
+
+    let tus_routes = Router::new()
+        .route("/api/admin/tus/:kind", post(tus_create))
+        .route("/api/admin/tus/:kind/:id", head(tus_head))
+        .route("/api/admin/tus/:kind/:id", patch(tus_patch))
+        .route("/api/admin/tus/:kind/:id", delete(tus_terminate))
+        .layer(middleware::from_fn_with_state(
+            auth_config.clone(),
+            auth_middleware,
+        ))
+        .layer(middleware::from_fn(tus_protocol))
         .with_state(AppState {
             pool: pool.clone(),
             upload_config,
@@ -102,19 +192,56 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
     let app = Router::new()
         .merge(public_routes)
+        .merge(image_routes)
+        .merge(view_routes)
+        .merge(feed_routes)
         .merge(auth_routes)
         .merge(admin_routes)
         .merge(upload_routes)
+        .merge(tus_discovery_routes)
+        .merge(tus_routes)
         .nest_service("/uploads", ServeDir::new("uploads"))
         .layer(ServiceBuilder::new().layer(cors));
 
-    let cleanup_task = tokio::spawn(async {
+    let drain_pool = pool.clone();
+    tokio::spawn(async move {
+        if let Err(e) = resume_draining_disks(&drain_pool).await {
+            tracing::error!("Failed to resume disk drains: {}", e);
+        }
+    });
+
+    tokio::spawn(run_health_monitor(
+        pool.clone(),
+        HealthConfig::from_env(),
+        notifier_from_env(),
+    ));
+
+    tokio::spawn(run_replica_repair(pool.clone(), ReplicationConfig::from_env()));
+
+    tokio::spawn(run_analytics_rollup(pool.clone(), AnalyticsConfig::from_env()));
+
+    tokio::spawn(run_feed_refresh(pool.clone(), feed_cache, FeedConfig::from_env()));
+
+    tokio::spawn(run_view_counter_flush(
+        pool.clone(),
+        view_counter.clone(),
+        view_config.flush_interval,
+    ));
+
+    let cleanup_pool = pool.clone();
+    let cleanup_task = tokio::spawn(async move {
         let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(3600)); // Every hour
         loop {
             interval.tick().await;
-            if let Err(e) = cleanup_expired_uploads().await {
+            if let Err(e) = cleanup_expired_uploads(&cleanup_pool).await {
                 tracing::error!("Failed to cleanup expired uploads: {}", e);
             }
+            if let Err(e) = purge_unreferenced_media(&cleanup_pool).await {
+                tracing::error!("Failed to purge unreferenced media: {}", e);
+            }
+            if let Err(e) = prune_video_view_events(&cleanup_pool, view_config.event_retention_days).await {
+                tracing::error!("Failed to prune view events: {}", e);
+            }
         }
     });
     
@@ -122,7 +249,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("Server running on http://0.0.0.0:3001");
     
     tokio::select! {
-        result = axum::serve(listener, app) => {
+        result = axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+            .with_graceful_shutdown(shutdown_signal()) => {
             if let Err(e) = result {
                 tracing::error!("Server error: {}", e);
             }
@@ -131,6 +259,34 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             tracing::info!("Cleanup task finished");
         }
     }
+
+    // Write the views buffered since the last periodic flush before exiting.
+    if let Err(e) = view_counter.flush(&pool).await {
+        tracing::error!("Failed to flush view counts on shutdown: {}", e);
+    }
     
     Ok(())
 }
+
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        let _ = tokio::signal::ctrl_c().await;
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut signal) => {
+                signal.recv().await;
+            }
+            Err(_) => std::future::pending::<()>().await,
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+    tracing::info!("Shutting down");
+}
@@ -0,0 +1,202 @@
+use anyhow::Result;
+use sqlx::{PgConnection, PgPool};
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use uuid::Uuid;
+use crate::{
+    database::{
+        acquire_media_blob, create_media_blob, delete_media_blob, get_disk_by_id, release_media_blob,
+        take_unreferenced_media_blobs, update_disk_usage,
+    },
+    images::remove_image_variants,
+    models::{MediaBlob, MediaReplica, Video},
+};
+
+pub const VIDEO: &str = "video";
+pub const THUMBNAIL: &str = "thumbnail";
+pub const CHANNEL_IMAGE: &str = "channel_image";
+pub const BANNER: &str = "banner";
+
+/// Root directory for media that is not stored on a `DiskStorage` (images).
+pub const MEDIA_ROOT: &str = "uploads";
+
+/// Where an uploaded file ended up after deduplication.
+pub struct StoredMedia {
+    pub reference: String,
+    pub disk_id: Option<Uuid>,
+    pub deduplicated: bool,
+}
+
+/// Channel rows store the bare file name while uploads report `channels/<name>`.
+pub fn channel_image_reference(image: &str) -> String {
+    if image.starts_with("channels/") {
+        image.to_string()
+    } else {
+        format!("channels/{}", image)
+    }
+}
+
+/// Channel and category rows store the bare banner file name, like channel images.
+pub fn banner_reference(banner: &str) -> String {
+    if banner.starts_with("banners/") {
+        banner.to_string()
+    } else {
+        format!("banners/{}", banner)
+    }
+}
+
+async fn blob_path(pool: &PgPool, blob: &MediaBlob) -> Result<Option<PathBuf>> {
+    match blob.disk_id {
+        Some(disk_id) => Ok(get_disk_by_id(pool, disk_id)
+            .await?
+            .map(|disk| PathBuf::from(disk.path).join(&blob.reference))),
+        None => Ok(Some(PathBuf::from(MEDIA_ROOT).join(&blob.reference))),
+    }
+}
+
+/// Registers a freshly assembled file by its content hash.
+///
+/// When identical content is already stored, the new copy at `final_path` is deleted and the
+/// existing file's reference is returned instead.
+pub async fn store_media(
+    pool: &PgPool,
+    kind: &str,
+    sha256: &str,
+    final_path: &Path,
+    reference: &str,
+    disk_id: Option<Uuid>,
+    size_bytes: i64,
+) -> Result<StoredMedia> {
+    let blob = create_media_blob(pool, kind, sha256, reference, disk_id, size_bytes).await?;
+    if blob.reference == reference && blob.disk_id == disk_id {
+        return Ok(StoredMedia {
+            reference: reference.to_string(),
+            disk_id,
+            deduplicated: false,
+        });
+    }
+
+    let existing_path = blob_path(pool, &blob).await?;
+    let existing_present = match &existing_path {
+        Some(path) => fs::metadata(path).await.is_ok(),
+        None => false,
+    };
+
+    if !existing_present {
+        tracing::warn!("Media blob {} lost its file, registering the new upload instead", blob.id);
+        delete_media_blob(pool, blob.id).await?;
+        let blob = create_media_blob(pool, kind, sha256, reference, disk_id, size_bytes).await?;
+        return Ok(StoredMedia {
+            reference: blob.reference,
+            disk_id: blob.disk_id,
+            deduplicated: false,
+        });
+    }
+
+    fs::remove_file(final_path).await?;
+    Ok(StoredMedia {
+        reference: blob.reference,
+        disk_id: blob.disk_id,
+        deduplicated: true,
+    })
+}
+
+/// Adds a reference to a stored file, on the transaction that writes the referencing row.
+pub async fn retain_media(conn: &mut PgConnection, kind: &str, reference: Option<&str>) -> Result<()> {
+    if let Some(reference) = reference {
+        acquire_media_blob(conn, kind, reference).await?;
+    }
+    Ok(())
+}
+
+/// A file whose last reference was dropped, with its replicas. The rows are gone once the
+/// releasing transaction commits; `remove_freed_media` then deletes the bytes.
+pub struct FreedMedia {
+    blob: MediaBlob,
+    replicas: Vec<MediaReplica>,
+}
+
+/// Drops a reference on the transaction that changes or deletes the referencing row, collecting
+/// the file in `freed` once nothing refers to it any more.
+pub async fn release_media(
+    conn: &mut PgConnection,
+    kind: &str,
+    reference: Option<&str>,
+    freed: &mut Vec<FreedMedia>,
+) -> Result<()> {
+    if let Some(reference) = reference {
+        if let Some((blob, replicas)) = release_media_blob(conn, kind, reference).await? {
+            freed.push(FreedMedia { blob, replicas });
+        }
+    }
+    Ok(())
+}
+
+/// Swaps the reference a row holds, retaining the new media before releasing the old one.
+pub async fn replace_media(
+    conn: &mut PgConnection,
+    kind: &str,
+    old: Option<&str>,
+    new: Option<&str>,
+    freed: &mut Vec<FreedMedia>,
+) -> Result<()> {
+    if old == new {
+        return Ok(());
+    }
+    retain_media(conn, kind, new).await?;
+    release_media(conn, kind, old, freed).await
+}
+
+pub async fn retain_video_media(conn: &mut PgConnection, video: &Video) -> Result<()> {
+    retain_media(conn, VIDEO, Some(&video.video_file)).await?;
+    retain_media(conn, THUMBNAIL, video.thumbnail.as_deref()).await
+}
+
+/// Releases the media of a deleted video.
+pub async fn release_video_media(conn: &mut PgConnection, video: &Video, freed: &mut Vec<FreedMedia>) -> Result<()> {
+    release_media(conn, THUMBNAIL, video.thumbnail.as_deref(), freed).await?;
+    release_media(conn, VIDEO, Some(&video.video_file), freed).await
+}
+
+/// Deletes the files, replica copies and image variants of media released by a committed
+/// transaction. Failures are only logged: the references are already gone.
+pub async fn remove_freed_media(pool: &PgPool, freed: Vec<FreedMedia>) {
+    for FreedMedia { blob, replicas } in freed {
+        if let Err(e) = remove_blob_files(pool, &blob, &replicas).await {
+            tracing::warn!("Could not remove unreferenced media {}: {}", blob.reference, e);
+        }
+    }
+}
+
+async fn remove_blob_files(pool: &PgPool, blob: &MediaBlob, replicas: &[MediaReplica]) -> Result<()> {
+    if let Some(path) = blob_path(pool, blob).await? {
+        if let Err(e) = fs::remove_file(&path).await {
+            tracing::warn!("Could not remove unreferenced media {:?}: {}", path, e);
+        }
+    }
+    for replica in replicas {
+        let disk = match get_disk_by_id(pool, replica.disk_id).await? {
+            Some(disk) => disk,
+            None => continue,
+        };
+        let path = PathBuf::from(&disk.path).join(&blob.reference);
+        if let Ok(metadata) = fs::metadata(&path).await {
+            if fs::remove_file(&path).await.is_ok() {
+                update_disk_usage(pool, disk.id, -(metadata.len() as i64)).await?;
+            }
+        }
+    }
+    remove_image_variants(pool, &blob.reference).await
+}
+
+/// Deletes uploads that finished but were never attached to a video or channel.
+pub async fn purge_unreferenced_media(pool: &PgPool) -> Result<usize> {
+    let blobs = take_unreferenced_media_blobs(pool, 24).await?;
+    for blob in &blobs {
+        if let Some(path) = blob_path(pool, blob).await? {
+            let _ = fs::remove_file(&path).await;
+        }
+        remove_image_variants(pool, &blob.reference).await?;
+    }
+    Ok(blobs.len())
+}
@@ -1,7 +1,7 @@
 use serde::{Deserialize, Serialize};
-use sqlx::FromRow;
+use sqlx::{types::Json, FromRow};
 use uuid::Uuid;
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, NaiveDate, Utc};
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
 pub struct Channel {
@@ -9,6 +9,15 @@ pub struct Channel {
     pub name: String,
     pub image: Option<String>,
     pub slug: String,
+    /// Place in navigation lists, ascending; set through the reorder endpoint.
+    pub position: i32,
+    pub description: Option<String>,
+    /// Accent color as `#rrggbb`.
+    pub color: Option<String>,
+    /// File name of an image uploaded as `banner`.
+    pub banner: Option<String>,
+    pub seo_title: Option<String>,
+    pub seo_description: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -17,6 +26,11 @@ pub struct Channel {
 pub struct CreateChannel {
     pub name: String,
     pub image: Option<String>,
+    pub description: Option<String>,
+    pub color: Option<String>,
+    pub banner: Option<String>,
+    pub seo_title: Option<String>,
+    pub seo_description: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
@@ -24,6 +38,19 @@ pub struct Category {
     pub id: Uuid,
     pub name: String,
     pub slug: String,
+    pub parent_id: Option<Uuid>,
+    /// Place among the categories with the same parent, ascending; new and moved categories go
+    /// last. Set through the reorder endpoint.
+    pub position: i32,
+    pub description: Option<String>,
+    /// Icon name the frontend maps to an icon component.
+    pub icon: Option<String>,
+    /// Accent color as `#rrggbb`.
+    pub color: Option<String>,
+    /// File name of an image uploaded as `banner`.
+    pub banner: Option<String>,
+    pub seo_title: Option<String>,
+    pub seo_description: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -31,6 +58,82 @@ pub struct Category {
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreateCategory {
     pub name: String,
+    pub parent_id: Option<Uuid>,
+    pub description: Option<String>,
+    pub icon: Option<String>,
+    pub color: Option<String>,
+    pub banner: Option<String>,
+    pub seo_title: Option<String>,
+    pub seo_description: Option<String>,
+}
+
+/// New order of channels or categories: the listed ids take positions 1, 2, … in turn. Lists
+/// every channel, or every child of one parent category.
+#[derive(Debug, Deserialize)]
+pub struct ReorderRequest {
+    pub ids: Vec<Uuid>,
+}
+
+/// A category with its sub-categories, as returned by `/api/categories?tree=true`.
+#[derive(Debug, Serialize)]
+pub struct CategoryNode {
+    #[serde(flatten)]
+    pub category: Category,
+    pub children: Vec<CategoryNode>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum CategoryList {
+    Flat(Vec<Category>),
+    Tree(Vec<CategoryNode>),
+}
+
+/// One step of a category's path from the top level.
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct CategoryCrumb {
+    pub id: Uuid,
+    pub name: String,
+    pub slug: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CategoryDetails {
+    #[serde(flatten)]
+    pub category: Category,
+    /// Ancestors from the top level down, ending with the category itself.
+    pub breadcrumbs: Vec<CategoryCrumb>,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct Tag {
+    pub id: Uuid,
+    pub name: String,
+    pub slug: String,
+    pub video_count: i64,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CreateTag {
+    pub name: String,
+}
+
+/// A category as embedded in `VideoWithDetails`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CategorySummary {
+    pub id: Uuid,
+    pub name: String,
+    pub slug: String,
+    pub is_primary: bool,
+}
+
+/// A tag as embedded in `VideoWithDetails`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TagSummary {
+    pub name: String,
+    pub slug: String,
 }
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
@@ -47,6 +150,7 @@ pub struct Video {
     pub channel_id: Uuid,
     pub category_id: Uuid,
     pub disk_id: Option<Uuid>,
+    pub file_sha256: Option<String>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -61,9 +165,24 @@ pub struct CreateVideo {
     pub video_file: Option<String>,
     pub thumbnail: Option<String>,
     pub disk_id: Option<Uuid>,
+    /// Length in whole seconds, read from the file by the admin panel. Leaving it out keeps the
+    /// current one on update.
+    pub duration: Option<u32>,
+    /// Tag names; unknown ones are created. Leaving it out keeps the current tags on update.
+    pub tags: Option<Vec<String>>,
+    /// Categories besides `category_id`, the primary one. Leaving it out keeps them on update.
+    pub category_ids: Option<Vec<Uuid>>,
 }
 
-#[derive(Debug, Serialize, Deserialize, FromRow)]
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VideoIntegrityReport {
+    pub video_id: Uuid,
+    pub expected_sha256: Option<String>,
+    pub actual_sha256: String,
+    pub matches: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct VideoWithDetails {
     pub id: Uuid,
     pub title: String,
@@ -80,6 +199,197 @@ pub struct VideoWithDetails {
     pub channel_profile_image: Option<String>,
     pub category_name: String,
     pub category_slug: String,
+    pub thumbnail_variants: Json<Vec<ImageSource>>,
+    pub tags: Json<Vec<TagSummary>>,
+    /// Every assigned category, the primary one first.
+    pub categories: Json<Vec<CategorySummary>>,
+}
+
+/// A resized rendition of an uploaded image.
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct ImageVariant {
+    pub id: Uuid,
+    pub source_reference: String,
+    pub width: i32,
+    pub height: i32,
+    pub mime_type: String,
+    pub reference: String,
+    pub size_bytes: i64,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CreateImageVariant {
+    pub source_reference: String,
+    pub width: i32,
+    pub height: i32,
+    pub mime_type: String,
+    pub reference: String,
+    pub size_bytes: i64,
+}
+
+/// One `srcset` candidate: `url` is served from `/uploads`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImageSource {
+    pub url: String,
+    pub width: i32,
+    pub height: i32,
+    pub mime_type: String,
+}
+
+/// Heartbeat a player sends while a video plays.
+#[derive(Debug, Deserialize)]
+pub struct PlaybackHeartbeat {
+    /// Random id the player picks per playback, so heartbeats of one session add up.
+    pub session_id: Uuid,
+    pub client_id: Option<String>,
+    /// Position where the span played since the previous heartbeat began.
+    pub start: f64,
+    pub position: f64,
+    /// Seconds of playback since the previous heartbeat.
+    pub watched: f64,
+}
+
+/// Bounds on the watch time heartbeats may add to a playback session.
+#[derive(Debug, Clone, Copy)]
+pub struct WatchTimeLimits {
+    /// How much a heartbeat may add beyond the wall-clock time since the previous one, to
+    /// absorb network jitter.
+    pub slack_seconds: f64,
+    /// How many full plays of the video a session counts at most, for seeking back and loops.
+    pub max_plays: f64,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct VideoDailyStats {
+    pub video_id: Uuid,
+    pub day: NaiveDate,
+    pub views: i32,
+    pub unique_viewers: i32,
+    pub sessions: i32,
+    pub watch_seconds: f64,
+    pub avg_percent_watched: f64,
+    pub retention: Vec<i32>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct VideoAnalytics {
+    pub video_id: Uuid,
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub total_views: i32,
+    pub views: i64,
+    pub sessions: i64,
+    pub watch_seconds: f64,
+    pub avg_percent_watched: f64,
+    /// Share of sessions, in percent, that played each 5% segment of the video over the range.
+    pub retention: Vec<f64>,
+    pub daily: Vec<VideoDailyStats>,
+}
+
+/// Totals of one channel or category: `videos`, `total_views` and `storage_bytes` are current,
+/// `uploads`, `views` and `uploads_per_week` cover the requested date range.
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct ContentRollup {
+    pub id: Uuid,
+    pub name: String,
+    pub slug: String,
+    pub videos: i64,
+    pub total_views: i64,
+    pub storage_bytes: i64,
+    pub uploads: i64,
+    pub views: i64,
+    #[sqlx(skip)]
+    pub uploads_per_week: Vec<WeeklyUploads>,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct WeeklyUploads {
+    #[serde(skip)]
+    pub id: Uuid,
+    /// Monday of the week.
+    pub week_start: NaiveDate,
+    pub uploads: i64,
+}
+
+/// A stored media file, shared by every row whose column holds `reference`.
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct MediaBlob {
+    pub id: Uuid,
+    pub kind: String,
+    pub sha256: String,
+    pub reference: String,
+    pub disk_id: Option<Uuid>,
+    pub size_bytes: i64,
+    pub ref_count: i32,
+    pub mime_type: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// An extra copy of a stored file on a disk other than the blob's own `disk_id`.
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct MediaReplica {
+    pub id: Uuid,
+    pub blob_id: Uuid,
+    pub disk_id: Uuid,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Progress of a chunked or tus upload.
+///
+/// `status` is one of `uploading`, `finalizing`, `completed`, `failed`, `cancelled` or `expired`.
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct UploadSession {
+    pub id: String,
+    pub owner: Option<String>,
+    pub kind: String,
+    pub protocol: String,
+    pub file_name: String,
+    pub total_size: i64,
+    pub total_chunks: i32,
+    #[serde(skip)]
+    pub received_chunks: Vec<u8>,
+    pub received_bytes: i64,
+    pub file_sha256: Option<String>,
+    pub status: String,
+    pub file_reference: Option<String>,
+    pub disk_id: Option<Uuid>,
+    pub expires_at: DateTime<Utc>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl UploadSession {
+    pub fn has_chunk(&self, chunk_number: u32) -> bool {
+        let bit = chunk_number as usize - 1;
+        self.received_chunks
+            .get(bit / 8)
+            .is_some_and(|byte| byte & (1 << (bit % 8)) != 0)
+    }
+
+    pub fn received_chunk_numbers(&self) -> Vec<u32> {
+        (1..=self.total_chunks as u32)
+            .filter(|&chunk_number| self.has_chunk(chunk_number))
+            .collect()
+    }
+
+    pub fn all_chunks_received(&self) -> bool {
+        (1..=self.total_chunks as u32).all(|chunk_number| self.has_chunk(chunk_number))
+    }
+}
+
+#[derive(Debug)]
+pub struct CreateUploadSession {
+    pub id: String,
+    pub owner: Option<String>,
+    pub kind: String,
+    pub protocol: &'static str,
+    pub file_name: String,
+    pub total_size: i64,
+    pub total_chunks: i32,
+    pub file_sha256: Option<String>,
+    pub expires_in_hours: i32,
 }
 
 #[derive(Debug, Serialize, Deserialize, FromRow)]
@@ -90,10 +400,38 @@ pub struct DiskStorage {
     pub total_space: i64,
     pub used_space: i64,
     pub is_active: bool,
+    pub is_draining: bool,
+    pub health_status: String,
+    pub disabled_by_health: bool,
+    pub last_checked_at: Option<DateTime<Utc>>,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
 
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct DiskHealthCheck {
+    pub id: Uuid,
+    pub disk_id: Uuid,
+    pub status: String,
+    pub is_mounted: bool,
+    pub is_writable: bool,
+    pub total_space: Option<i64>,
+    pub available_space: Option<i64>,
+    pub message: Option<String>,
+    pub checked_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CreateDiskHealthCheck {
+    pub disk_id: Uuid,
+    pub status: String,
+    pub is_mounted: bool,
+    pub is_writable: bool,
+    pub total_space: Option<i64>,
+    pub available_space: Option<i64>,
+    pub message: Option<String>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct CreateDiskStorage {
     pub name: String,
@@ -120,6 +458,18 @@ pub struct SystemDiskInfo {
     pub used_space: u64,
     pub available_space: u64,
     pub mount_point: String,
+    pub fs_type: Option<String>,
+    pub device: Option<String>,
+    pub total_inodes: Option<u64>,
+    pub free_inodes: Option<u64>,
+    pub is_read_only: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RegisterSystemDisk {
+    pub mount_point: String,
+    pub name: Option<String>,
+    pub subdirectory: Option<String>,
 }
 
 #[derive(Debug)]
@@ -0,0 +1,151 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::Deserialize;
+use sqlx::PgPool;
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+use crate::{
+    config::env_or,
+    database::get_ranked_videos,
+    models::{ApiResponse, VideoWithDetails},
+};
+
+/// Number of videos kept per feed; requests can ask for fewer.
+const FEED_SIZE: i64 = 50;
+const DEFAULT_FEED_LIMIT: usize = 20;
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Period {
+    Day,
+    #[default]
+    Week,
+    Month,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Feed {
+    Trending,
+    Popular(Period),
+}
+
+impl Feed {
+    const ALL: [Feed; 4] = [
+        Feed::Trending,
+        Feed::Popular(Period::Day),
+        Feed::Popular(Period::Week),
+        Feed::Popular(Period::Month),
+    ];
+
+    /// Hours of views considered and the half-life of a view's weight, in hours.
+    ///
+    /// Trending decays fast so videos picking up views now rise quickly; the popular feeds
+    /// decay over their whole period so they stay close to plain view counts.
+    fn window_and_half_life(self) -> (i32, f64) {
+        match self {
+            Feed::Trending => (72, 12.0),
+            Feed::Popular(Period::Day) => (24, 24.0),
+            Feed::Popular(Period::Week) => (7 * 24, 7.0 * 24.0),
+            Feed::Popular(Period::Month) => (30 * 24, 30.0 * 24.0),
+        }
+    }
+
+    async fn compute(self, pool: &PgPool) -> anyhow::Result<Vec<VideoWithDetails>> {
+        let (window_hours, half_life_hours) = self.window_and_half_life();
+        get_ranked_videos(pool, window_hours, half_life_hours, FEED_SIZE).await
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct FeedConfig {
+    pub refresh_interval: Duration,
+}
+
+impl FeedConfig {
+    pub fn from_env() -> Self {
+        Self {
+            refresh_interval: Duration::from_secs(env_or("FEED_REFRESH_INTERVAL_SECS", 300)),
+        }
+    }
+}
+
+/// Ranked feeds as of their last refresh, so requests never run the ranking query.
+#[derive(Clone, Default)]
+pub struct FeedCache {
+    feeds: Arc<RwLock<HashMap<Feed, Arc<Vec<VideoWithDetails>>>>>,
+}
+
+impl FeedCache {
+    fn get(&self, feed: Feed) -> Option<Arc<Vec<VideoWithDetails>>> {
+        self.feeds.read().unwrap().get(&feed).cloned()
+    }
+
+    async fn refresh(&self, pool: &PgPool, feed: Feed) -> anyhow::Result<Arc<Vec<VideoWithDetails>>> {
+        let videos = Arc::new(feed.compute(pool).await?);
+        self.feeds.write().unwrap().insert(feed, videos.clone());
+        Ok(videos)
+    }
+}
+
+pub async fn run_feed_refresh(pool: PgPool, cache: FeedCache, config: FeedConfig) {
+    let mut interval = tokio::time::interval(config.refresh_interval);
+    loop {
+        interval.tick().await;
+        for feed in Feed::ALL {
+            if let Err(e) = cache.refresh(&pool, feed).await {
+                tracing::error!("Failed to refresh {:?} feed: {}", feed, e);
+            }
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct FeedState {
+    pub pool: PgPool,
+    pub cache: FeedCache,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct FeedQuery {
+    #[serde(default)]
+    pub period: Period,
+    pub limit: Option<usize>,
+}
+
+async fn serve_feed(
+    state: &FeedState,
+    feed: Feed,
+    limit: Option<usize>,
+) -> Result<Json<ApiResponse<Vec<VideoWithDetails>>>, StatusCode> {
+    // Only hit before the first background refresh has finished.
+    let videos = match state.cache.get(feed) {
+        Some(videos) => videos,
+        None => match state.cache.refresh(&state.pool, feed).await {
+            Ok(videos) => videos,
+            Err(_) => return Ok(Json(ApiResponse::error("Videolar alınamadı".to_string()))),
+        },
+    };
+    let limit = limit.unwrap_or(DEFAULT_FEED_LIMIT).min(FEED_SIZE as usize);
+    Ok(Json(ApiResponse::success(videos.iter().take(limit).cloned().collect())))
+}
+
+pub async fn get_trending_videos_handler(
+    State(state): State<FeedState>,
+    Query(params): Query<FeedQuery>,
+) -> Result<Json<ApiResponse<Vec<VideoWithDetails>>>, StatusCode> {
+    serve_feed(&state, Feed::Trending, params.limit).await
+}
+
+/// Most viewed videos of the last day, week (default) or month: `?period=day|week|month`.
+pub async fn get_popular_videos_handler(
+    State(state): State<FeedState>,
+    Query(params): Query<FeedQuery>,
+) -> Result<Json<ApiResponse<Vec<VideoWithDetails>>>, StatusCode> {
+    serve_feed(&state, Feed::Popular(params.period), params.limit).await
+}
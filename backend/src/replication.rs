@@ -0,0 +1,203 @@
+use anyhow::{anyhow, Result};
+use sqlx::PgPool;
+use std::{collections::BTreeSet, env, io::ErrorKind, path::PathBuf, sync::Mutex, time::Duration};
+use tokio::fs;
+use uuid::Uuid;
+use crate::{
+    database::{
+        delete_media_replica, get_available_disk_for_replica, get_disk_by_id, get_media_blob,
+        get_media_replicas, get_stored_video_blobs, get_video_by_id, promote_media_replica,
+        reserve_media_replica,
+    },
+    media,
+    models::{DiskStorage, MediaBlob},
+    storage::{copy_file_exclusive, InFlightGuard},
+};
+
+/// Video files (blob ids) being replicated in this process. Upload-time replication and the
+/// repair loop would otherwise both treat a replica whose copy is still being written as missing.
+static ACTIVE_REPLICATIONS: Mutex<BTreeSet<Uuid>> = Mutex::new(BTreeSet::new());
+
+#[derive(Clone, Debug)]
+pub struct ReplicationConfig {
+    /// Total number of copies each video should have, including the primary one.
+    pub factor: usize,
+    pub repair_interval: Duration,
+}
+
+impl ReplicationConfig {
+    pub fn from_env() -> Self {
+        Self {
+            factor: env::var("REPLICATION_FACTOR")
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .unwrap_or(1)
+                .max(1),
+            repair_interval: Duration::from_secs(
+                env::var("REPLICA_REPAIR_INTERVAL_SECS")
+                    .ok()
+                    .and_then(|value| value.parse().ok())
+                    .unwrap_or(900),
+            ),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.factor > 1
+    }
+}
+
+/// Returns the file path of a video on the given disk if the disk is usable and the file exists.
+async fn healthy_copy(disk: &DiskStorage, video_file: &str) -> Option<PathBuf> {
+    if !disk.is_active {
+        return None;
+    }
+    let path = PathBuf::from(&disk.path).join(video_file);
+    match fs::metadata(&path).await {
+        Ok(metadata) if metadata.is_file() => Some(path),
+        _ => None,
+    }
+}
+
+/// Brings the file of a video up to the configured number of healthy copies.
+pub async fn replicate_video(pool: &PgPool, video_id: Uuid, config: &ReplicationConfig) -> Result<usize> {
+    let video = get_video_by_id(pool, video_id)
+        .await?
+        .ok_or_else(|| anyhow!("video {} not found", video_id))?;
+    match get_media_blob(pool, media::VIDEO, &video.video_file).await? {
+        Some(blob) => replicate_blob(pool, &blob, config).await,
+        None => {
+            tracing::warn!("Video {} has no registered file, it cannot be replicated", video.id);
+            Ok(0)
+        }
+    }
+}
+
+/// Brings a stored video file up to the configured number of healthy copies.
+///
+/// Replicas belong to the file, so videos deduplicated onto it share them. If the primary copy
+/// is unreachable a healthy replica is promoted first. Replica rows whose file has disappeared
+/// from an active disk are dropped so the copy gets recreated elsewhere.
+/// Returns the number of new replicas written.
+pub async fn replicate_blob(pool: &PgPool, blob: &MediaBlob, config: &ReplicationConfig) -> Result<usize> {
+    let Some(_guard) = InFlightGuard::acquire(&ACTIVE_REPLICATIONS, blob.id) else {
+        return Ok(0);
+    };
+    let primary_disk_id = match blob.disk_id {
+        Some(disk_id) => disk_id,
+        None => return Ok(0),
+    };
+
+    let primary_path = match get_disk_by_id(pool, primary_disk_id).await? {
+        Some(disk) => healthy_copy(&disk, &blob.reference).await,
+        None => None,
+    };
+
+    let mut healthy_paths = Vec::new();
+    let mut promote_candidate = None;
+    let mut missing_replicas = Vec::new();
+
+    for replica in get_media_replicas(pool, blob.id).await? {
+        let disk = match get_disk_by_id(pool, replica.disk_id).await? {
+            Some(disk) => disk,
+            None => continue,
+        };
+        match healthy_copy(&disk, &blob.reference).await {
+            Some(path) => {
+                promote_candidate.get_or_insert(replica.id);
+                healthy_paths.push(path);
+            }
+            None if disk.is_active => missing_replicas.push(replica.id),
+            None => {}
+        }
+    }
+
+    match &primary_path {
+        Some(path) => healthy_paths.insert(0, path.clone()),
+        None => match promote_candidate {
+            Some(replica_id) => {
+                tracing::warn!("Primary copy of {} unavailable, promoting replica", blob.reference);
+                promote_media_replica(pool, blob.id, replica_id).await?;
+            }
+            None => {
+                tracing::error!("Video file {} has no healthy copy left", blob.reference);
+                return Ok(0);
+            }
+        },
+    }
+
+    let source = healthy_paths[0].clone();
+    let size = fs::metadata(&source).await?.len() as i64;
+
+    for replica_id in missing_replicas {
+        tracing::warn!("Replica {} of {} is missing its file, dropping it", replica_id, blob.reference);
+        delete_media_replica(pool, replica_id, size).await?;
+    }
+
+    let mut created = 0;
+    while healthy_paths.len() < config.factor {
+        let target = match get_available_disk_for_replica(pool, &blob.reference, size).await? {
+            Some(target) => target,
+            None => {
+                tracing::warn!(
+                    "Only {} of {} copies possible for {}: no more disks available",
+                    healthy_paths.len(), config.factor, blob.reference
+                );
+                break;
+            }
+        };
+
+        let target_dir = PathBuf::from(&target.path);
+        let target_path = target_dir.join(&blob.reference);
+        // The row is claimed first; another writer that picked the same disk backs off here.
+        let replica = match reserve_media_replica(pool, blob.id, target.id, size).await? {
+            Some(replica) => replica,
+            None => continue,
+        };
+
+        let copied = match fs::create_dir_all(&target_dir).await {
+            Ok(()) => copy_file_exclusive(&source, &target_path).await,
+            Err(e) => Err(e),
+        };
+        match copied {
+            Ok(()) => created += 1,
+            // A file left behind by an earlier copy; the row now accounts for it again.
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => {}
+            Err(e) => {
+                delete_media_replica(pool, replica.id, size).await?;
+                return Err(e.into());
+            }
+        }
+        healthy_paths.push(target_path);
+    }
+
+    Ok(created)
+}
+
+/// Restores the replica count of every stored video file, e.g. after a disk failure.
+pub async fn repair_replicas(pool: &PgPool, config: &ReplicationConfig) -> Result<usize> {
+    let mut created = 0;
+    for blob in get_stored_video_blobs(pool).await? {
+        match replicate_blob(pool, &blob, config).await {
+            Ok(count) => created += count,
+            Err(e) => tracing::error!("Failed to repair replicas of {}: {}", blob.reference, e),
+        }
+    }
+    if created > 0 {
+        tracing::info!("Replica repair created {} new copies", created);
+    }
+    Ok(created)
+}
+
+pub async fn run_replica_repair(pool: PgPool, config: ReplicationConfig) {
+    if !config.is_enabled() {
+        return;
+    }
+    let mut interval = tokio::time::interval(config.repair_interval);
+    loop {
+        interval.tick().await;
+        if let Err(e) = repair_replicas(&pool, &config).await {
+            tracing::error!("Replica repair failed: {}", e);
+        }
+    }
+}
@@ -0,0 +1,430 @@
+use anyhow::{anyhow, Result};
+use sqlx::PgPool;
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+use sha2::{Digest, Sha256};
+use tokio::{fs, io::AsyncReadExt};
+use uuid::Uuid;
+use crate::{
+    database::{
+        get_available_disk_for_replica, get_disk_by_id, get_draining_disks, get_replicas_on_disk,
+        get_videos_on_disk, move_replica_to_disk, move_video_to_disk,
+    },
+    models::SystemDiskInfo,
+};
+
+/// Filesystem types that never back real storage and are hidden from disk discovery.
+const PSEUDO_FILESYSTEMS: &[&str] = &[
+    "autofs", "binfmt_misc", "bpf", "cgroup", "cgroup2", "configfs", "debugfs", "devpts",
+    "devtmpfs", "efivarfs", "fusectl", "hugetlbfs", "mqueue", "nsfs", "proc", "pstore",
+    "ramfs", "rpc_pipefs", "securityfs", "squashfs", "sysfs", "tmpfs", "tracefs",
+    "fuse.gvfsd-fuse", "fuse.portal",
+];
+
+/// Disks with a drain running in this process; a second drain of the same disk would move the
+/// same files twice.
+static ACTIVE_DRAINS: Mutex<BTreeSet<Uuid>> = Mutex::new(BTreeSet::new());
+
+/// Marks an id as having a task in flight in a set like `ACTIVE_DRAINS` until dropped.
+pub(crate) struct InFlightGuard {
+    set: &'static Mutex<BTreeSet<Uuid>>,
+    id: Uuid,
+}
+
+impl InFlightGuard {
+    /// None when a task for `id` is already running.
+    pub(crate) fn acquire(set: &'static Mutex<BTreeSet<Uuid>>, id: Uuid) -> Option<Self> {
+        set.lock().unwrap().insert(id).then_some(Self { set, id })
+    }
+}
+
+impl Drop for InFlightGuard {
+    fn drop(&mut self) {
+        self.set.lock().unwrap().remove(&self.id);
+    }
+}
+
+/// Copies `source` to `target` without ever replacing an existing file: the bytes go to a
+/// temporary file next to `target`, which is then hard-linked into place. Fails with
+/// `AlreadyExists` when `target` exists, so the caller only ever deletes files it created.
+pub async fn copy_file_exclusive(source: &Path, target: &Path) -> std::io::Result<()> {
+    let file_name = target.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+    let partial = target.with_file_name(format!(".{}.{}.tmp", file_name, Uuid::new_v4()));
+    let linked = match fs::copy(source, &partial).await {
+        Ok(_) => fs::hard_link(&partial, target).await,
+        Err(e) => Err(e),
+    };
+    let _ = fs::remove_file(&partial).await;
+    linked
+}
+
+/// Relocates every video and replica stored on a draining disk to other active disks.
+///
+/// Targets never already hold a copy of the same file, so replication is preserved. Each file
+/// is copied to the target disk before the database row is switched over, and the
+/// source file is only removed afterwards, so an interrupted drain never leaves a video without
+/// a readable file. Videos that cannot be placed anywhere are left in place and logged.
+/// Returns immediately when the disk is already being drained, and stops before the next file
+/// once the drain is cancelled.
+pub async fn drain_disk(pool: &PgPool, disk_id: Uuid) -> Result<usize> {
+    let Some(_guard) = InFlightGuard::acquire(&ACTIVE_DRAINS, disk_id) else {
+        tracing::info!("Disk {} is already being drained", disk_id);
+        return Ok(0);
+    };
+
+    let disk = get_disk_by_id(pool, disk_id)
+        .await?
+        .ok_or_else(|| anyhow!("disk {} not found", disk_id))?;
+
+    if !disk.is_draining {
+        return Err(anyhow!("disk {} is not draining", disk_id));
+    }
+
+    let source_dir = PathBuf::from(&disk.path);
+    let mut moved = 0;
+
+    // (file name, replica id when the copy on this disk is a replica)
+    let mut files: Vec<(String, Option<Uuid>)> = Vec::new();
+    for video in get_videos_on_disk(pool, disk_id).await? {
+        // Deduplicated videos share one file; it is moved once for all of them.
+        if !files.iter().any(|(file, replica)| replica.is_none() && *file == video.video_file) {
+            files.push((video.video_file, None));
+        }
+    }
+    for (replica_id, video_file) in get_replicas_on_disk(pool, disk_id).await? {
+        files.push((video_file, Some(replica_id)));
+    }
+
+    for (video_file, replica_id) in files {
+        // Cancelling clears the flag; files not reached yet stay on the disk.
+        if !get_disk_by_id(pool, disk_id).await?.is_some_and(|disk| disk.is_draining) {
+            tracing::info!("Drain of disk {} was cancelled after {} file(s)", disk_id, moved);
+            break;
+        }
+
+        let source_path = source_dir.join(&video_file);
+        let size = match fs::metadata(&source_path).await {
+            Ok(metadata) => metadata.len() as i64,
+            Err(e) => {
+                tracing::warn!("Skipping {} during drain of disk {}: {}", video_file, disk_id, e);
+                continue;
+            }
+        };
+
+        let target = match get_available_disk_for_replica(pool, &video_file, size).await? {
+            Some(target) => target,
+            None => {
+                tracing::warn!("No disk available for {} while draining disk {}", video_file, disk_id);
+                continue;
+            }
+        };
+
+        let target_dir = PathBuf::from(&target.path);
+        let target_path = target_dir.join(&video_file);
+        fs::create_dir_all(&target_dir).await?;
+
+        if let Err(e) = copy_file_exclusive(&source_path, &target_path).await {
+            tracing::error!("Failed to copy {} to disk {}: {}", video_file, target.id, e);
+            continue;
+        }
+
+        let reassigned = match replica_id {
+            Some(replica_id) => move_replica_to_disk(pool, replica_id, disk_id, target.id, size).await,
+            None => move_video_to_disk(pool, &video_file, disk_id, target.id, size).await,
+        };
+        match reassigned {
+            Ok(true) => {}
+            Ok(false) => {
+                tracing::warn!("{} left disk {} during its drain", video_file, disk_id);
+                let _ = fs::remove_file(&target_path).await;
+                continue;
+            }
+            Err(e) => {
+                tracing::error!("Failed to reassign {} to disk {}: {}", video_file, target.id, e);
+                let _ = fs::remove_file(&target_path).await;
+                continue;
+            }
+        }
+
+        if let Err(e) = fs::remove_file(&source_path).await {
+            tracing::warn!("Moved {} but could not remove {:?}: {}", video_file, source_path, e);
+        }
+
+        moved += 1;
+    }
+
+    tracing::info!("Drained {} video(s) from disk {}", moved, disk_id);
+    Ok(moved)
+}
+
+/// Restarts the drain of any disk that was still draining when the server last stopped.
+pub async fn resume_draining_disks(pool: &PgPool) -> Result<()> {
+    for disk in get_draining_disks(pool).await? {
+        if let Err(e) = drain_disk(pool, disk.id).await {
+            tracing::error!("Failed to drain disk {}: {}", disk.id, e);
+        }
+    }
+    Ok(())
+}
+
+/// Computes the hex-encoded SHA-256 of a file without loading it into memory.
+pub async fn sha256_file(path: &std::path::Path) -> Result<String> {
+    let mut file = fs::File::open(path).await?;
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 1024 * 1024];
+
+    loop {
+        let read = file.read(&mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..read]);
+    }
+
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// A single entry of `/proc/self/mountinfo`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MountEntry {
+    pub mount_point: String,
+    pub fs_type: String,
+    pub device: String,
+}
+
+/// Parses the contents of `/proc/self/mountinfo` into mount entries.
+///
+/// Later entries for the same mount point replace earlier ones, since they are mounted on top.
+pub fn parse_mountinfo(contents: &str) -> Vec<MountEntry> {
+    let mut mounts: Vec<MountEntry> = Vec::new();
+
+    for line in contents.lines() {
+        let (left, right) = match line.split_once(" - ") {
+            Some(parts) => parts,
+            None => continue,
+        };
+        let mount_point = match left.split_whitespace().nth(4) {
+            Some(mount_point) => unescape_mount_field(mount_point),
+            None => continue,
+        };
+        let mut right_fields = right.split_whitespace();
+        let (fs_type, device) = match (right_fields.next(), right_fields.next()) {
+            (Some(fs_type), Some(device)) => (fs_type.to_string(), unescape_mount_field(device)),
+            _ => continue,
+        };
+
+        mounts.retain(|mount| mount.mount_point != mount_point);
+        mounts.push(MountEntry { mount_point, fs_type, device });
+    }
+
+    mounts
+}
+
+/// Decodes the octal escapes (`\040` for a space, etc.) the kernel uses in mountinfo fields.
+fn unescape_mount_field(field: &str) -> String {
+    let bytes = field.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if bytes[i] == b'\\'
+            && i + 4 <= bytes.len()
+            && bytes[i + 1..i + 4].iter().all(|b| (b'0'..=b'7').contains(b))
+        {
+            let octal = std::str::from_utf8(&bytes[i + 1..i + 4]).unwrap_or("0");
+            decoded.push(u8::from_str_radix(octal, 8).unwrap_or(b'?'));
+            i += 4;
+        } else {
+            decoded.push(bytes[i]);
+            i += 1;
+        }
+    }
+
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+/// The mount `path` lives on: the entry with the longest mount point containing it.
+pub fn owning_mount<'a>(mounts: &'a [MountEntry], path: &Path) -> Option<&'a MountEntry> {
+    mounts
+        .iter()
+        .filter(|mount| path.starts_with(&mount.mount_point))
+        .max_by_key(|mount| mount.mount_point.len())
+}
+
+/// Looks up the mount an existing path lives on, after resolving symlinks.
+#[cfg(target_os = "linux")]
+pub fn mount_of(path: &Path) -> Result<MountEntry> {
+    let path = std::fs::canonicalize(path)?;
+    let mounts = parse_mountinfo(&std::fs::read_to_string("/proc/self/mountinfo")?);
+    owning_mount(&mounts, &path)
+        .cloned()
+        .ok_or_else(|| anyhow!("no mount found for {:?}", path))
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn mount_of(_path: &Path) -> Result<MountEntry> {
+    Err(anyhow!("mount discovery is only available on Linux"))
+}
+
+fn is_pseudo_filesystem(fs_type: &str) -> bool {
+    PSEUDO_FILESYSTEMS.contains(&fs_type)
+}
+
+/// Space, inode and read-only information reported by `statvfs(3)`.
+#[derive(Debug, Clone, Copy)]
+pub struct FilesystemStats {
+    pub total: u64,
+    pub free: u64,
+    pub available: u64,
+    pub total_inodes: u64,
+    pub free_inodes: u64,
+    pub is_read_only: bool,
+}
+
+// The `statvfs` field widths differ between platforms, so the casts are only no-ops on some.
+#[cfg(unix)]
+#[allow(clippy::unnecessary_cast)]
+pub fn statvfs(path: &str) -> Result<FilesystemStats> {
+    use std::ffi::CString;
+
+    let c_path = CString::new(path)?;
+    let mut stats = std::mem::MaybeUninit::<libc::statvfs>::uninit();
+    // SAFETY: `c_path` is a valid NUL-terminated string and `stats` points to writable memory
+    // large enough for a `statvfs` struct, which the call fully initialises on success.
+    let result = unsafe { libc::statvfs(c_path.as_ptr(), stats.as_mut_ptr()) };
+    if result != 0 {
+        return Err(std::io::Error::last_os_error().into());
+    }
+    // SAFETY: `statvfs` returned 0, so the struct has been initialised.
+    let stats = unsafe { stats.assume_init() };
+
+    let fragment_size = stats.f_frsize as u64;
+    Ok(FilesystemStats {
+        total: stats.f_blocks as u64 * fragment_size,
+        free: stats.f_bfree as u64 * fragment_size,
+        available: stats.f_bavail as u64 * fragment_size,
+        total_inodes: stats.f_files as u64,
+        free_inodes: stats.f_ffree as u64,
+        is_read_only: stats.f_flag & libc::ST_RDONLY != 0,
+    })
+}
+
+#[cfg(not(unix))]
+pub fn statvfs(_path: &str) -> Result<FilesystemStats> {
+    Err(anyhow!("statvfs is only available on Unix systems"))
+}
+
+/// Lists the real (non-pseudo) filesystems mounted on this host, with usage from `statvfs`.
+#[cfg(target_os = "linux")]
+pub fn scan_mounts() -> Result<Vec<SystemDiskInfo>> {
+    let contents = std::fs::read_to_string("/proc/self/mountinfo")?;
+    let mut disks = Vec::new();
+
+    for mount in parse_mountinfo(&contents) {
+        if is_pseudo_filesystem(&mount.fs_type) {
+            continue;
+        }
+
+        let stats = match statvfs(&mount.mount_point) {
+            Ok(stats) if stats.total > 0 => stats,
+            _ => continue,
+        };
+
+        let name = match mount.mount_point.rsplit('/').next() {
+            Some(last) if !last.is_empty() => last.to_string(),
+            _ => mount.mount_point.clone(),
+        };
+
+        disks.push(SystemDiskInfo {
+            name,
+            path: mount.mount_point.clone(),
+            total_space: stats.total,
+            used_space: stats.total - stats.free,
+            available_space: stats.available,
+            mount_point: mount.mount_point,
+            fs_type: Some(mount.fs_type),
+            device: Some(mount.device),
+            total_inodes: Some(stats.total_inodes),
+            free_inodes: Some(stats.free_inodes),
+            is_read_only: stats.is_read_only,
+        });
+    }
+
+    Ok(disks)
+}
+
+#[cfg(not(target_os = "linux"))]
+pub fn scan_mounts() -> Result<Vec<SystemDiskInfo>> {
+    Err(anyhow!("mount discovery is only available on Linux"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unescapes_octal_sequences() {
+        assert_eq!(unescape_mount_field(r"/mnt/my\040disk"), "/mnt/my disk");
+        assert_eq!(unescape_mount_field(r"/mnt/tab\011here"), "/mnt/tab\there");
+        assert_eq!(unescape_mount_field(r"/mnt/back\134slash"), r"/mnt/back\slash");
+    }
+
+    #[test]
+    fn keeps_incomplete_or_non_octal_escapes() {
+        assert_eq!(unescape_mount_field(r"/mnt/a\04"), r"/mnt/a\04");
+        assert_eq!(unescape_mount_field(r"/mnt/a\089"), r"/mnt/a\089");
+        assert_eq!(unescape_mount_field("/mnt/plain"), "/mnt/plain");
+    }
+
+    #[test]
+    fn parses_entries_with_and_without_optional_fields() {
+        let contents = "\
+22 1 8:1 / / rw,relatime shared:1 - ext4 /dev/sda1 rw
+35 22 8:17 / /mnt/media\\040library rw,relatime shared:12 master:3 - xfs /dev/sdb1 rw
+36 22 0:5 / /proc rw,nosuid - proc proc rw
+";
+        let mounts = parse_mountinfo(contents);
+        assert_eq!(
+            mounts,
+            vec![
+                MountEntry { mount_point: "/".into(), fs_type: "ext4".into(), device: "/dev/sda1".into() },
+                MountEntry {
+                    mount_point: "/mnt/media library".into(),
+                    fs_type: "xfs".into(),
+                    device: "/dev/sdb1".into(),
+                },
+                MountEntry { mount_point: "/proc".into(), fs_type: "proc".into(), device: "proc".into() },
+            ]
+        );
+    }
+
+    #[test]
+    fn finds_the_innermost_mount_of_a_path() {
+        let mount = |point: &str| MountEntry { mount_point: point.into(), fs_type: "ext4".into(), device: "d".into() };
+        let mounts = vec![mount("/"), mount("/mnt/disk1"), mount("/mnt/disk1/cache")];
+        let owner = |path: &str| owning_mount(&mounts, Path::new(path)).map(|m| m.mount_point.as_str());
+        assert_eq!(owner("/mnt/disk1/videos"), Some("/mnt/disk1"));
+        assert_eq!(owner("/mnt/disk1/cache/x"), Some("/mnt/disk1/cache"));
+        // Component-wise: /mnt/disk10 is not under /mnt/disk1.
+        assert_eq!(owner("/mnt/disk10"), Some("/"));
+        assert_eq!(owning_mount(&mounts[1..], Path::new("/srv")), None);
+    }
+
+    #[test]
+    fn later_mounts_replace_earlier_ones_and_bad_lines_are_skipped() {
+        let contents = "\
+40 22 8:17 / /mnt/disk1 rw - ext4 /dev/sdb1 rw
+garbage line without separator
+41 22 8:33 / /mnt/disk1 rw - xfs /dev/sdc1 rw
+42 22 8:49 / /mnt/short rw - ext4
+";
+        let mounts = parse_mountinfo(contents);
+        assert_eq!(
+            mounts,
+            vec![MountEntry { mount_point: "/mnt/disk1".into(), fs_type: "xfs".into(), device: "/dev/sdc1".into() }]
+        );
+    }
+}
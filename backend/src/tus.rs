@@ -0,0 +1,498 @@
+//! tus 1.0 resumable uploads (https://tus.io/protocols/resumable-upload).
+//!
+//! Supports the core protocol plus the `creation`, `termination`, `checksum` and `expiration`
+//! extensions. Progress is kept in `upload_sessions` like chunked uploads, and finished uploads go
+//! through the same kind configuration, deduplication and hooks as `upload_chunk`.
+
+use axum::{
+    body::Body,
+    extract::{Extension, Path as UrlPath, Request, State},
+    http::{header, HeaderMap, HeaderName, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use chrono::{DateTime, Utc};
+use futures_util::StreamExt;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use std::{
+    collections::{BTreeSet, HashMap},
+    path::Path,
+    sync::Mutex,
+};
+use tokio::{fs, io::AsyncWriteExt};
+use uuid::Uuid;
+use crate::{
+    database::{
+        advance_upload_offset, get_or_create_upload_session, get_upload_session,
+        set_upload_session_status,
+    },
+    models::{Claims, CreateUploadSession, UploadSession},
+    storage::{sha256_file, InFlightGuard},
+    upload::{
+        authorize_session, finish_session, get_stream_file_path, get_temp_dir, is_sha256_hex, is_valid_upload_id, prepare_target,
+        remove_session_files, store_completed_upload, CompletedUpload, UploadConfig, UploadError,
+        UPLOAD_EXPIRY_HOURS,
+    },
+};
+
+pub const TUS_VERSION: &str = "1.0.0";
+const TUS_EXTENSIONS: &str = "creation,termination,checksum,expiration";
+const TUS_CHECKSUM_ALGORITHMS: &str = "sha1,sha256";
+const OFFSET_CONTENT_TYPE: &str = "application/offset+octet-stream";
+/// `protocol` of the upload sessions created here.
+const TUS_PROTOCOL: &str = "tus";
+
+/// Uploads with a PATCH request writing to their file in this process.
+static ACTIVE_PATCHES: Mutex<BTreeSet<Uuid>> = Mutex::new(BTreeSet::new());
+
+const TUS_RESUMABLE: HeaderName = HeaderName::from_static("tus-resumable");
+const TUS_VERSION_HEADER: HeaderName = HeaderName::from_static("tus-version");
+const TUS_EXTENSION: HeaderName = HeaderName::from_static("tus-extension");
+const TUS_MAX_SIZE: HeaderName = HeaderName::from_static("tus-max-size");
+const TUS_CHECKSUM_ALGORITHM: HeaderName = HeaderName::from_static("tus-checksum-algorithm");
+const UPLOAD_LENGTH: HeaderName = HeaderName::from_static("upload-length");
+const UPLOAD_OFFSET: HeaderName = HeaderName::from_static("upload-offset");
+const UPLOAD_METADATA: HeaderName = HeaderName::from_static("upload-metadata");
+const UPLOAD_EXPIRES: HeaderName = HeaderName::from_static("upload-expires");
+const UPLOAD_CHECKSUM: HeaderName = HeaderName::from_static("upload-checksum");
+/// Where the finished file was stored, for use as `video_file`/`thumbnail` etc.
+const UPLOAD_REFERENCE: HeaderName = HeaderName::from_static("x-upload-reference");
+const UPLOAD_DISK_ID: HeaderName = HeaderName::from_static("x-upload-disk-id");
+const UPLOAD_SHA256: HeaderName = HeaderName::from_static("x-upload-sha256");
+
+/// Status defined by the checksum extension for a body that does not match `Upload-Checksum`.
+fn checksum_mismatch() -> StatusCode {
+    StatusCode::from_u16(460).unwrap()
+}
+
+/// Loads a tus session addressed by a request, answering 404 for unknown or terminated uploads,
+/// 403 for another admin's upload and 410 for expired or failed ones.
+async fn find_upload(
+    pool: &PgPool,
+    claims: &Claims,
+    kind_name: &str,
+    upload_id: &str,
+) -> Result<UploadSession, StatusCode> {
+    if !is_valid_upload_id(upload_id) {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    let session = get_upload_session(pool, upload_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .filter(|session| session.kind == kind_name && session.protocol == TUS_PROTOCOL)
+        .ok_or(StatusCode::NOT_FOUND)?;
+    authorize_session(&session, claims)?;
+
+    match session.status.as_str() {
+        "uploading" if session.expires_at < Utc::now() => {
+            let _ = set_upload_session_status(pool, upload_id, "uploading", "expired").await;
+            remove_session_files(&session).await;
+            Err(StatusCode::GONE)
+        }
+        "expired" | "failed" => Err(StatusCode::GONE),
+        "cancelled" => Err(StatusCode::NOT_FOUND),
+        _ => Ok(session),
+    }
+}
+
+fn header_str<'a>(headers: &'a HeaderMap, name: &HeaderName) -> Option<&'a str> {
+    headers.get(name).and_then(|value| value.to_str().ok())
+}
+
+fn header_u64(headers: &HeaderMap, name: &HeaderName) -> Result<Option<u64>, StatusCode> {
+    match header_str(headers, name) {
+        Some(value) => value.trim().parse().map(Some).map_err(|_| StatusCode::BAD_REQUEST),
+        None => Ok(None),
+    }
+}
+
+/// Parses `Upload-Metadata`: comma separated `key base64(value)` pairs, the value being optional.
+fn parse_metadata(value: &str) -> Result<HashMap<String, String>, StatusCode> {
+    let mut metadata = HashMap::new();
+    for pair in value.split(',').map(str::trim).filter(|pair| !pair.is_empty()) {
+        let mut parts = pair.splitn(2, ' ');
+        let key = parts.next().unwrap_or_default().to_string();
+        let value = match parts.next() {
+            Some(encoded) => {
+                let bytes = BASE64.decode(encoded.trim()).map_err(|_| StatusCode::BAD_REQUEST)?;
+                String::from_utf8(bytes).map_err(|_| StatusCode::BAD_REQUEST)?
+            }
+            None => String::new(),
+        };
+        metadata.insert(key, value);
+    }
+    Ok(metadata)
+}
+
+fn http_date(time: DateTime<Utc>) -> String {
+    time.format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
+
+fn rejected(status: StatusCode, message: impl Into<String>) -> Response {
+    (status, message.into()).into_response()
+}
+
+/// Validates `Tus-Resumable` on every request but OPTIONS and stamps it on every response.
+pub async fn tus_protocol(request: Request, next: Next) -> Response {
+    let supported = request.method() == Method::OPTIONS
+        || request.headers().get(TUS_RESUMABLE).and_then(|v| v.to_str().ok()) == Some(TUS_VERSION);
+
+    let mut response = if supported {
+        next.run(request).await
+    } else {
+        let mut response = StatusCode::PRECONDITION_FAILED.into_response();
+        response
+            .headers_mut()
+            .insert(TUS_VERSION_HEADER, HeaderValue::from_static(TUS_VERSION));
+        response
+    };
+    response
+        .headers_mut()
+        .insert(TUS_RESUMABLE, HeaderValue::from_static(TUS_VERSION));
+    response
+}
+
+/// `OPTIONS` on either tus URL: advertises the protocol version, extensions and size limit.
+pub async fn tus_options(
+    State(config): State<UploadConfig>,
+    UrlPath(params): UrlPath<HashMap<String, String>>,
+) -> Result<Response, StatusCode> {
+    let kind = params
+        .get("kind")
+        .and_then(|kind| config.kinds.get(kind))
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    Ok((
+        StatusCode::NO_CONTENT,
+        [
+            (TUS_VERSION_HEADER, TUS_VERSION.to_string()),
+            (TUS_EXTENSION, TUS_EXTENSIONS.to_string()),
+            (TUS_MAX_SIZE, kind.max_file_size.to_string()),
+            (TUS_CHECKSUM_ALGORITHM, TUS_CHECKSUM_ALGORITHMS.to_string()),
+        ],
+    )
+        .into_response())
+}
+
+/// `POST /api/admin/tus/:kind` (creation extension).
+pub async fn tus_create(
+    State(pool): State<PgPool>,
+    State(config): State<UploadConfig>,
+    Extension(claims): Extension<Claims>,
+    UrlPath(kind_name): UrlPath<String>,
+    headers: HeaderMap,
+) -> Result<Response, StatusCode> {
+    let kind = config.kinds.get(&kind_name).ok_or(StatusCode::NOT_FOUND)?;
+
+    // Deferred lengths are not supported, so Upload-Length is mandatory.
+    let length = header_u64(&headers, &UPLOAD_LENGTH)?.ok_or(StatusCode::BAD_REQUEST)?;
+    if length > kind.max_file_size {
+        return Ok(StatusCode::PAYLOAD_TOO_LARGE.into_response());
+    }
+
+    let metadata = match header_str(&headers, &UPLOAD_METADATA) {
+        Some(value) => parse_metadata(value)?,
+        None => HashMap::new(),
+    };
+    let file_name = metadata
+        .get("filename")
+        .or_else(|| metadata.get("name"))
+        .cloned()
+        .unwrap_or_default();
+    if let Err(message) = kind.extension_for(&file_name) {
+        return Ok(rejected(StatusCode::UNSUPPORTED_MEDIA_TYPE, message));
+    }
+    let file_sha256 = metadata.get("sha256").map(|hash| hash.to_ascii_lowercase());
+    match &file_sha256 {
+        Some(hash) if !is_sha256_hex(hash) => {
+            return Ok(rejected(StatusCode::BAD_REQUEST, "Geçersiz dosya SHA-256 özeti"));
+        }
+        None if kind.requires_file_sha256 => {
+            return Ok(rejected(StatusCode::BAD_REQUEST, "Dosya SHA-256 özeti eksik"));
+        }
+        _ => {}
+    }
+
+    let upload_id = Uuid::new_v4().to_string();
+    fs::create_dir_all(get_temp_dir())
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    fs::File::create(get_stream_file_path(&upload_id))
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let session = get_or_create_upload_session(&pool, &CreateUploadSession {
+        id: upload_id.clone(),
+        owner: Some(claims.sub.clone()),
+        kind: kind_name.clone(),
+        protocol: TUS_PROTOCOL,
+        file_name,
+        total_size: length as i64,
+        total_chunks: 0,
+        file_sha256,
+        expires_in_hours: UPLOAD_EXPIRY_HOURS,
+    })
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    Ok((
+        StatusCode::CREATED,
+        [
+            (header::LOCATION, format!("/api/admin/tus/{}/{}", kind_name, upload_id)),
+            (UPLOAD_EXPIRES, http_date(session.expires_at)),
+            (UPLOAD_OFFSET, "0".to_string()),
+        ],
+    )
+        .into_response())
+}
+
+fn offset_headers(session: &UploadSession) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert(UPLOAD_OFFSET, session.received_bytes.into());
+    headers.insert(UPLOAD_LENGTH, session.total_size.into());
+    headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-store"));
+    if session.status == "completed" {
+        if let Some(reference) = session.file_reference.as_deref().and_then(|r| HeaderValue::from_str(r).ok()) {
+            headers.insert(UPLOAD_REFERENCE, reference);
+        }
+        if let Some(disk_id) = session.disk_id.and_then(|id| HeaderValue::from_str(&id.to_string()).ok()) {
+            headers.insert(UPLOAD_DISK_ID, disk_id);
+        }
+        if let Some(sha256) = session.file_sha256.as_deref().and_then(|h| HeaderValue::from_str(h).ok()) {
+            headers.insert(UPLOAD_SHA256, sha256);
+        }
+    } else if let Ok(expires) = HeaderValue::from_str(&http_date(session.expires_at)) {
+        headers.insert(UPLOAD_EXPIRES, expires);
+    }
+    headers
+}
+
+/// `HEAD /api/admin/tus/:kind/:id`: reports how many bytes the server has.
+pub async fn tus_head(
+    State(pool): State<PgPool>,
+    Extension(claims): Extension<Claims>,
+    UrlPath((kind_name, upload_id)): UrlPath<(String, String)>,
+) -> Result<Response, StatusCode> {
+    let session = find_upload(&pool, &claims, &kind_name, &upload_id).await?;
+    Ok((StatusCode::OK, offset_headers(&session)).into_response())
+}
+
+enum ChecksumHasher {
+    Sha1(Sha1),
+    Sha256(Sha256),
+}
+
+impl ChecksumHasher {
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            ChecksumHasher::Sha1(hasher) => hasher.update(data),
+            ChecksumHasher::Sha256(hasher) => hasher.update(data),
+        }
+    }
+
+    fn finalize(self) -> Vec<u8> {
+        match self {
+            ChecksumHasher::Sha1(hasher) => hasher.finalize().to_vec(),
+            ChecksumHasher::Sha256(hasher) => hasher.finalize().to_vec(),
+        }
+    }
+}
+
+/// Parses `Upload-Checksum: <algorithm> <base64 digest>`.
+fn parse_checksum(value: &str) -> Result<(ChecksumHasher, Vec<u8>), StatusCode> {
+    let (algorithm, digest) = value.trim().split_once(' ').ok_or(StatusCode::BAD_REQUEST)?;
+    let hasher = match algorithm {
+        "sha1" => ChecksumHasher::Sha1(Sha1::new()),
+        "sha256" => ChecksumHasher::Sha256(Sha256::new()),
+        _ => return Err(StatusCode::BAD_REQUEST),
+    };
+    let digest = BASE64.decode(digest.trim()).map_err(|_| StatusCode::BAD_REQUEST)?;
+    Ok((hasher, digest))
+}
+
+/// `PATCH /api/admin/tus/:kind/:id`: appends the body at `Upload-Offset` and finishes the upload
+/// once all bytes have arrived.
+pub async fn tus_patch(
+    State(pool): State<PgPool>,
+    State(config): State<UploadConfig>,
+    Extension(claims): Extension<Claims>,
+    UrlPath((kind_name, upload_id)): UrlPath<(String, String)>,
+    headers: HeaderMap,
+    body: Body,
+) -> Result<Response, StatusCode> {
+    if header_str(&headers, &header::CONTENT_TYPE) != Some(OFFSET_CONTENT_TYPE) {
+        return Err(StatusCode::UNSUPPORTED_MEDIA_TYPE);
+    }
+    let offset = header_u64(&headers, &UPLOAD_OFFSET)?.ok_or(StatusCode::BAD_REQUEST)?;
+    let mut checksum = match header_str(&headers, &UPLOAD_CHECKSUM) {
+        Some(value) => Some(parse_checksum(value)?),
+        None => None,
+    };
+
+    let session = find_upload(&pool, &claims, &kind_name, &upload_id).await?;
+    // Only one PATCH per upload writes the file at a time; the guard is taken after the session
+    // is loaded, so the offset read below is only stale if another process wrote meanwhile, and
+    // recording the new offset fails then.
+    let id = Uuid::parse_str(&upload_id).map_err(|_| StatusCode::NOT_FOUND)?;
+    let _guard = InFlightGuard::acquire(&ACTIVE_PATCHES, id).ok_or(StatusCode::LOCKED)?;
+    let session = get_upload_session(&pool, &session.id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    if session.status != "uploading" || offset != session.received_bytes as u64 {
+        return Err(StatusCode::CONFLICT);
+    }
+
+    let path = get_stream_file_path(&upload_id);
+    let existing = fs::OpenOptions::new()
+        .write(true)
+        .open(&path)
+        .await
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+    // Drop whatever an interrupted request may have left after the last confirmed offset.
+    existing.set_len(offset).await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    drop(existing);
+    let mut file = fs::OpenOptions::new()
+        .append(true)
+        .open(&path)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    let mut received = 0u64;
+    let mut stream = body.into_data_stream();
+    while let Some(chunk) = stream.next().await {
+        // A broken connection keeps what was written so far; tus clients resume from HEAD.
+        let chunk = match chunk {
+            Ok(chunk) => chunk,
+            Err(_) => break,
+        };
+        received += chunk.len() as u64;
+        if offset + received > session.total_size as u64 {
+            let _ = file.set_len(offset).await;
+            return Ok(rejected(StatusCode::PAYLOAD_TOO_LARGE, "Upload-Length aşıldı"));
+        }
+        if let Some((hasher, _)) = checksum.as_mut() {
+            hasher.update(&chunk);
+        }
+        file.write_all(&chunk)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    }
+    file.flush().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if let Some((hasher, expected)) = checksum {
+        if hasher.finalize() != expected {
+            let _ = file.set_len(offset).await;
+            return Err(checksum_mismatch());
+        }
+    }
+    drop(file);
+
+    let mut tx = pool.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let mut session = advance_upload_offset(&mut *tx, &upload_id, offset as i64, (offset + received) as i64)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::CONFLICT)?;
+
+    // Only the request that moves the session to `finalizing` stores the file.
+    let finalizing = session.received_bytes == session.total_size
+        && set_upload_session_status(&mut *tx, &upload_id, "uploading", "finalizing")
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+
+    if finalizing {
+        let result = finish_upload(&pool, &config, &session).await;
+        match finish_session(&pool, &session, result).await {
+            Ok(_) => {}
+            Err(UploadError::Rejected(message)) => {
+                return Ok(rejected(StatusCode::UNPROCESSABLE_ENTITY, message));
+            }
+            Err(UploadError::Status(status)) => return Err(status),
+        }
+        session = get_upload_session(&pool, &upload_id)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+            .ok_or(StatusCode::NOT_FOUND)?;
+    }
+
+    Ok((StatusCode::NO_CONTENT, offset_headers(&session)).into_response())
+}
+
+/// Moves a fully received upload to its destination and registers it like a chunked upload.
+async fn finish_upload(
+    pool: &PgPool,
+    config: &UploadConfig,
+    session: &UploadSession,
+) -> Result<CompletedUpload, UploadError> {
+    let kind = config.kinds.get(&session.kind).ok_or(StatusCode::NOT_FOUND)?;
+    let extension = kind.extension_for(&session.file_name).map_err(UploadError::Rejected)?;
+    let target = prepare_target(pool, config, kind, &extension).await?;
+
+    let source = get_stream_file_path(&session.id);
+    move_file(&source, &target.final_path)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let final_path = target.final_path.clone();
+
+    let sha256 = match sha256_file(&final_path).await {
+        Ok(sha256) => sha256,
+        Err(_) => return Err(restore_stream_file(&final_path, &source, StatusCode::INTERNAL_SERVER_ERROR).await),
+    };
+    if let Some(expected) = &session.file_sha256 {
+        if !expected.eq_ignore_ascii_case(&sha256) {
+            let _ = fs::remove_file(&final_path).await;
+            return Err(UploadError::Rejected(format!(
+                "Dosya doğrulanamadı: SHA-256 uyuşmuyor ({})",
+                session.file_name
+            )));
+        }
+    }
+
+    match store_completed_upload(pool, config, &session.kind, kind, target, &sha256, session.total_size as u64).await {
+        Err(UploadError::Status(status)) => Err(restore_stream_file(&final_path, &source, status).await),
+        result => result,
+    }
+}
+
+/// Renames a file, copying it when the destination is on another filesystem.
+async fn move_file(source: &Path, target: &Path) -> std::io::Result<()> {
+    if fs::rename(source, target).await.is_err() {
+        fs::copy(source, target).await?;
+        let _ = fs::remove_file(source).await;
+    }
+    Ok(())
+}
+
+/// Puts a file back where PATCH requests append to after finishing failed, so the session can
+/// return to `uploading` and be finished again. Fails the upload when that is not possible.
+async fn restore_stream_file(final_path: &Path, stream_path: &Path, status: StatusCode) -> UploadError {
+    match move_file(final_path, stream_path).await {
+        Ok(()) => UploadError::Status(status),
+        Err(e) => {
+            tracing::error!("Could not restore upload file {:?}: {}", stream_path, e);
+            let _ = fs::remove_file(final_path).await;
+            UploadError::Rejected("Dosya kaydedilemedi, yükleme yeniden başlatılmalı".to_string())
+        }
+    }
+}
+
+/// `DELETE /api/admin/tus/:kind/:id` (termination extension).
+pub async fn tus_terminate(
+    State(pool): State<PgPool>,
+    Extension(claims): Extension<Claims>,
+    UrlPath((kind_name, upload_id)): UrlPath<(String, String)>,
+) -> Result<Response, StatusCode> {
+    let session = find_upload(&pool, &claims, &kind_name, &upload_id).await?;
+    let cancelled = set_upload_session_status(&pool, &upload_id, "uploading", "cancelled")
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if !cancelled {
+        return Err(StatusCode::CONFLICT);
+    }
+    remove_session_files(&session).await;
+    Ok(StatusCode::NO_CONTENT.into_response())
+}
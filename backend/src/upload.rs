@@ -1,26 +1,179 @@
+use async_trait::async_trait;
 use axum::{
-    extract::{Multipart, State, FromRef, Query},
+    extract::{Extension, Multipart, Path as UrlPath, State, FromRef, Query},
     http::StatusCode,
     Json,
 };
 use sqlx::PgPool;
 use std::{
-    fs::{self},
-    io::{Write},
     path::{Path, PathBuf},
     collections::HashMap,
+    sync::Arc,
 };
+use tokio::{
+    fs,
+    io::{AsyncReadExt, AsyncWriteExt},
+};
+use chrono::{DateTime, Utc};
 use uuid::Uuid;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
 use crate::{
-    database::{get_available_disk, update_disk_usage},
-    models::ApiResponse,
+    database::{
+        complete_upload_session, get_available_disk, get_disk_by_id, get_or_create_upload_session,
+        get_upload_session, list_upload_sessions, lock_uploading_session, mark_upload_chunk_received,
+        prune_upload_sessions, set_media_blob_mime_type, set_upload_session_status,
+        take_expired_upload_sessions, update_disk_usage,
+    },
+    filetype::{self, FileType},
+    images::ImageVariantsHook,
+    media::{self, store_media},
+    models::{ApiResponse, Claims, CreateUploadSession, DiskStorage, UploadSession},
+    storage::sha256_file,
 };
 
 #[derive(Clone)]
 pub struct UploadConfig {
     pub upload_dir: String,
     pub max_file_size: usize,
+    /// Upload kinds keyed by the name used in `/api/admin/upload/:kind/chunk`.
+    pub kinds: HashMap<String, UploadKind>,
+}
+
+/// Where the assembled file of an upload kind is stored.
+#[derive(Clone, Debug)]
+pub enum UploadDestination {
+    /// On the least used active `DiskStorage`; the stored reference is the bare file name.
+    Disk,
+    /// In a sub-directory of `upload_dir`; the stored reference is `<dir>/<file name>`.
+    Local(&'static str),
+}
+
+/// Details of a file that has been assembled, verified and registered.
+pub struct CompletedUpload {
+    pub kind: String,
+    pub path: PathBuf,
+    pub reference: String,
+    pub disk_id: Option<Uuid>,
+    pub disk_location: String,
+    pub size: u64,
+    pub sha256: String,
+    pub mime_type: &'static str,
+    pub deduplicated: bool,
+}
+
+/// Processing steps run around storing an upload of a kind.
+#[async_trait]
+pub trait UploadHook: Send + Sync {
+    /// Rewrites the verified file before it is hashed and registered; returns whether the
+    /// file changed.
+    async fn before_store(&self, _path: &Path, _file_type: FileType) -> anyhow::Result<bool> {
+        Ok(false)
+    }
+
+    async fn after_store(&self, pool: &PgPool, upload: &CompletedUpload) -> anyhow::Result<()>;
+}
+
+/// Everything that differs between upload kinds: limits, accepted files and destination.
+#[derive(Clone)]
+pub struct UploadKind {
+    pub label: &'static str,
+    /// Kind recorded in `media_blobs` for deduplication.
+    pub media_kind: &'static str,
+    pub max_chunk_size: usize,
+    pub max_file_size: u64,
+    pub allowed_extensions: &'static [&'static str],
+    pub default_extension: &'static str,
+    /// Formats accepted after sniffing the assembled file's magic bytes.
+    pub allowed_types: &'static [FileType],
+    pub destination: UploadDestination,
+    /// Whether the client must send the whole file's SHA-256 before the first byte is stored.
+    pub requires_file_sha256: bool,
+    pub hooks: Vec<Arc<dyn UploadHook>>,
+}
+
+impl UploadKind {
+    pub fn extension_for(&self, file_name: &str) -> Result<String, String> {
+        let extension = Path::new(file_name)
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_ascii_lowercase())
+            .unwrap_or_else(|| self.default_extension.to_string());
+        
+        if self.allowed_extensions.contains(&extension.as_str()) {
+            Ok(extension)
+        } else {
+            Err(format!(
+                "Desteklenmeyen dosya türü: .{} (izin verilenler: {})",
+                extension,
+                self.allowed_extensions.join(", ")
+            ))
+        }
+    }
+}
+
+pub const MB: usize = 1024 * 1024;
+/// How long an unfinished upload is kept before its temp files are removed.
+pub const UPLOAD_EXPIRY_HOURS: i32 = 24;
+/// How long finished, failed and cancelled sessions stay visible.
+const SESSION_HISTORY_DAYS: i32 = 7;
+
+fn default_upload_kinds(max_video_size: usize) -> HashMap<String, UploadKind> {
+    let kinds = [
+        ("video", UploadKind {
+            label: "Video",
+            media_kind: media::VIDEO,
+            max_chunk_size: 10 * MB,
+            max_file_size: max_video_size as u64,
+            allowed_extensions: &["mp4", "mov", "webm", "mkv"],
+            default_extension: "mp4",
+            allowed_types: filetype::VIDEO_TYPES,
+            destination: UploadDestination::Disk,
+            requires_file_sha256: true,
+            hooks: Vec::new(),
+        }),
+        ("thumbnail", UploadKind {
+            label: "Thumbnail",
+            media_kind: media::THUMBNAIL,
+            max_chunk_size: 2 * MB,
+            max_file_size: 5 * MB as u64,
+            allowed_extensions: &["jpg", "jpeg", "png", "webp"],
+            default_extension: "jpg",
+            allowed_types: filetype::IMAGE_TYPES,
+            destination: UploadDestination::Local("thumbnails"),
+            requires_file_sha256: false,
+            hooks: vec![Arc::new(ImageVariantsHook)],
+        }),
+        ("channel-image", UploadKind {
+            label: "Görsel",
+            media_kind: media::CHANNEL_IMAGE,
+            max_chunk_size: MB,
+            max_file_size: 2 * MB as u64,
+            allowed_extensions: &["jpg", "jpeg", "png", "webp"],
+            default_extension: "jpg",
+            allowed_types: filetype::IMAGE_TYPES,
+            destination: UploadDestination::Local("channels"),
+            requires_file_sha256: false,
+            hooks: vec![Arc::new(ImageVariantsHook)],
+        }),
+        ("banner", UploadKind {
+            label: "Banner",
+            media_kind: media::BANNER,
+            max_chunk_size: 2 * MB,
+            max_file_size: 8 * MB as u64,
+            allowed_extensions: &["jpg", "jpeg", "png", "webp"],
+            default_extension: "jpg",
+            allowed_types: filetype::IMAGE_TYPES,
+            destination: UploadDestination::Local("banners"),
+            requires_file_sha256: false,
+            hooks: vec![Arc::new(ImageVariantsHook)],
+        }),
+    ];
+    
+    kinds
+        .into_iter()
+        .map(|(name, kind)| (name.to_string(), kind))
+        .collect()
 }
 
 #[derive(Clone)]
@@ -43,9 +196,11 @@ impl FromRef<AppState> for UploadConfig {
 
 impl Default for UploadConfig {
     fn default() -> Self {
+        let max_file_size = 2 * 1024 * 1024 * 1024; // 2GB
         Self {
             upload_dir: "uploads".to_string(),
-            max_file_size: 2 * 1024 * 1024 * 1024, // 2GB
+            max_file_size,
+            kinds: default_upload_kinds(max_file_size),
         }
     }
 }
@@ -58,6 +213,12 @@ pub struct ChunkUploadRequest {
     pub total_size: u64,
     pub file_name: String,
     pub upload_id: Option<String>,
+    /// Hex-encoded SHA-256 of this chunk's bytes, checked before the chunk is stored.
+    pub chunk_sha256: Option<String>,
+    /// Hex-encoded SHA-256 of the whole file, checked after the chunks are combined.
+    /// It only needs to be sent once; later chunks without it keep the stored value.
+    #[serde(default)]
+    pub file_sha256: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -71,13 +232,8 @@ pub struct ChunkUploadResponse {
     pub disk_location: Option<String>,
     pub file_size: Option<u64>,
     pub disk_id: Option<String>,
-}
-
-#[derive(Serialize)]
-pub struct UploadResponse {
-    pub file_path: String,
-    pub disk_location: String,
-    pub file_size: u64,
+    pub file_sha256: Option<String>,
+    pub mime_type: Option<String>,
 }
 
 #[derive(Serialize)]
@@ -85,11 +241,71 @@ pub struct UploadStatusResponse {
     pub upload_id: String,
     pub uploaded_chunks: Vec<u32>,
     pub total_chunks: u32,
+    pub received_bytes: u64,
+    pub total_size: u64,
+    pub status: String,
     pub completed: bool,
     pub file_path: Option<String>,
 }
 
-fn get_temp_dir() -> PathBuf {
+#[derive(Deserialize)]
+pub struct UploadListQuery {
+    /// Comma separated statuses, `all` for every session; defaults to unfinished uploads.
+    pub status: Option<String>,
+    pub owner: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct UploadSummary {
+    pub upload_id: String,
+    pub owner: Option<String>,
+    pub kind: String,
+    pub protocol: String,
+    pub file_name: String,
+    pub status: String,
+    pub received_bytes: u64,
+    pub total_size: u64,
+    pub received_chunks: u32,
+    pub total_chunks: u32,
+    pub progress_percent: f64,
+    pub age_seconds: i64,
+    pub idle_seconds: i64,
+    pub file_path: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl From<UploadSession> for UploadSummary {
+    fn from(session: UploadSession) -> Self {
+        let now = Utc::now();
+        let progress_percent = if session.total_size > 0 {
+            session.received_bytes as f64 / session.total_size as f64 * 100.0
+        } else {
+            100.0
+        };
+        Self {
+            received_chunks: session.received_chunk_numbers().len() as u32,
+            upload_id: session.id,
+            owner: session.owner,
+            kind: session.kind,
+            protocol: session.protocol,
+            file_name: session.file_name,
+            status: session.status,
+            received_bytes: session.received_bytes as u64,
+            total_size: session.total_size as u64,
+            total_chunks: session.total_chunks as u32,
+            progress_percent,
+            age_seconds: (now - session.created_at).num_seconds(),
+            idle_seconds: (now - session.updated_at).num_seconds(),
+            file_path: session.file_reference,
+            created_at: session.created_at,
+            expires_at: session.expires_at,
+        }
+    }
+}
+
+pub fn get_temp_dir() -> PathBuf {
     PathBuf::from("uploads/temp")
 }
 
@@ -97,153 +313,303 @@ fn get_chunk_file_path(upload_id: &str, chunk_number: u32) -> PathBuf {
     get_temp_dir().join(format!("{}_chunk_{}", upload_id, chunk_number))
 }
 
-fn get_upload_info_path(upload_id: &str) -> PathBuf {
-    get_temp_dir().join(format!("{}_info.json", upload_id))
+/// Single growing file used by streamed (tus) uploads.
+pub fn get_stream_file_path(upload_id: &str) -> PathBuf {
+    get_temp_dir().join(format!("{}_stream", upload_id))
 }
 
-async fn save_upload_info(upload_id: &str, info: &ChunkUploadRequest) -> Result<(), std::io::Error> {
-    let temp_dir = get_temp_dir();
-    fs::create_dir_all(&temp_dir)?;
-    
-    let info_path = get_upload_info_path(upload_id);
-    let info_json = serde_json::to_string(info).map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
-    fs::write(info_path, info_json)?;
-    Ok(())
+/// Deletes whatever an unfinished session left in the temp directory.
+pub async fn remove_session_files(session: &UploadSession) {
+    for chunk_number in 1..=session.total_chunks.max(0) as u32 {
+        let _ = fs::remove_file(get_chunk_file_path(&session.id, chunk_number)).await;
+    }
+    let _ = fs::remove_file(get_stream_file_path(&session.id)).await;
 }
 
-pub async fn cleanup_expired_uploads() -> Result<(), std::io::Error> {
-    let temp_dir = get_temp_dir();
-    if !temp_dir.exists() {
-        return Ok(());
+/// Expires abandoned sessions and removes their temp files, then forgets old finished sessions.
+pub async fn cleanup_expired_uploads(pool: &PgPool) -> anyhow::Result<()> {
+    for session in take_expired_upload_sessions(pool).await? {
+        remove_session_files(&session).await;
     }
-    
-    let now = std::time::SystemTime::now();
-    let expiry_duration = std::time::Duration::from_secs(24 * 60 * 60); // 24 hours
-    
-    if let Ok(entries) = fs::read_dir(&temp_dir) {
-        for entry in entries.flatten() {
-            if let Ok(metadata) = entry.metadata() {
-                if let Ok(created) = metadata.created() {
-                    if let Ok(elapsed) = now.duration_since(created) {
-                        if elapsed > expiry_duration {
-                            let path = entry.path();
-                            if path.is_file() {
-                                let _ = fs::remove_file(&path);
-                            }
-                        }
-                    }
-                }
-            }
+    prune_upload_sessions(pool, SESSION_HISTORY_DAYS).await?;
+    remove_stale_part_files().await;
+    Ok(())
+}
+
+/// Chunks still streaming are never older than this; older `.part` files were left by a crash.
+const STALE_PART_AGE: std::time::Duration = std::time::Duration::from_secs(60 * 60);
+
+async fn remove_stale_part_files() {
+    let mut entries = match fs::read_dir(get_temp_dir()).await {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let path = entry.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some("part") {
+            continue;
+        }
+        let stale = entry
+            .metadata()
+            .await
+            .and_then(|metadata| metadata.modified())
+            .ok()
+            .and_then(|modified| modified.elapsed().ok())
+            .is_some_and(|age| age > STALE_PART_AGE);
+        if stale {
+            let _ = fs::remove_file(&path).await;
         }
     }
-    
-    Ok(())
 }
 
 pub async fn cancel_upload(
+    State(pool): State<PgPool>,
+    Extension(claims): Extension<Claims>,
     Query(params): Query<HashMap<String, String>>,
 ) -> Result<Json<ApiResponse<String>>, StatusCode> {
     let upload_id = params.get("upload_id")
         .ok_or(StatusCode::BAD_REQUEST)?;
     
-    let temp_dir = get_temp_dir();
-    let mut removed_files = 0;
+    let session = match get_upload_session(&pool, upload_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+    {
+        Some(session) => session,
+        None => return Ok(Json(ApiResponse::error("Upload bulunamadı".to_string()))),
+    };
+    authorize_session(&session, &claims)?;
     
-    if let Ok(entries) = fs::read_dir(&temp_dir) {
-        for entry in entries.flatten() {
-            if let Some(file_name) = entry.file_name().to_str() {
-                if file_name.starts_with(&format!("{}_", upload_id)) {
-                    if let Ok(_) = fs::remove_file(entry.path()) {
-                        removed_files += 1;
-                    }
-                }
-            }
-        }
+    let cancelled = set_upload_session_status(&pool, upload_id, "uploading", "cancelled")
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if !cancelled {
+        return Ok(Json(ApiResponse::error("Upload iptal edilemez".to_string())));
     }
     
-    if removed_files > 0 {
-        Ok(Json(ApiResponse::success("Upload iptal edildi".to_string())))
-    } else {
-        Ok(Json(ApiResponse::error("Upload bulunamadı".to_string())))
+    remove_session_files(&session).await;
+    Ok(Json(ApiResponse::success("Upload iptal edildi".to_string())))
+}
+
+fn invalid_data(message: String) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message)
+}
+
+/// A chunk streamed into a uniquely named file in the temp directory.
+///
+/// The file is removed on a blocking thread when dropped, unless it was moved into place with
+/// `persist`.
+struct ReceivedChunk {
+    path: PathBuf,
+    size: u64,
+    sha256: String,
+    persisted: bool,
+}
+
+impl ReceivedChunk {
+    async fn persist(mut self, target: &Path) -> Result<(), std::io::Error> {
+        fs::rename(&self.path, target).await?;
+        self.persisted = true;
+        Ok(())
+    }
+}
+
+impl Drop for ReceivedChunk {
+    fn drop(&mut self) {
+        if !self.persisted {
+            let path = std::mem::take(&mut self.path);
+            tokio::task::spawn_blocking(move || std::fs::remove_file(path));
+        }
     }
 }
 
-async fn load_upload_info(upload_id: &str) -> Result<Option<ChunkUploadRequest>, std::io::Error> {
-    let info_path = get_upload_info_path(upload_id);
-    if !info_path.exists() {
-        return Ok(None);
+/// Checks a received chunk against its metadata before it is moved into place.
+fn verify_chunk(chunk: &ReceivedChunk, info: &ChunkUploadRequest) -> Result<(), String> {
+    if info.chunk_number == 0 || info.chunk_number > info.total_chunks {
+        return Err(format!("Geçersiz parça numarası: {}", info.chunk_number));
+    }
+    
+    if chunk.size != info.chunk_size {
+        return Err(format!(
+            "Parça boyutu uyuşmuyor (beklenen {}, alınan {})",
+            info.chunk_size,
+            chunk.size
+        ));
     }
     
-    let info_json = fs::read_to_string(info_path)?;
-    let info: ChunkUploadRequest = serde_json::from_str(&info_json)
-        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
-    Ok(Some(info))
+    let expected = info.chunk_sha256.as_deref()
+        .ok_or_else(|| "Parça SHA-256 özeti eksik".to_string())?;
+    if !chunk.sha256.eq_ignore_ascii_case(expected) {
+        return Err(format!("Parça {} SHA-256 doğrulaması başarısız", info.chunk_number));
+    }
+    
+    Ok(())
+}
+
+/// Upper bound on the chunks of one upload; the session keeps a bit per chunk and cleanup
+/// visits every chunk number.
+const MAX_TOTAL_CHUNKS: u32 = 100_000;
+
+/// Checks that the declared chunk count fits the file and chunk sizes before it is trusted for
+/// the session's bitmap and for removing chunk files.
+///
+/// Every chunk but the last has the same size, so any of them must divide the file into exactly
+/// `total_chunks` parts.
+fn verify_chunk_layout(kind: &UploadKind, info: &ChunkUploadRequest) -> Result<(), String> {
+    if info.chunk_size > kind.max_chunk_size as u64 {
+        return Err(format!("Chunk boyutu çok büyük (max {}MB)", kind.max_chunk_size / MB));
+    }
+    let min_chunks = info.total_size.div_ceil(kind.max_chunk_size as u64).max(1);
+    let consistent = if info.chunk_number == info.total_chunks {
+        info.chunk_size <= info.total_size && (info.chunk_size > 0 || info.total_size == 0)
+    } else {
+        info.chunk_size > 0 && info.total_size.div_ceil(info.chunk_size) == info.total_chunks as u64
+    };
+    if (info.total_chunks as u64) < min_chunks || info.total_chunks > MAX_TOTAL_CHUNKS || !consistent {
+        return Err(format!(
+            "Geçersiz parça sayısı: {} parça, {} bayt dosya",
+            info.total_chunks, info.total_size
+        ));
+    }
+    Ok(())
 }
 
-async fn get_uploaded_chunks(upload_id: &str) -> Vec<u32> {
-    let temp_dir = get_temp_dir();
-    let mut chunks = Vec::new();
+/// Buffer size used when copying chunks into the assembled file.
+const COPY_BUFFER_SIZE: usize = MB;
+
+/// Concatenates all chunks into `final_path` and returns the SHA-256 of the result.
+///
+/// Chunks are streamed through a fixed-size buffer, so memory use does not depend on the chunk
+/// size. Fails with `InvalidData` if a chunk is missing, the size differs from `total_size`, or
+/// the hash differs from the client's `file_sha256`. Chunks are only removed once the file is
+/// valid.
+async fn combine_chunks(session: &UploadSession, final_path: &Path) -> Result<String, std::io::Error> {
+    let total_chunks = session.total_chunks as u32;
+    let mut missing = Vec::new();
+    for chunk_num in 1..=total_chunks {
+        if fs::metadata(get_chunk_file_path(&session.id, chunk_num)).await.is_err() {
+            missing.push(chunk_num);
+        }
+    }
+    if !missing.is_empty() {
+        return Err(invalid_data(format!("Eksik parçalar: {:?}", missing)));
+    }
     
-    if let Ok(entries) = fs::read_dir(&temp_dir) {
-        for entry in entries.flatten() {
-            if let Some(file_name) = entry.file_name().to_str() {
-                if file_name.starts_with(&format!("{}_chunk_", upload_id)) {
-                    if let Some(chunk_str) = file_name.strip_prefix(&format!("{}_chunk_", upload_id)) {
-                        if let Ok(chunk_num) = chunk_str.parse::<u32>() {
-                            chunks.push(chunk_num);
-                        }
-                    }
-                }
+    let mut final_file = fs::File::create(final_path).await?;
+    let mut hasher = Sha256::new();
+    let mut written: u64 = 0;
+    let mut buffer = vec![0u8; COPY_BUFFER_SIZE];
+    
+    for chunk_num in 1..=total_chunks {
+        let mut chunk_file = fs::File::open(get_chunk_file_path(&session.id, chunk_num)).await?;
+        loop {
+            let read = chunk_file.read(&mut buffer).await?;
+            if read == 0 {
+                break;
             }
+            hasher.update(&buffer[..read]);
+            final_file.write_all(&buffer[..read]).await?;
+            written += read as u64;
         }
     }
+    final_file.sync_all().await?;
+    drop(final_file);
     
-    chunks.sort();
-    chunks
-}
-
-async fn combine_chunks(upload_id: &str, info: &ChunkUploadRequest, final_path: &Path) -> Result<(), std::io::Error> {
-    let mut final_file = fs::File::create(final_path)?;
+    let file_sha256 = hex::encode(hasher.finalize());
     
-    for chunk_num in 1..=info.total_chunks {
-        let chunk_path = get_chunk_file_path(upload_id, chunk_num);
-        if chunk_path.exists() {
-            let chunk_data = fs::read(&chunk_path)?;
-            final_file.write_all(&chunk_data)?;
-            fs::remove_file(&chunk_path)?;
+    let verification = if written != session.total_size as u64 {
+        Err(invalid_data(format!("Dosya boyutu uyuşmuyor (beklenen {}, birleşen {})", session.total_size, written)))
+    } else {
+        match &session.file_sha256 {
+            Some(expected) if !expected.eq_ignore_ascii_case(&file_sha256) => {
+                Err(invalid_data("Dosya SHA-256 doğrulaması başarısız".to_string()))
+            }
+            _ => Ok(()),
         }
+    };
+    
+    if let Err(e) = verification {
+        let _ = fs::remove_file(final_path).await;
+        return Err(e);
     }
     
-    let info_path = get_upload_info_path(upload_id);
-    if info_path.exists() {
-        fs::remove_file(&info_path)?;
+    for chunk_num in 1..=total_chunks {
+        fs::remove_file(get_chunk_file_path(&session.id, chunk_num)).await?;
     }
     
-    Ok(())
+    Ok(file_sha256)
 }
 
+pub fn is_sha256_hex(hash: &str) -> bool {
+    hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit())
+}
 
+/// Upload ids come from the client and end up in file names, so only allow safe characters.
+pub fn is_valid_upload_id(upload_id: &str) -> bool {
+    !upload_id.is_empty()
+        && upload_id.len() <= 64
+        && upload_id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
 
-pub async fn upload_video_chunk(
-    State(pool): State<PgPool>,
-    State(config): State<UploadConfig>,
+/// Only the admin who started an upload may continue, query or cancel it. Sessions created
+/// before uploads had owners stay open to every admin.
+pub fn authorize_session(session: &UploadSession, claims: &Claims) -> Result<(), StatusCode> {
+    match &session.owner {
+        Some(owner) if *owner != claims.sub => Err(StatusCode::FORBIDDEN),
+        _ => Ok(()),
+    }
+}
+
+/// Streams the `chunk` field to a temp file, hashing it on the way, and parses `metadata`.
+///
+/// Chunks larger than `max_chunk_size` are rejected as soon as the limit is crossed.
+async fn read_chunk_request(
     mut multipart: Multipart,
-) -> Result<Json<ApiResponse<ChunkUploadResponse>>, StatusCode> {
-    println!("Video chunk upload request received");
-    let mut chunk_data: Option<Vec<u8>> = None;
+    max_chunk_size: usize,
+) -> Result<(ReceivedChunk, ChunkUploadRequest), UploadError> {
+    let mut chunk: Option<ReceivedChunk> = None;
     let mut upload_info: Option<ChunkUploadRequest> = None;
     
-    while let Some(field) = multipart.next_field().await.map_err(|_| StatusCode::BAD_REQUEST)? {
+    while let Some(mut field) = multipart.next_field().await.map_err(|_| StatusCode::BAD_REQUEST)? {
         let name = field.name().unwrap_or("").to_string();
         
         match name.as_str() {
             "chunk" => {
-                chunk_data = Some(field.bytes().await.map_err(|_| StatusCode::BAD_REQUEST)?.to_vec());
+                let temp_dir = get_temp_dir();
+                fs::create_dir_all(&temp_dir)
+                    .await
+                    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+                
+                let mut received = ReceivedChunk {
+                    path: temp_dir.join(format!("{}.part", Uuid::new_v4())),
+                    size: 0,
+                    sha256: String::new(),
+                    persisted: false,
+                };
+                let mut file = fs::File::create(&received.path)
+                    .await
+                    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+                let mut hasher = Sha256::new();
+                
+                while let Some(bytes) = field.chunk().await.map_err(|_| StatusCode::BAD_REQUEST)? {
+                    received.size += bytes.len() as u64;
+                    if received.size > max_chunk_size as u64 {
+                        return Err(UploadError::Rejected(format!(
+                            "Chunk boyutu çok büyük (max {}MB)",
+                            max_chunk_size / MB
+                        )));
+                    }
+                    hasher.update(&bytes);
+                    file.write_all(&bytes)
+                        .await
+                        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+                }
+                file.flush().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+                
+                received.sha256 = hex::encode(hasher.finalize());
+                chunk = Some(received);
             }
             "metadata" => {
-                let metadata_str = String::from_utf8(
-                    field.bytes().await.map_err(|_| StatusCode::BAD_REQUEST)?.to_vec()
-                ).map_err(|_| StatusCode::BAD_REQUEST)?;
+                let metadata_str = field.text().await.map_err(|_| StatusCode::BAD_REQUEST)?;
                 
                 upload_info = Some(
                     serde_json::from_str::<ChunkUploadRequest>(&metadata_str)
@@ -254,340 +620,433 @@ pub async fn upload_video_chunk(
         }
     }
     
-    let chunk_data = chunk_data.ok_or_else(|| {
-        println!("No chunk data received");
-        StatusCode::BAD_REQUEST
-    })?;
-    let mut info = upload_info.ok_or_else(|| {
-        println!("No metadata received");
-        StatusCode::BAD_REQUEST
-    })?;
+    let chunk = chunk.ok_or(StatusCode::BAD_REQUEST)?;
+    let upload_info = upload_info.ok_or(StatusCode::BAD_REQUEST)?;
+    Ok((chunk, upload_info))
+}
+
+/// Accepts one chunk of an upload of the kind named in the URL, and assembles and stores the
+/// file once every chunk has arrived.
+///
+/// Chunks may arrive in any order and in parallel: each one is streamed to its own temp file
+/// and renamed into place, and `next_chunk` is only a hint for sequential clients.
+pub async fn upload_chunk(
+    State(pool): State<PgPool>,
+    State(config): State<UploadConfig>,
+    Extension(claims): Extension<Claims>,
+    UrlPath(kind_name): UrlPath<String>,
+    multipart: Multipart,
+) -> Result<Json<ApiResponse<ChunkUploadResponse>>, StatusCode> {
+    let kind = config.kinds.get(&kind_name).ok_or(StatusCode::NOT_FOUND)?;
+    let (chunk, info) = match read_chunk_request(multipart, kind.max_chunk_size).await {
+        Ok(request) => request,
+        Err(UploadError::Rejected(message)) => return Ok(Json(ApiResponse::error(message))),
+        Err(UploadError::Status(status)) => return Err(status),
+    };
+    
+    if info.total_size > kind.max_file_size {
+        return Ok(Json(ApiResponse::error(format!(
+            "{} boyutu çok büyük (max {}MB)",
+            kind.label,
+            kind.max_file_size / MB as u64
+        ))));
+    }
     
-    println!("Received chunk {} of {} for file: {}", info.chunk_number, info.total_chunks, info.file_name);
+    let extension = match kind.extension_for(&info.file_name) {
+        Ok(extension) => extension,
+        Err(message) => return Ok(Json(ApiResponse::error(message))),
+    };
     
-    if chunk_data.len() > 10 * 1024 * 1024 {
-        return Ok(Json(ApiResponse::error("Chunk boyutu çok büyük (max 10MB)".to_string())));
+    if let Err(message) = verify_chunk(&chunk, &info).and_then(|_| verify_chunk_layout(kind, &info)) {
+        return Ok(Json(ApiResponse::error(message)));
+    }
+    match info.file_sha256.as_deref() {
+        Some(hash) if !is_sha256_hex(hash) => {
+            return Ok(Json(ApiResponse::error("Geçersiz dosya SHA-256 özeti".to_string())));
+        }
+        None if kind.requires_file_sha256 => {
+            return Ok(Json(ApiResponse::error("Dosya SHA-256 özeti eksik".to_string())));
+        }
+        _ => {}
     }
     
-    let upload_id = if let Some(id) = &info.upload_id {
-        id.clone()
-    } else {
-        let new_id = Uuid::new_v4().to_string();
-        info.upload_id = Some(new_id.clone());
-        new_id
+    let upload_id = match &info.upload_id {
+        Some(id) if is_valid_upload_id(id) => id.clone(),
+        Some(_) => return Err(StatusCode::BAD_REQUEST),
+        None => Uuid::new_v4().to_string(),
     };
     
-    if info.total_size > config.max_file_size as u64 {
-        return Ok(Json(ApiResponse::error("Dosya boyutu çok büyük".to_string())));
+    let session = get_or_create_upload_session(&pool, &CreateUploadSession {
+        id: upload_id.clone(),
+        owner: Some(claims.sub.clone()),
+        kind: kind_name.clone(),
+        protocol: "chunked",
+        file_name: info.file_name.clone(),
+        total_size: info.total_size as i64,
+        total_chunks: info.total_chunks as i32,
+        file_sha256: info.file_sha256.as_deref().map(str::to_ascii_lowercase),
+        expires_in_hours: UPLOAD_EXPIRY_HOURS,
+    })
+    .await
+    .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    authorize_session(&session, &claims)?;
+    
+    if session.kind != kind_name
+        || session.protocol != "chunked"
+        || session.total_chunks as u32 != info.total_chunks
+        || session.total_size as u64 != info.total_size
+    {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    if session.status != "uploading" {
+        return Ok(Json(ApiResponse::error(format!("Upload artık devam etmiyor ({})", session.status))));
     }
     
-    save_upload_info(&upload_id, &info)
+    // The row lock orders this chunk against other chunks of the upload, so exactly one
+    // request sees the last bit set and moves the session to `finalizing`.
+    let chunk_size = chunk.size as i64;
+    let mut tx = pool.begin().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    if lock_uploading_session(&mut *tx, &upload_id)
         .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .is_none()
+    {
+        return Ok(Json(ApiResponse::error("Upload artık devam etmiyor".to_string())));
+    }
     
-    let chunk_path = get_chunk_file_path(&upload_id, info.chunk_number);
-    fs::create_dir_all(chunk_path.parent().unwrap())
+    chunk.persist(&get_chunk_file_path(&upload_id, info.chunk_number))
+        .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
     
-    fs::write(&chunk_path, &chunk_data)
+    let session = mark_upload_chunk_received(&mut *tx, &upload_id, info.chunk_number, chunk_size)
+        .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
     
-    let uploaded_chunks = get_uploaded_chunks(&upload_id).await;
-    let completed = uploaded_chunks.len() == info.total_chunks as usize;
-    
-    if completed {
-        let disk = get_available_disk(&pool)
-            .await
-            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-            .ok_or(StatusCode::INSUFFICIENT_STORAGE)?;
-        
-        let file_id = Uuid::new_v4();
-        let extension = Path::new(&info.file_name)
-            .extension()
-            .and_then(|ext| ext.to_str())
-            .unwrap_or("mp4");
-        let new_filename = format!("{}.{}", file_id, extension);
-        
-        let disk_path = PathBuf::from(&disk.path);
-        let final_path = disk_path.join(&new_filename);
-        
-        fs::create_dir_all(&disk_path)
-            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-        
-        combine_chunks(&upload_id, &info, &final_path)
-            .await
-            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-        
-        update_disk_usage(&pool, disk.id, info.total_size as i64)
+    let finalizing = session.all_chunks_received()
+        && set_upload_session_status(&mut *tx, &upload_id, "uploading", "finalizing")
             .await
             .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    tx.commit().await.map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    
+    if !finalizing {
+        let next_chunk = (1..=info.total_chunks)
+            .find(|&chunk| !session.has_chunk(chunk));
         
         return Ok(Json(ApiResponse::success(ChunkUploadResponse {
             upload_id,
             chunk_number: info.chunk_number,
             uploaded: true,
-            next_chunk: None,
-            completed: true,
-            file_path: Some(new_filename),
-            disk_location: Some(disk.name),
-            file_size: Some(info.total_size),
-            disk_id: Some(disk.id.to_string()),
+            next_chunk,
+            completed: false,
+            file_path: None,
+            disk_location: None,
+            file_size: None,
+            disk_id: None,
+            file_sha256: None,
+            mime_type: None,
         })));
     }
     
-    let next_chunk = (1..=info.total_chunks)
-        .find(|&chunk| !uploaded_chunks.contains(&chunk));
-    
-    Ok(Json(ApiResponse::success(ChunkUploadResponse {
-        upload_id,
-        chunk_number: info.chunk_number,
-        uploaded: true,
-        next_chunk,
-        completed: false,
-        file_path: None,
-        disk_location: None,
-        file_size: None,
-        disk_id: None,
-    })))
-}
-
-pub async fn get_upload_status(
-    Query(params): Query<HashMap<String, String>>,
-) -> Result<Json<ApiResponse<UploadStatusResponse>>, StatusCode> {
-    let upload_id = params.get("upload_id")
-        .ok_or(StatusCode::BAD_REQUEST)?;
-    
-    let info = load_upload_info(upload_id)
-        .await
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-        .ok_or(StatusCode::NOT_FOUND)?;
-    
-    let uploaded_chunks = get_uploaded_chunks(upload_id).await;
-    let completed = uploaded_chunks.len() == info.total_chunks as usize;
-    
-    Ok(Json(ApiResponse::success(UploadStatusResponse {
-        upload_id: upload_id.clone(),
-        uploaded_chunks,
-        total_chunks: info.total_chunks,
-        completed,
-        file_path: None,
-    })))
+    match finish_chunked_upload(&pool, &config, &kind_name, kind, &extension, &session).await {
+        Ok(completed) => Ok(Json(ApiResponse::success(ChunkUploadResponse {
+            upload_id,
+            chunk_number: info.chunk_number,
+            uploaded: true,
+            next_chunk: None,
+            completed: true,
+            file_path: Some(completed.reference),
+            disk_location: Some(completed.disk_location),
+            file_size: Some(completed.size),
+            disk_id: completed.disk_id.map(|id| id.to_string()),
+            file_sha256: Some(completed.sha256),
+            mime_type: Some(completed.mime_type.to_string()),
+        }))),
+        Err(UploadError::Rejected(message)) => Ok(Json(ApiResponse::error(message))),
+        Err(UploadError::Status(status)) => Err(status),
+    }
 }
 
-pub async fn upload_thumbnail_chunk(
-    State(config): State<UploadConfig>,
-    mut multipart: Multipart,
-) -> Result<Json<ApiResponse<ChunkUploadResponse>>, StatusCode> {
-    let mut chunk_data: Option<Vec<u8>> = None;
-    let mut upload_info: Option<ChunkUploadRequest> = None;
-    
-    while let Some(field) = multipart.next_field().await.map_err(|_| StatusCode::BAD_REQUEST)? {
-        let name = field.name().unwrap_or("").to_string();
-        
-        match name.as_str() {
-            "chunk" => {
-                chunk_data = Some(field.bytes().await.map_err(|_| StatusCode::BAD_REQUEST)?.to_vec());
+/// Assembles and stores a session whose chunks have all arrived.
+///
+/// A file that fails verification fails the session; any other error before the chunks are
+/// combined hands the session back to `uploading` so the last chunk can be retried. Combining
+/// removes the chunks, so a later failure fails the session and removes the assembled file.
+async fn finish_chunked_upload(
+    pool: &PgPool,
+    config: &UploadConfig,
+    kind_name: &str,
+    kind: &UploadKind,
+    extension: &str,
+    session: &UploadSession,
+) -> Result<CompletedUpload, UploadError> {
+    let result = async {
+        let target = prepare_target(pool, config, kind, extension).await?;
+        let file_sha256 = match combine_chunks(session, &target.final_path).await {
+            Ok(hash) => hash,
+            Err(e) if e.kind() == std::io::ErrorKind::InvalidData => {
+                return Err(UploadError::Rejected(format!("Dosya doğrulanamadı: {}", e)));
             }
-            "metadata" => {
-                let metadata_str = String::from_utf8(
-                    field.bytes().await.map_err(|_| StatusCode::BAD_REQUEST)?.to_vec()
-                ).map_err(|_| StatusCode::BAD_REQUEST)?;
-                
-                upload_info = Some(
-                    serde_json::from_str::<ChunkUploadRequest>(&metadata_str)
-                        .map_err(|_| StatusCode::BAD_REQUEST)?
-                );
+            Err(_) => return Err(StatusCode::INTERNAL_SERVER_ERROR.into()),
+        };
+        let final_path = target.final_path.clone();
+        match store_completed_upload(pool, config, kind_name, kind, target, &file_sha256, session.total_size as u64).await {
+            Err(UploadError::Status(_)) => {
+                let _ = fs::remove_file(&final_path).await;
+                Err(UploadError::Rejected("Dosya kaydedilemedi, yükleme yeniden başlatılmalı".to_string()))
             }
-            _ => {}
+            result => result,
         }
     }
+    .await;
     
-    let chunk_data = chunk_data.ok_or(StatusCode::BAD_REQUEST)?;
-    let mut info = upload_info.ok_or(StatusCode::BAD_REQUEST)?;
-    
-    if chunk_data.len() > 2 * 1024 * 1024 {
-        return Ok(Json(ApiResponse::error("Chunk boyutu çok büyük (max 2MB)".to_string())));
+    finish_session(pool, session, result).await
+}
+
+/// Records the outcome of finalizing a session that is in the `finalizing` state.
+pub async fn finish_session(
+    pool: &PgPool,
+    session: &UploadSession,
+    result: Result<CompletedUpload, UploadError>,
+) -> Result<CompletedUpload, UploadError> {
+    let update = match &result {
+        Ok(completed) => complete_upload_session(pool, &session.id, &completed.reference, completed.disk_id, &completed.sha256)
+            .await
+            .map(|_| ()),
+        Err(UploadError::Rejected(_)) => {
+            remove_session_files(session).await;
+            set_upload_session_status(pool, &session.id, "finalizing", "failed").await.map(|_| ())
+        }
+        Err(UploadError::Status(_)) => {
+            set_upload_session_status(pool, &session.id, "finalizing", "uploading").await.map(|_| ())
+        }
+    };
+    if let Err(e) = update {
+        tracing::error!("Failed to record the result of upload {}: {}", session.id, e);
     }
-    
-    if info.total_size > 5 * 1024 * 1024 {
-        return Ok(Json(ApiResponse::error("Thumbnail boyutu çok büyük (max 5MB)".to_string())));
+    result
+}
+
+/// Failure while finishing an upload: either a message for the client or a bare status.
+pub enum UploadError {
+    Rejected(String),
+    Status(StatusCode),
+}
+
+impl From<StatusCode> for UploadError {
+    fn from(status: StatusCode) -> Self {
+        UploadError::Status(status)
     }
+}
+
+/// Where the assembled file of an upload is about to be written.
+pub struct UploadTarget {
+    disk: Option<DiskStorage>,
+    pub final_path: PathBuf,
+    reference: String,
+}
+
+/// Picks the destination of a completed upload and creates its directory.
+pub async fn prepare_target(
+    pool: &PgPool,
+    config: &UploadConfig,
+    kind: &UploadKind,
+    extension: &str,
+) -> Result<UploadTarget, StatusCode> {
+    let new_filename = format!("{}.{}", Uuid::new_v4(), extension);
     
-    let upload_id = if let Some(id) = &info.upload_id {
-        id.clone()
-    } else {
-        let new_id = Uuid::new_v4().to_string();
-        info.upload_id = Some(new_id.clone());
-        new_id
+    let (disk, target_dir, reference) = match kind.destination {
+        UploadDestination::Disk => {
+            let disk = get_available_disk(pool)
+                .await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+                .ok_or(StatusCode::INSUFFICIENT_STORAGE)?;
+            let target_dir = PathBuf::from(&disk.path);
+            (Some(disk), target_dir, new_filename.clone())
+        }
+        UploadDestination::Local(dir) => (
+            None,
+            PathBuf::from(&config.upload_dir).join(dir),
+            format!("{}/{}", dir, new_filename),
+        ),
     };
     
-    save_upload_info(&upload_id, &info)
+    fs::create_dir_all(&target_dir)
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
     
-    let chunk_path = get_chunk_file_path(&upload_id, info.chunk_number);
-    fs::create_dir_all(chunk_path.parent().unwrap())
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-    
-    fs::write(&chunk_path, &chunk_data)
+    Ok(UploadTarget {
+        disk,
+        final_path: target_dir.join(&new_filename),
+        reference,
+    })
+}
+
+/// Sniffs the assembled file and rejects content the kind does not accept.
+///
+/// The client's file name only picked the provisional extension, so the file is renamed when
+/// its content says otherwise.
+async fn check_content(kind: &UploadKind, mut target: UploadTarget) -> Result<(UploadTarget, FileType), UploadError> {
+    let detected = filetype::detect_file(&target.final_path)
+        .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let file_type = match detected {
+        Some(file_type) if kind.allowed_types.contains(&file_type) => file_type,
+        other => {
+            let _ = fs::remove_file(&target.final_path).await;
+            let allowed: Vec<&str> = kind.allowed_types.iter().map(|t| t.mime_type).collect();
+            return Err(UploadError::Rejected(format!(
+                "Dosya içeriği desteklenmiyor: {} (izin verilenler: {})",
+                other.map(|t| t.mime_type).unwrap_or("bilinmeyen tür"),
+                allowed.join(", ")
+            )));
+        }
+    };
     
-    let uploaded_chunks = get_uploaded_chunks(&upload_id).await;
-    let completed = uploaded_chunks.len() == info.total_chunks as usize;
-    
-    if completed {
-        let file_id = Uuid::new_v4();
-        let extension = Path::new(&info.file_name)
-            .extension()
-            .and_then(|ext| ext.to_str())
-            .unwrap_or("jpg");
-        let new_filename = format!("{}.{}", file_id, extension);
-        
-        let thumbnails_dir = PathBuf::from(&config.upload_dir).join("thumbnails");
-        fs::create_dir_all(&thumbnails_dir)
-            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-        
-        let final_path = thumbnails_dir.join(&new_filename);
-        
-        combine_chunks(&upload_id, &info, &final_path)
+    if target.final_path.extension().and_then(|ext| ext.to_str()) != Some(file_type.extension) {
+        let renamed = target.final_path.with_extension(file_type.extension);
+        fs::rename(&target.final_path, &renamed)
             .await
             .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-        
-        return Ok(Json(ApiResponse::success(ChunkUploadResponse {
-            upload_id,
-            chunk_number: info.chunk_number,
-            uploaded: true,
-            next_chunk: None,
-            completed: true,
-            file_path: Some(format!("thumbnails/{}", new_filename)),
-            disk_location: Some("local".to_string()),
-            file_size: Some(info.total_size),
-            disk_id: None,
-        })));
+        target.final_path = renamed;
+        target.reference = Path::new(&target.reference)
+            .with_extension(file_type.extension)
+            .to_string_lossy()
+            .into_owned();
     }
     
-    let next_chunk = (1..=info.total_chunks)
-        .find(|&chunk| !uploaded_chunks.contains(&chunk));
-    
-    Ok(Json(ApiResponse::success(ChunkUploadResponse {
-        upload_id,
-        chunk_number: info.chunk_number,
-        uploaded: true,
-        next_chunk,
-        completed: false,
-        file_path: None,
-        disk_location: None,
-        file_size: None,
-        disk_id: None,
-    })))
+    Ok((target, file_type))
 }
 
-
-
-pub async fn upload_channel_image_chunk(
-    State(config): State<UploadConfig>,
-    mut multipart: Multipart,
-) -> Result<Json<ApiResponse<ChunkUploadResponse>>, StatusCode> {
-    let mut chunk_data: Option<Vec<u8>> = None;
-    let mut upload_info: Option<ChunkUploadRequest> = None;
+/// Registers an assembled and verified file: deduplicates it, accounts disk usage and runs the
+/// kind's post-processing hooks.
+pub async fn store_completed_upload(
+    pool: &PgPool,
+    config: &UploadConfig,
+    kind_name: &str,
+    kind: &UploadKind,
+    target: UploadTarget,
+    file_sha256: &str,
+    size: u64,
+) -> Result<CompletedUpload, UploadError> {
+    let (target, file_type) = check_content(kind, target).await?;
     
-    while let Some(field) = multipart.next_field().await.map_err(|_| StatusCode::BAD_REQUEST)? {
-        let name = field.name().unwrap_or("").to_string();
-        
-        match name.as_str() {
-            "chunk" => {
-                chunk_data = Some(field.bytes().await.map_err(|_| StatusCode::BAD_REQUEST)?.to_vec());
+    let mut rewritten = false;
+    for hook in &kind.hooks {
+        match hook.before_store(&target.final_path, file_type).await {
+            Ok(changed) => rewritten |= changed,
+            Err(e) => {
+                tracing::error!("Processing of {} upload {} failed: {}", kind_name, target.reference, e);
+                let _ = fs::remove_file(&target.final_path).await;
+                return Err(UploadError::Rejected(format!("Dosya işlenemedi: {}", e)));
             }
-            "metadata" => {
-                let metadata_str = String::from_utf8(
-                    field.bytes().await.map_err(|_| StatusCode::BAD_REQUEST)?.to_vec()
-                ).map_err(|_| StatusCode::BAD_REQUEST)?;
-                
-                upload_info = Some(
-                    serde_json::from_str::<ChunkUploadRequest>(&metadata_str)
-                        .map_err(|_| StatusCode::BAD_REQUEST)?
-                );
-            }
-            _ => {}
         }
     }
-    
-    let chunk_data = chunk_data.ok_or(StatusCode::BAD_REQUEST)?;
-    let mut info = upload_info.ok_or(StatusCode::BAD_REQUEST)?;
-    
-    if chunk_data.len() > 1 * 1024 * 1024 {
-        return Ok(Json(ApiResponse::error("Chunk boyutu çok büyük (max 1MB)".to_string())));
-    }
-    
-    if info.total_size > 2 * 1024 * 1024 {
-        return Ok(Json(ApiResponse::error("Görsel boyutu çok büyük (max 2MB)".to_string())));
-    }
-    
-    let upload_id = if let Some(id) = &info.upload_id {
-        id.clone()
+    let (file_sha256, size) = if rewritten {
+        let sha256 = sha256_file(&target.final_path)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        let size = fs::metadata(&target.final_path)
+            .await
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+            .len();
+        (sha256, size)
     } else {
-        let new_id = Uuid::new_v4().to_string();
-        info.upload_id = Some(new_id.clone());
-        new_id
+        (file_sha256.to_string(), size)
     };
+    let file_sha256 = file_sha256.as_str();
     
-    save_upload_info(&upload_id, &info)
+    let disk_id = target.disk.as_ref().map(|disk| disk.id);
+    let stored = store_media(pool, kind.media_kind, file_sha256, &target.final_path, &target.reference, disk_id, size as i64)
         .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-    
-    let chunk_path = get_chunk_file_path(&upload_id, info.chunk_number);
-    fs::create_dir_all(chunk_path.parent().unwrap())
+    set_media_blob_mime_type(pool, kind.media_kind, file_sha256, file_type.mime_type)
+        .await
         .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
     
-    fs::write(&chunk_path, &chunk_data)
-        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    let stored_disk = match (target.disk, stored.deduplicated) {
+        (Some(disk), false) => {
+            update_disk_usage(pool, disk.id, size as i64)
+                .await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+            Some(disk)
+        }
+        (_, _) => match stored.disk_id {
+            Some(disk_id) => get_disk_by_id(pool, disk_id)
+                .await
+                .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?,
+            None => None,
+        },
+    };
     
-    let uploaded_chunks = get_uploaded_chunks(&upload_id).await;
-    let completed = uploaded_chunks.len() == info.total_chunks as usize;
+    let completed = CompletedUpload {
+        kind: kind_name.to_string(),
+        path: match &stored_disk {
+            Some(disk) => PathBuf::from(&disk.path).join(&stored.reference),
+            None => PathBuf::from(&config.upload_dir).join(&stored.reference),
+        },
+        reference: stored.reference,
+        disk_id: stored.disk_id,
+        disk_location: stored_disk
+            .map(|disk| disk.name)
+            .unwrap_or_else(|| "local".to_string()),
+        size,
+        sha256: file_sha256.to_string(),
+        mime_type: file_type.mime_type,
+        deduplicated: stored.deduplicated,
+    };
     
-    if completed {
-        let file_id = Uuid::new_v4();
-        let extension = Path::new(&info.file_name)
-            .extension()
-            .and_then(|ext| ext.to_str())
-            .unwrap_or("jpg");
-        let new_filename = format!("{}.{}", file_id, extension);
-        
-        let channels_dir = PathBuf::from(&config.upload_dir).join("channels");
-        fs::create_dir_all(&channels_dir)
-            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-        
-        let final_path = channels_dir.join(&new_filename);
-        
-        combine_chunks(&upload_id, &info, &final_path)
-            .await
-            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
-        
-        return Ok(Json(ApiResponse::success(ChunkUploadResponse {
-            upload_id,
-            chunk_number: info.chunk_number,
-            uploaded: true,
-            next_chunk: None,
-            completed: true,
-            file_path: Some(format!("channels/{}", new_filename)),
-            disk_location: Some("local".to_string()),
-            file_size: Some(info.total_size),
-            disk_id: None,
-        })));
+    for hook in &kind.hooks {
+        if let Err(e) = hook.after_store(pool, &completed).await {
+            tracing::error!("Post-processing of {} upload {} failed: {}", kind_name, completed.reference, e);
+            return Err(UploadError::Rejected(format!("Dosya işlenemedi: {}", e)));
+        }
     }
     
-    let next_chunk = (1..=info.total_chunks)
-        .find(|&chunk| !uploaded_chunks.contains(&chunk));
+    Ok(completed)
+}
+
+pub async fn get_upload_status(
+    State(pool): State<PgPool>,
+    Extension(claims): Extension<Claims>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Json<ApiResponse<UploadStatusResponse>>, StatusCode> {
+    let upload_id = params.get("upload_id")
+        .ok_or(StatusCode::BAD_REQUEST)?;
+    
+    let session = get_upload_session(&pool, upload_id)
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    authorize_session(&session, &claims)?;
     
-    Ok(Json(ApiResponse::success(ChunkUploadResponse {
-        upload_id,
-        chunk_number: info.chunk_number,
-        uploaded: true,
-        next_chunk,
-        completed: false,
-        file_path: None,
-        disk_location: None,
-        file_size: None,
-        disk_id: None,
+    Ok(Json(ApiResponse::success(UploadStatusResponse {
+        upload_id: upload_id.clone(),
+        uploaded_chunks: session.received_chunk_numbers(),
+        total_chunks: session.total_chunks as u32,
+        received_bytes: session.received_bytes as u64,
+        total_size: session.total_size as u64,
+        status: session.status.clone(),
+        completed: session.status == "completed",
+        file_path: session.file_reference,
     })))
-}
\ No newline at end of file
+}
+
+/// Lists uploads across all admins with their progress, for `/api/admin/uploads`.
+pub async fn list_uploads(
+    State(pool): State<PgPool>,
+    Query(params): Query<UploadListQuery>,
+) -> Result<Json<ApiResponse<Vec<UploadSummary>>>, StatusCode> {
+    let statuses = match params.status.as_deref() {
+        Some("all") => None,
+        Some(statuses) => Some(statuses.split(',').map(|status| status.trim().to_string()).collect()),
+        None => Some(vec!["uploading".to_string(), "finalizing".to_string()]),
+    };
+    let limit = params.limit.unwrap_or(100).clamp(1, 1000);
+    
+    match list_upload_sessions(&pool, statuses, params.owner.as_deref(), limit).await {
+        Ok(sessions) => Ok(Json(ApiResponse::success(
+            sessions.into_iter().map(UploadSummary::from).collect(),
+        ))),
+        Err(_) => Ok(Json(ApiResponse::error("Yüklemeler alınamadı".to_string()))),
+    }
+}
@@ -0,0 +1,197 @@
+use axum::{
+    body::Bytes,
+    extract::{ConnectInfo, Path, State},
+    http::{header, HeaderMap, StatusCode},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use std::{
+    collections::HashMap,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use uuid::Uuid;
+use crate::{
+    config::env_or,
+    database::{add_video_views, get_video_id_by_slug, record_video_view},
+    models::ApiResponse,
+};
+
+/// Lower-cased User-Agent fragments of crawlers, link previewers and scripted clients.
+const BOT_USER_AGENTS: &[&str] = &[
+    "bot", "crawler", "spider", "slurp", "headless", "lighthouse", "preview", "facebookexternalhit",
+    "embedly", "curl", "wget", "python-requests", "go-http-client", "okhttp", "java/", "axios",
+];
+
+#[derive(Clone, Debug)]
+pub struct ViewConfig {
+    /// A viewer is counted at most once per video within this many minutes.
+    pub dedupe_window_minutes: i32,
+    /// Mixed into viewer hashes so stored events cannot be mapped back to an IP address.
+    pub hash_salt: String,
+    /// Whether the client address is taken from `X-Forwarded-For`; only safe behind a proxy.
+    pub trust_forwarded_for: bool,
+    pub event_retention_days: i32,
+    /// How often buffered view increments are written; views counted since the last flush are
+    /// lost if the process dies without shutting down gracefully.
+    pub flush_interval: Duration,
+}
+
+impl ViewConfig {
+    pub fn from_env() -> Self {
+        let hash_salt = env_or("VIEW_HASH_SALT", String::new());
+        let hash_salt = if hash_salt.is_empty() {
+            tracing::warn!("VIEW_HASH_SALT is not set; viewers are deduplicated only until restart");
+            Uuid::new_v4().to_string()
+        } else {
+            hash_salt
+        };
+        Self {
+            dedupe_window_minutes: env_or("VIEW_DEDUPE_WINDOW_MINUTES", 30),
+            hash_salt,
+            trust_forwarded_for: env_or("VIEW_TRUST_FORWARDED_FOR", false),
+            event_retention_days: env_or("VIEW_EVENT_RETENTION_DAYS", 90),
+            flush_interval: Duration::from_secs(env_or("VIEW_FLUSH_INTERVAL_SECS", 5).max(1)),
+        }
+    }
+}
+
+/// Accumulates counted views in memory and adds them to `videos.views` in batches, so a popular
+/// video's row is updated once per flush instead of once per view.
+#[derive(Clone, Default)]
+pub struct ViewCounter {
+    pending: Arc<Mutex<HashMap<Uuid, i64>>>,
+}
+
+impl ViewCounter {
+    pub fn add(&self, video_id: Uuid) {
+        *self.pending.lock().unwrap().entry(video_id).or_default() += 1;
+    }
+
+    /// Writes the buffered increments; on failure they are kept for the next flush.
+    pub async fn flush(&self, pool: &PgPool) -> anyhow::Result<usize> {
+        let batch: Vec<(Uuid, i64)> = std::mem::take(&mut *self.pending.lock().unwrap())
+            .into_iter()
+            .collect();
+        if batch.is_empty() {
+            return Ok(0);
+        }
+        if let Err(e) = add_video_views(pool, &batch).await {
+            let mut pending = self.pending.lock().unwrap();
+            for (video_id, count) in batch {
+                *pending.entry(video_id).or_default() += count;
+            }
+            return Err(e);
+        }
+        Ok(batch.len())
+    }
+}
+
+pub async fn run_view_counter_flush(pool: PgPool, counter: ViewCounter, interval: Duration) {
+    let mut ticker = tokio::time::interval(interval);
+    loop {
+        ticker.tick().await;
+        if let Err(e) = counter.flush(&pool).await {
+            tracing::error!("Failed to flush view counts: {}", e);
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct ViewState {
+    pub pool: PgPool,
+    pub config: Arc<ViewConfig>,
+    pub counter: ViewCounter,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ViewBeacon {
+    /// Random id the player keeps in local storage; preferred over the address when present.
+    pub client_id: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ViewBeaconResponse {
+    pub counted: bool,
+}
+
+pub(crate) fn is_bot(headers: &HeaderMap) -> bool {
+    let prefetch = ["purpose", "sec-purpose", "x-purpose"].iter().any(|name| {
+        headers
+            .get(*name)
+            .and_then(|value| value.to_str().ok())
+            .is_some_and(|value| value.contains("prefetch") || value.contains("preview"))
+    });
+    let user_agent = headers
+        .get(header::USER_AGENT)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    prefetch || user_agent.is_empty() || BOT_USER_AGENTS.iter().any(|bot| user_agent.contains(bot))
+}
+
+fn is_valid_client_id(id: &str) -> bool {
+    (8..=64).contains(&id.len()) && id.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+}
+
+/// Hashes the client id, or the address and User-Agent when there is none, into a viewer key.
+pub(crate) fn viewer_hash(config: &ViewConfig, headers: &HeaderMap, addr: SocketAddr, client_id: Option<&str>) -> String {
+    let identity = match client_id.filter(|id| is_valid_client_id(id)) {
+        Some(client_id) => format!("client:{}", client_id),
+        None => {
+            let forwarded = config
+                .trust_forwarded_for
+                .then(|| headers.get("x-forwarded-for"))
+                .flatten()
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.split(',').next())
+                .map(|ip| ip.trim().to_string());
+            let user_agent = headers
+                .get(header::USER_AGENT)
+                .and_then(|value| value.to_str().ok())
+                .unwrap_or_default();
+            format!("addr:{}|{}", forwarded.unwrap_or_else(|| addr.ip().to_string()), user_agent)
+        }
+    };
+    hex::encode(Sha256::digest(format!("{}|{}", config.hash_salt, identity)))
+}
+
+/// View beacon the player sends once playback starts.
+///
+/// The body is parsed regardless of its content type, because `navigator.sendBeacon` can only
+/// send `text/plain` without a CORS preflight.
+pub async fn record_view_handler(
+    State(state): State<ViewState>,
+    Path(slug): Path<String>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<ApiResponse<ViewBeaconResponse>>, StatusCode> {
+    if is_bot(&headers) {
+        return Ok(Json(ApiResponse::success(ViewBeaconResponse { counted: false })));
+    }
+    let beacon: ViewBeacon = serde_json::from_slice(&body).unwrap_or_default();
+
+    let video_id = match get_video_id_by_slug(&state.pool, &slug).await {
+        Ok(Some(id)) => id,
+        Ok(None) => return Ok(Json(ApiResponse::error("Video bulunamadı".to_string()))),
+        Err(_) => return Ok(Json(ApiResponse::error("Video alınamadı".to_string()))),
+    };
+
+    let hash = viewer_hash(&state.config, &headers, addr, beacon.client_id.as_deref());
+    match record_video_view(&state.pool, video_id, &hash, state.config.dedupe_window_minutes).await {
+        Ok(counted) => {
+            if counted {
+                state.counter.add(video_id);
+            }
+            Ok(Json(ApiResponse::success(ViewBeaconResponse { counted })))
+        }
+        Err(e) => {
+            tracing::warn!("Failed to record view for video {}: {}", video_id, e);
+            Ok(Json(ApiResponse::error("Görüntülenme kaydedilemedi".to_string())))
+        }
+    }
+}
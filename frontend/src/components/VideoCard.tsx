@@ -1,6 +1,7 @@
 import React, { memo, useMemo } from 'react';
 import { PlayIcon } from '@heroicons/react/24/solid';
 import { getUploadsUrl } from '../config/env';
+import { ImageSource } from '../types';
 
 interface VideoCardProps {
   video: {
@@ -17,6 +18,7 @@ interface VideoCardProps {
     channel_profile_image?: string;
     category_name: string;
     category_slug: string;
+    thumbnail_variants?: ImageSource[];
   };
   size?: 'small' | 'medium' | 'large';
 }
@@ -50,6 +52,15 @@ const VideoCard: React.FC<VideoCardProps> = memo(({ video, size = 'medium' }) =>
     return getUploadsUrl(`/${path}`);
   }, [video.thumbnail]);
 
+  // One srcset per format; the browser picks the width, <picture> picks WebP when supported
+  const thumbnailSrcSets = useMemo(() => {
+    const srcSet = (mimeType: string) => (video.thumbnail_variants || [])
+      .filter(variant => variant.mime_type === mimeType)
+      .map(variant => `${getUploadsUrl(variant.url.replace(/^\/uploads/, ''))} ${variant.width}w`)
+      .join(', ');
+    return { webp: srcSet('image/webp'), jpeg: srcSet('image/jpeg') };
+  }, [video.thumbnail_variants]);
+
   const channelImageUrl = useMemo(() => {
     return video.channel_profile_image ? getUploadsUrl(`/channels/${video.channel_profile_image}`) : null;
   }, [video.channel_profile_image]);
@@ -73,13 +84,20 @@ const VideoCard: React.FC<VideoCardProps> = memo(({ video, size = 'medium' }) =>
         <a href={`/video/${video.slug}`}>
           <div className="aspect-video bg-gray-900 relative overflow-hidden rounded-lg sm:rounded-xl">
             {thumbnailUrl ? (
-              <img
-                src={thumbnailUrl}
-                alt={video.title}
-                className="w-full h-full object-cover transition-transform duration-300 group-hover:scale-110"
-                loading="lazy"
-                decoding="async"
-              />
+              <picture>
+                {thumbnailSrcSets.webp && (
+                  <source type="image/webp" srcSet={thumbnailSrcSets.webp} sizes="(max-width: 640px) 100vw, 384px" />
+                )}
+                <img
+                  src={thumbnailUrl}
+                  srcSet={thumbnailSrcSets.jpeg || undefined}
+                  sizes="(max-width: 640px) 100vw, 384px"
+                  alt={video.title}
+                  className="w-full h-full object-cover transition-transform duration-300 group-hover:scale-110"
+                  loading="lazy"
+                  decoding="async"
+                />
+              </picture>
             ) : (
               <div className="w-full h-full flex items-center justify-center bg-gray-800">
                 <PlayIcon className="w-12 h-12 sm:w-16 sm:h-16 text-gray-500" />
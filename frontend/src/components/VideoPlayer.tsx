@@ -6,6 +6,7 @@ import {
   getSupportedVideoFormats,
   type BrowserInfo 
 } from '../utils/browserCompat';
+import { HEARTBEAT_INTERVAL_MS, PlaybackTracker, sendViewBeacon } from '../utils/analytics';
 import {
   PlayIcon,
   PauseIcon,
@@ -33,6 +34,8 @@ interface VideoPlayerProps {
   muted?: boolean;
   loop?: boolean;
   className?: string;
+  /** When set, a view is reported once playback starts and heartbeats are sent while it plays. */
+  videoSlug?: string;
   onTimeUpdate?: (currentTime: number, duration: number) => void;
   onEnded?: () => void;
   onPlay?: () => void;
@@ -66,6 +69,7 @@ const VideoPlayer: React.FC<VideoPlayerProps> = ({
   muted = false,
   loop = false,
   className = '',
+  videoSlug,
   onTimeUpdate,
   onEnded,
   onPlay,
@@ -76,6 +80,7 @@ const VideoPlayer: React.FC<VideoPlayerProps> = ({
   const progressRef = useRef<HTMLDivElement>(null);
   const volumeRef = useRef<HTMLDivElement>(null);
   const controlsTimeoutRef = useRef<NodeJS.Timeout | null>(null);
+  const viewReportedRef = useRef(false);
 
   const [state, setState] = useState<VideoState>({
     isPlaying: false,
@@ -228,7 +233,11 @@ const VideoPlayer: React.FC<VideoPlayerProps> = ({
 
   const handlePlay = useCallback(() => {
     setState(prev => ({ ...prev, isPlaying: true }));
-  }, []);
+    if (videoSlug && !viewReportedRef.current) {
+      viewReportedRef.current = true;
+      sendViewBeacon(videoSlug);
+    }
+  }, [videoSlug]);
 
   const handlePause = useCallback(() => {
     setState(prev => ({ ...prev, isPlaying: false }));
@@ -593,6 +602,35 @@ const VideoPlayer: React.FC<VideoPlayerProps> = ({
     };
   }, [handleLoadStart, handleTimeUpdate, handleLoadedMetadata, handleLoadedData, handlePlay, handlePause, handleEnded, handleCanPlay, handleWaiting, handleError, handleProgress, browserInfo]);
 
+  useEffect(() => {
+    const video = videoRef.current;
+    if (!video || !videoSlug) return;
+
+    const tracker = new PlaybackTracker(videoSlug);
+    const trackProgress = () => {
+      if (!video.paused) tracker.progress(video.currentTime, video.duration);
+    };
+    const trackSeek = () => tracker.seek();
+    const flush = () => tracker.flush();
+    const heartbeat = setInterval(flush, HEARTBEAT_INTERVAL_MS);
+
+    video.addEventListener('timeupdate', trackProgress);
+    video.addEventListener('seeking', trackSeek);
+    video.addEventListener('pause', flush);
+    video.addEventListener('ended', flush);
+    window.addEventListener('pagehide', flush);
+
+    return () => {
+      flush();
+      clearInterval(heartbeat);
+      video.removeEventListener('timeupdate', trackProgress);
+      video.removeEventListener('seeking', trackSeek);
+      video.removeEventListener('pause', flush);
+      video.removeEventListener('ended', flush);
+      window.removeEventListener('pagehide', flush);
+    };
+  }, [videoSlug]);
+
   useEffect(() => {
     if (state.isPlaying) {
       showControlsTemporarily();
@@ -48,7 +48,7 @@ const AdminPanel: React.FC<AdminPanelProps> = () => {
       case 'channels':
         return <ChannelManagement getAuthHeaders={getAuthHeaders} token={token!} />;
       case 'categories':
-        return <CategoryManagement getAuthHeaders={getAuthHeaders} />;
+        return <CategoryManagement getAuthHeaders={getAuthHeaders} token={token!} />;
       case 'disks':
         return <DiskManagement />;
       default:
@@ -1,36 +1,64 @@
 import React, { useState, useEffect } from 'react';
-import { getApiUrl } from '../../config/env';
+import { getApiUrl, getUploadsUrl } from '../../config/env';
+import ChunkedUpload from './ChunkedUpload';
 import { 
   PlusIcon,
   PencilIcon,
   TrashIcon,
-  XMarkIcon
+  XMarkIcon,
+  ChevronUpIcon,
+  ChevronDownIcon
 } from '@heroicons/react/24/outline';
 
 interface CategoryManagementProps {
   getAuthHeaders: () => Record<string, string>;
+  token: string;
 }
 
 interface Category {
   id: string;
   name: string;
   slug: string;
+  parent_id?: string | null;
+  position: number;
+  description?: string | null;
+  icon?: string | null;
+  color?: string | null;
+  banner?: string | null;
+  seo_title?: string | null;
+  seo_description?: string | null;
   created_at: string;
   video_count?: number;
 }
 
 interface CategoryFormData {
   name: string;
+  parent_id: string;
+  description: string;
+  icon: string;
+  color: string;
+  banner: string;
+  seo_title: string;
+  seo_description: string;
 }
 
-const CategoryManagement: React.FC<CategoryManagementProps> = ({ getAuthHeaders }) => {
+const emptyForm: CategoryFormData = {
+  name: '',
+  parent_id: '',
+  description: '',
+  icon: '',
+  color: '',
+  banner: '',
+  seo_title: '',
+  seo_description: ''
+};
+
+const CategoryManagement: React.FC<CategoryManagementProps> = ({ getAuthHeaders, token }) => {
   const [categories, setCategories] = useState<Category[]>([]);
   const [loading, setLoading] = useState(true);
   const [showModal, setShowModal] = useState(false);
   const [editingCategory, setEditingCategory] = useState<Category | null>(null);
-  const [formData, setFormData] = useState<CategoryFormData>({
-    name: ''
-  });
+  const [formData, setFormData] = useState<CategoryFormData>(emptyForm);
   const [error, setError] = useState<string | null>(null);
 
   useEffect(() => {
@@ -58,13 +86,18 @@ const CategoryManagement: React.FC<CategoryManagementProps> = ({ getAuthHeaders
     if (category) {
       setEditingCategory(category);
       setFormData({
-        name: category.name
+        name: category.name,
+        parent_id: category.parent_id || '',
+        description: category.description || '',
+        icon: category.icon || '',
+        color: category.color || '',
+        banner: category.banner || '',
+        seo_title: category.seo_title || '',
+        seo_description: category.seo_description || ''
       });
     } else {
       setEditingCategory(null);
-      setFormData({
-        name: ''
-      });
+      setFormData(emptyForm);
     }
     setError(null);
     setShowModal(true);
@@ -94,7 +127,16 @@ const CategoryManagement: React.FC<CategoryManagementProps> = ({ getAuthHeaders
       const response = await fetch(url, {
         method,
         headers: getAuthHeaders(),
-        body: JSON.stringify(formData)
+        body: JSON.stringify({
+          name: formData.name,
+          parent_id: formData.parent_id || null,
+          description: formData.description.trim() || null,
+          icon: formData.icon.trim() || null,
+          color: formData.color || null,
+          banner: formData.banner || null,
+          seo_title: formData.seo_title.trim() || null,
+          seo_description: formData.seo_description.trim() || null
+        })
       });
 
       const result = await response.json();
@@ -128,6 +170,32 @@ const CategoryManagement: React.FC<CategoryManagementProps> = ({ getAuthHeaders
     }
   };
 
+  // Categories are ordered among their siblings, so a move swaps with the neighbouring sibling.
+  const moveCategory = async (category: Category, direction: -1 | 1) => {
+    const siblings = categories.filter((other) => (other.parent_id || null) === (category.parent_id || null));
+    const index = siblings.findIndex((other) => other.id === category.id);
+    const target = index + direction;
+    if (target < 0 || target >= siblings.length) return;
+
+    const ids = siblings.map((other) => other.id);
+    [ids[index], ids[target]] = [ids[target], ids[index]];
+
+    try {
+      const response = await fetch(getApiUrl('/admin/categories/order'), {
+        method: 'PUT',
+        headers: getAuthHeaders(),
+        body: JSON.stringify({ ids })
+      });
+
+      const result = await response.json();
+      if (result.success) {
+        await loadCategories();
+      }
+    } catch (error) {
+      console.error('Kategori sırası güncellenirken hata:', error);
+    }
+  };
+
   const formatDate = (dateString: string) => {
     return new Date(dateString).toLocaleDateString('tr-TR');
   };
@@ -184,9 +252,20 @@ const CategoryManagement: React.FC<CategoryManagementProps> = ({ getAuthHeaders
                 {categories.map((category) => (
                   <tr key={category.id} className="hover:bg-gray-50">
                     <td className="px-6 py-4 whitespace-nowrap">
-                      <div className="text-sm font-medium text-gray-900">
+                      <div className="flex items-center text-sm font-medium text-gray-900">
+                        {category.color && (
+                          <span
+                            className="inline-block w-3 h-3 rounded-full mr-2"
+                            style={{ backgroundColor: category.color }}
+                          />
+                        )}
                         {category.name}
                       </div>
+                      {category.parent_id && (
+                        <div className="text-xs text-gray-500">
+                          {categories.find((parent) => parent.id === category.parent_id)?.name}
+                        </div>
+                      )}
                     </td>
                     <td className="px-6 py-4 whitespace-nowrap">
                       <div className="text-sm text-gray-500">
@@ -203,6 +282,20 @@ const CategoryManagement: React.FC<CategoryManagementProps> = ({ getAuthHeaders
                     </td>
                     <td className="px-6 py-4 whitespace-nowrap text-right text-sm font-medium">
                       <div className="flex items-center justify-end space-x-2">
+                        <button
+                          onClick={() => moveCategory(category, -1)}
+                          className="p-2 text-gray-500 hover:text-gray-700 hover:bg-gray-100 rounded"
+                          title="Yukarı taşı"
+                        >
+                          <ChevronUpIcon className="h-4 w-4" />
+                        </button>
+                        <button
+                          onClick={() => moveCategory(category, 1)}
+                          className="p-2 text-gray-500 hover:text-gray-700 hover:bg-gray-100 rounded"
+                          title="Aşağı taşı"
+                        >
+                          <ChevronDownIcon className="h-4 w-4" />
+                        </button>
                         <button
                           onClick={() => openModal(category)}
                           className="p-2 text-blue-600 hover:text-blue-800 hover:bg-blue-50 rounded"
@@ -228,7 +321,7 @@ const CategoryManagement: React.FC<CategoryManagementProps> = ({ getAuthHeaders
       {/* Modal */}
       {showModal && (
         <div className="fixed inset-0 bg-black bg-opacity-50 flex items-center justify-center p-4 z-50">
-          <div className="bg-white rounded-lg max-w-md w-full">
+          <div className="bg-white rounded-lg max-w-2xl w-full max-h-[90vh] overflow-y-auto">
             <div className="flex items-center justify-between p-6 border-b border-gray-200">
               <h3 className="text-lg font-semibold text-gray-900">
                 {editingCategory ? 'Kategori Düzenle' : 'Yeni Kategori Ekle'}
@@ -262,6 +355,138 @@ const CategoryManagement: React.FC<CategoryManagementProps> = ({ getAuthHeaders
                 />
               </div>
 
+              <div>
+                <label className="block text-sm font-medium text-gray-700 mb-1">
+                  Üst Kategori
+                </label>
+                <select
+                  value={formData.parent_id}
+                  onChange={(e) => setFormData({ ...formData, parent_id: e.target.value })}
+                  className="w-full px-3 py-2 border border-gray-300 rounded-lg focus:outline-none focus:ring-2 focus:ring-purple-500"
+                >
+                  <option value="">Yok (ana kategori)</option>
+                  {categories
+                    .filter((category) => category.id !== editingCategory?.id)
+                    .map((category) => (
+                      <option key={category.id} value={category.id}>
+                        {category.name}
+                      </option>
+                    ))}
+                </select>
+              </div>
+
+              <div>
+                <label className="block text-sm font-medium text-gray-700 mb-1">
+                  Açıklama
+                </label>
+                <textarea
+                  value={formData.description}
+                  onChange={(e) => setFormData({ ...formData, description: e.target.value })}
+                  className="w-full px-3 py-2 border border-gray-300 rounded-lg focus:outline-none focus:ring-2 focus:ring-purple-500"
+                  rows={3}
+                  placeholder="Kategori sayfasında gösterilir"
+                />
+              </div>
+
+              <div className="grid grid-cols-2 gap-4">
+                <div>
+                  <label className="block text-sm font-medium text-gray-700 mb-1">
+                    İkon
+                  </label>
+                  <input
+                    type="text"
+                    value={formData.icon}
+                    onChange={(e) => setFormData({ ...formData, icon: e.target.value })}
+                    className="w-full px-3 py-2 border border-gray-300 rounded-lg focus:outline-none focus:ring-2 focus:ring-purple-500"
+                    maxLength={50}
+                    placeholder="Örn. gamepad"
+                  />
+                </div>
+                <div>
+                  <label className="block text-sm font-medium text-gray-700 mb-1">
+                    Renk
+                  </label>
+                  <div className="flex items-center space-x-2">
+                    <input
+                      type="color"
+                      value={formData.color || '#9333ea'}
+                      onChange={(e) => setFormData({ ...formData, color: e.target.value })}
+                      className="h-10 w-14 border border-gray-300 rounded"
+                    />
+                    {formData.color && (
+                      <button
+                        type="button"
+                        onClick={() => setFormData({ ...formData, color: '' })}
+                        className="text-sm text-gray-500 hover:text-gray-700"
+                      >
+                        Kaldır
+                      </button>
+                    )}
+                  </div>
+                </div>
+              </div>
+
+              <div>
+                <label className="block text-sm font-medium text-gray-700 mb-2">
+                  Banner Görseli
+                </label>
+                {formData.banner && (
+                  <div className="flex items-center justify-between mb-2">
+                    <img
+                      src={getUploadsUrl(`/banners/${formData.banner}`)}
+                      alt="Banner"
+                      className="h-16 rounded object-cover"
+                    />
+                    <button
+                      type="button"
+                      onClick={() => setFormData({ ...formData, banner: '' })}
+                      className="text-sm text-red-600 hover:text-red-800"
+                    >
+                      Kaldır
+                    </button>
+                  </div>
+                )}
+                <ChunkedUpload
+                  token={token}
+                  uploadType="banner"
+                  onUploadComplete={(filename) => {
+                    setFormData((current) => ({ ...current, banner: filename }));
+                    setError(null);
+                  }}
+                  onUploadError={(error) => setError(error)}
+                  maxFileSize={8 * 1024 * 1024} // 8MB
+                  acceptedTypes="image/jpeg,image/png,image/webp"
+                  chunkSize={1024 * 1024} // 1MB chunks
+                />
+              </div>
+
+              <div>
+                <label className="block text-sm font-medium text-gray-700 mb-1">
+                  SEO Başlığı
+                </label>
+                <input
+                  type="text"
+                  value={formData.seo_title}
+                  onChange={(e) => setFormData({ ...formData, seo_title: e.target.value })}
+                  className="w-full px-3 py-2 border border-gray-300 rounded-lg focus:outline-none focus:ring-2 focus:ring-purple-500"
+                  maxLength={70}
+                  placeholder="Boş bırakılırsa kategori adı kullanılır"
+                />
+              </div>
+
+              <div>
+                <label className="block text-sm font-medium text-gray-700 mb-1">
+                  SEO Açıklaması
+                </label>
+                <textarea
+                  value={formData.seo_description}
+                  onChange={(e) => setFormData({ ...formData, seo_description: e.target.value })}
+                  className="w-full px-3 py-2 border border-gray-300 rounded-lg focus:outline-none focus:ring-2 focus:ring-purple-500"
+                  rows={2}
+                  maxLength={160}
+                />
+              </div>
+
               <div className="flex justify-end space-x-3 pt-4">
                 <button
                   type="button"
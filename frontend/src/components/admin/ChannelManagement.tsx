@@ -5,7 +5,9 @@ import {
   PlusIcon,
   PencilIcon,
   TrashIcon,
-  XMarkIcon
+  XMarkIcon,
+  ChevronLeftIcon,
+  ChevronRightIcon
 } from '@heroicons/react/24/outline';
 
 interface ChannelManagementProps {
@@ -18,6 +20,12 @@ interface Channel {
   name: string;
   slug: string;
   image?: string;
+  position: number;
+  description?: string | null;
+  color?: string | null;
+  banner?: string | null;
+  seo_title?: string | null;
+  seo_description?: string | null;
   created_at: string;
   video_count?: number;
 }
@@ -25,16 +33,28 @@ interface Channel {
 interface ChannelFormData {
   name: string;
   image?: string;
+  description: string;
+  color: string;
+  banner: string;
+  seo_title: string;
+  seo_description: string;
 }
 
+const emptyForm: ChannelFormData = {
+  name: '',
+  description: '',
+  color: '',
+  banner: '',
+  seo_title: '',
+  seo_description: ''
+};
+
 const ChannelManagement: React.FC<ChannelManagementProps> = ({ getAuthHeaders, token }) => {
   const [channels, setChannels] = useState<Channel[]>([]);
   const [loading, setLoading] = useState(true);
   const [showModal, setShowModal] = useState(false);
   const [editingChannel, setEditingChannel] = useState<Channel | null>(null);
-  const [formData, setFormData] = useState<ChannelFormData>({
-    name: ''
-  });
+  const [formData, setFormData] = useState<ChannelFormData>(emptyForm);
   const [uploadedImage, setUploadedImage] = useState<string | null>(null);
   const [uploadError, setUploadError] = useState<string | null>(null);
 
@@ -63,13 +83,17 @@ const ChannelManagement: React.FC<ChannelManagementProps> = ({ getAuthHeaders, t
     if (channel) {
       setEditingChannel(channel);
       setFormData({
-        name: channel.name
+        name: channel.name,
+        image: channel.image,
+        description: channel.description || '',
+        color: channel.color || '',
+        banner: channel.banner || '',
+        seo_title: channel.seo_title || '',
+        seo_description: channel.seo_description || ''
       });
     } else {
       setEditingChannel(null);
-      setFormData({
-        name: ''
-      });
+      setFormData(emptyForm);
     }
     setUploadedImage(null);
     setUploadError(null);
@@ -93,8 +117,13 @@ const ChannelManagement: React.FC<ChannelManagementProps> = ({ getAuthHeaders, t
 
     try {
       const requestData = {
-        ...formData,
-        ...(uploadedImage && { image: uploadedImage })
+        name: formData.name,
+        image: uploadedImage || formData.image || null,
+        description: formData.description.trim() || null,
+        color: formData.color || null,
+        banner: formData.banner || null,
+        seo_title: formData.seo_title.trim() || null,
+        seo_description: formData.seo_description.trim() || null
       };
 
       const url = editingChannel 
@@ -140,6 +169,29 @@ const ChannelManagement: React.FC<ChannelManagementProps> = ({ getAuthHeaders, t
     }
   };
 
+  const moveChannel = async (index: number, direction: -1 | 1) => {
+    const target = index + direction;
+    if (target < 0 || target >= channels.length) return;
+
+    const ids = channels.map((channel) => channel.id);
+    [ids[index], ids[target]] = [ids[target], ids[index]];
+
+    try {
+      const response = await fetch(getApiUrl('/admin/channels/order'), {
+        method: 'PUT',
+        headers: getAuthHeaders(),
+        body: JSON.stringify({ ids })
+      });
+
+      const result = await response.json();
+      if (result.success) {
+        await loadChannels();
+      }
+    } catch (error) {
+      console.error('Kanal sırası güncellenirken hata:', error);
+    }
+  };
+
   const formatDate = (dateString: string) => {
     return new Date(dateString).toLocaleDateString('tr-TR');
   };
@@ -171,7 +223,7 @@ const ChannelManagement: React.FC<ChannelManagementProps> = ({ getAuthHeaders, t
         </div>
       ) : (
         <div className="grid grid-cols-1 md:grid-cols-2 lg:grid-cols-3 gap-6">
-          {channels.map((channel) => (
+          {channels.map((channel, index) => (
             <div key={channel.id} className="bg-white rounded-lg shadow-sm border border-gray-200 overflow-hidden">
               <div className="aspect-video bg-gray-100 flex items-center justify-center">
                 {channel.image ? (
@@ -202,6 +254,20 @@ const ChannelManagement: React.FC<ChannelManagementProps> = ({ getAuthHeaders, t
                     {channel.video_count || 0} video
                   </span>
                   <div className="flex items-center space-x-2">
+                    <button
+                      onClick={() => moveChannel(index, -1)}
+                      className="p-2 text-gray-500 hover:text-gray-700 hover:bg-gray-100 rounded"
+                      title="Öne taşı"
+                    >
+                      <ChevronLeftIcon className="h-4 w-4" />
+                    </button>
+                    <button
+                      onClick={() => moveChannel(index, 1)}
+                      className="p-2 text-gray-500 hover:text-gray-700 hover:bg-gray-100 rounded"
+                      title="Sona taşı"
+                    >
+                      <ChevronRightIcon className="h-4 w-4" />
+                    </button>
                     <button
                       onClick={() => openModal(channel)}
                       className="p-2 text-blue-600 hover:text-blue-800 hover:bg-blue-50 rounded"
@@ -272,12 +338,109 @@ const ChannelManagement: React.FC<ChannelManagementProps> = ({ getAuthHeaders, t
                     }}
                     onUploadError={(error) => setUploadError(error)}
                     maxFileSize={2 * 1024 * 1024} // 2MB
-                    acceptedTypes="image/*"
+                    acceptedTypes="image/jpeg,image/png,image/webp"
                     chunkSize={512 * 1024} // 512KB chunks
                   />
                 </div>
               )}
 
+              <div>
+                <label className="block text-sm font-medium text-gray-700 mb-1">
+                  Açıklama
+                </label>
+                <textarea
+                  value={formData.description}
+                  onChange={(e) => setFormData({ ...formData, description: e.target.value })}
+                  className="w-full px-3 py-2 border border-gray-300 rounded-lg focus:outline-none focus:ring-2 focus:ring-green-500"
+                  rows={3}
+                  placeholder="Kanal sayfasında gösterilir"
+                />
+              </div>
+
+              <div>
+                <label className="block text-sm font-medium text-gray-700 mb-1">
+                  Renk
+                </label>
+                <div className="flex items-center space-x-2">
+                  <input
+                    type="color"
+                    value={formData.color || '#16a34a'}
+                    onChange={(e) => setFormData({ ...formData, color: e.target.value })}
+                    className="h-10 w-14 border border-gray-300 rounded"
+                  />
+                  {formData.color && (
+                    <button
+                      type="button"
+                      onClick={() => setFormData({ ...formData, color: '' })}
+                      className="text-sm text-gray-500 hover:text-gray-700"
+                    >
+                      Kaldır
+                    </button>
+                  )}
+                </div>
+              </div>
+
+              <div>
+                <label className="block text-sm font-medium text-gray-700 mb-2">
+                  Banner Görseli
+                </label>
+                {formData.banner && (
+                  <div className="flex items-center justify-between mb-2">
+                    <img
+                      src={getUploadsUrl(`/banners/${formData.banner}`)}
+                      alt="Banner"
+                      className="h-16 rounded object-cover"
+                    />
+                    <button
+                      type="button"
+                      onClick={() => setFormData({ ...formData, banner: '' })}
+                      className="text-sm text-red-600 hover:text-red-800"
+                    >
+                      Kaldır
+                    </button>
+                  </div>
+                )}
+                <ChunkedUpload
+                  token={token}
+                  uploadType="banner"
+                  onUploadComplete={(filename) => {
+                    setFormData((current) => ({ ...current, banner: filename }));
+                    setUploadError(null);
+                  }}
+                  onUploadError={(error) => setUploadError(error)}
+                  maxFileSize={8 * 1024 * 1024} // 8MB
+                  acceptedTypes="image/jpeg,image/png,image/webp"
+                  chunkSize={1024 * 1024} // 1MB chunks
+                />
+              </div>
+
+              <div>
+                <label className="block text-sm font-medium text-gray-700 mb-1">
+                  SEO Başlığı
+                </label>
+                <input
+                  type="text"
+                  value={formData.seo_title}
+                  onChange={(e) => setFormData({ ...formData, seo_title: e.target.value })}
+                  className="w-full px-3 py-2 border border-gray-300 rounded-lg focus:outline-none focus:ring-2 focus:ring-green-500"
+                  maxLength={70}
+                  placeholder="Boş bırakılırsa kanal adı kullanılır"
+                />
+              </div>
+
+              <div>
+                <label className="block text-sm font-medium text-gray-700 mb-1">
+                  SEO Açıklaması
+                </label>
+                <textarea
+                  value={formData.seo_description}
+                  onChange={(e) => setFormData({ ...formData, seo_description: e.target.value })}
+                  className="w-full px-3 py-2 border border-gray-300 rounded-lg focus:outline-none focus:ring-2 focus:ring-green-500"
+                  rows={2}
+                  maxLength={160}
+                />
+              </div>
+
               <div className="flex justify-end space-x-3 pt-6 border-t border-gray-200">
                 <button
                   type="button"
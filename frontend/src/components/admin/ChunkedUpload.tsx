@@ -1,5 +1,6 @@
 import React, { useState, useRef } from 'react';
 import { getApiUrl } from '../../config/env';
+import { sha256File } from '../../utils/sha256';
 import { 
   CloudArrowUpIcon,
   XMarkIcon,
@@ -9,12 +10,13 @@ import {
 
 interface ChunkedUploadProps {
   token: string;
-  uploadType: 'video' | 'thumbnail' | 'channel-image';
-  onUploadComplete: (filename: string, response?: any) => void;
+  uploadType: 'video' | 'thumbnail' | 'channel-image' | 'banner';
+  onUploadComplete: (filename: string, response?: any, file?: File) => void;
   onUploadError: (error: string) => void;
   maxFileSize: number;
   acceptedTypes: string;
   chunkSize?: number;
+  parallelChunks?: number;
 }
 
 interface UploadProgress {
@@ -32,7 +34,8 @@ const ChunkedUpload: React.FC<ChunkedUploadProps> = ({
   onUploadError,
   maxFileSize,
   acceptedTypes,
-  chunkSize = 1024 * 1024 // 1MB default chunk size
+  chunkSize = 1024 * 1024, // 1MB default chunk size
+  parallelChunks = 3
 }) => {
   const [file, setFile] = useState<File | null>(null);
   const [progress, setProgress] = useState<UploadProgress | null>(null);
@@ -74,13 +77,21 @@ const ChunkedUpload: React.FC<ChunkedUploadProps> = ({
     setIsDragging(false);
   };
 
+  const sha256Hex = async (data: Blob) => {
+    const digest = await crypto.subtle.digest('SHA-256', await data.arrayBuffer());
+    return Array.from(new Uint8Array(digest))
+      .map(byte => byte.toString(16).padStart(2, '0'))
+      .join('');
+  };
+
   const uploadChunk = async (
     chunk: Blob, 
     chunkIndex: number, 
     totalChunks: number, 
     uploadId: string,
     filename: string,
-    totalSize: number
+    totalSize: number,
+    fileSha256: string
   ) => {
     const formData = new FormData();
     formData.append('chunk', chunk);
@@ -91,7 +102,9 @@ const ChunkedUpload: React.FC<ChunkedUploadProps> = ({
       chunk_size: chunk.size,
       total_size: totalSize,
       file_name: filename,
-      upload_id: uploadId
+      upload_id: uploadId,
+      chunk_sha256: await sha256Hex(chunk),
+      file_sha256: fileSha256
     };
     
     formData.append('metadata', JSON.stringify(metadata));
@@ -109,7 +122,12 @@ const ChunkedUpload: React.FC<ChunkedUploadProps> = ({
       throw new Error(`Chunk ${chunkIndex} upload failed`);
     }
 
-    return await response.json();
+    const result = await response.json();
+    if (!result.success) {
+      throw new Error(result.message || `Chunk ${chunkIndex} upload failed`);
+    }
+
+    return result;
   };
 
   const startUpload = async () => {
@@ -129,43 +147,65 @@ const ChunkedUpload: React.FC<ChunkedUploadProps> = ({
     });
 
     try {
-      for (let i = 0; i < totalChunks; i++) {
-        if (abortControllerRef.current?.signal.aborted) {
-          throw new Error('Upload cancelled');
-        }
+      // The server checks the assembled file against this before accepting it.
+      const fileSha256 = await sha256File(file, 8 * 1024 * 1024, abortControllerRef.current.signal);
 
-        const start = i * chunkSize;
-        const end = Math.min(start + chunkSize, file.size);
-        const chunk = file.slice(start, end);
+      // Chunks are independent on the server, so a few are sent at once; whichever request
+      // delivers the last missing chunk gets the completed response.
+      let nextChunk = 0;
+      let uploadedChunks = 0;
+      let completedResult: any = null;
 
-        const result = await uploadChunk(
-          chunk, 
-          i, 
-          totalChunks, 
-          uploadId, 
-          file.name, 
-          file.size
-        );
+      const worker = async () => {
+        while (nextChunk < totalChunks && !completedResult) {
+          if (abortControllerRef.current?.signal.aborted) {
+            throw new Error('Upload cancelled');
+          }
 
-        const uploadedChunks = i + 1;
-        const progressPercent = Math.round((uploadedChunks / totalChunks) * 100);
+          const i = nextChunk++;
+          const start = i * chunkSize;
+          const end = Math.min(start + chunkSize, file.size);
+          const chunk = file.slice(start, end);
 
-        setProgress(prev => prev ? {
-          ...prev,
-          uploadedChunks,
-          progress: progressPercent
-        } : null);
+          const result = await uploadChunk(
+            chunk, 
+            i, 
+            totalChunks, 
+            uploadId, 
+            file.name, 
+            file.size,
+            fileSha256
+          );
+
+          uploadedChunks += 1;
+          const progressPercent = Math.round((uploadedChunks / totalChunks) * 100);
 
-        if (result.success && result.data?.completed) {
           setProgress(prev => prev ? {
             ...prev,
-            status: 'completed'
+            uploadedChunks,
+            progress: progressPercent
           } : null);
-          // Backend returns file_path like "thumbnails/uuid.jpg", keep the full path for thumbnails
-          const filename = uploadType === 'thumbnail' ? result.data.file_path : (result.data.file_path ? result.data.file_path.split('/').pop() : '');
-          onUploadComplete(filename, result.data);
-          return;
+
+          if (result.success && result.data?.completed) {
+            completedResult = result;
+          }
         }
+      };
+
+      await Promise.all(
+        Array.from({ length: Math.min(parallelChunks, totalChunks) }, () => worker())
+      );
+
+      if (completedResult) {
+        const result = completedResult;
+        setProgress(prev => prev ? {
+          ...prev,
+          status: 'completed'
+        } : null);
+        // Backend returns file_path like "thumbnails/uuid.jpg", keep the full path for thumbnails
+        const filename = uploadType === 'thumbnail' ? result.data.file_path : (result.data.file_path ? result.data.file_path.split('/').pop() : '');
+        onUploadComplete(filename, result.data, file ?? undefined);
+        return;
       }
     } catch (error: any) {
       if (error.name === 'AbortError' || error.message === 'Upload cancelled') {
@@ -176,13 +216,11 @@ const ChunkedUpload: React.FC<ChunkedUploadProps> = ({
         
         // Cancel upload on server
         try {
-          await fetch(getApiUrl('/admin/upload/cancel'), {
+          await fetch(getApiUrl(`/admin/upload/cancel?upload_id=${encodeURIComponent(uploadId)}`), {
             method: 'DELETE',
             headers: {
-              'Authorization': `Bearer ${token}`,
-              'Content-Type': 'application/json'
-            },
-            body: JSON.stringify({ upload_id: uploadId })
+              'Authorization': `Bearer ${token}`
+            }
           });
         } catch (cancelError) {
           console.error('Error cancelling upload:', cancelError);
@@ -9,6 +9,20 @@ import {
   XMarkIcon
 } from '@heroicons/react/24/outline';
 
+/** Whole seconds of a video file, read from its metadata by the browser; null if unreadable. */
+const readVideoDuration = (file: File) => new Promise<number | null>(resolve => {
+  const video = document.createElement('video');
+  const url = URL.createObjectURL(file);
+  const done = (duration: number | null) => {
+    URL.revokeObjectURL(url);
+    resolve(duration);
+  };
+  video.preload = 'metadata';
+  video.onloadedmetadata = () => done(Number.isFinite(video.duration) ? Math.round(video.duration) : null);
+  video.onerror = () => done(null);
+  video.src = url;
+});
+
 interface VideoManagementProps {
   getAuthHeaders: () => Record<string, string>;
   token: string;
@@ -28,6 +42,8 @@ interface Video {
   category_name: string;
   category_id: string;
   is_featured: boolean;
+  tags?: { name: string; slug: string }[];
+  categories?: { id: string; name: string; is_primary: boolean }[];
 }
 
 interface Channel {
@@ -46,6 +62,10 @@ interface VideoFormData {
   channel_id: string;
   category_id: string;
   is_featured: boolean;
+  /** Comma separated tag names. */
+  tags: string;
+  /** Categories besides the primary `category_id`. */
+  category_ids: string[];
   video_file?: string;
   thumbnail?: string;
   disk_id?: string;
@@ -63,11 +83,14 @@ const VideoManagement: React.FC<VideoManagementProps> = ({ getAuthHeaders, token
     description: '',
     channel_id: '',
     category_id: '',
-    is_featured: false
+    is_featured: false,
+    tags: '',
+    category_ids: []
   });
   const [uploadedVideoFile, setUploadedVideoFile] = useState<string | null>(null);
   const [uploadedThumbnail, setUploadedThumbnail] = useState<string | null>(null);
   const [uploadedDiskId, setUploadedDiskId] = useState<string | null>(null);
+  const [uploadedDuration, setUploadedDuration] = useState<number | null>(null);
   const [uploadError, setUploadError] = useState<string | null>(null);
 
   useEffect(() => {
@@ -118,6 +141,7 @@ const VideoManagement: React.FC<VideoManagementProps> = ({ getAuthHeaders, token
     setUploadedVideoFile(null);
     setUploadedThumbnail(null);
     setUploadedDiskId(null);
+    setUploadedDuration(null);
     setUploadError(null);
     
     if (video) {
@@ -127,7 +151,9 @@ const VideoManagement: React.FC<VideoManagementProps> = ({ getAuthHeaders, token
         description: video.description || '',
         channel_id: video.channel_id,
         category_id: video.category_id,
-        is_featured: video.is_featured
+        is_featured: video.is_featured,
+        tags: (video.tags || []).map(tag => tag.name).join(', '),
+        category_ids: (video.categories || []).filter(category => !category.is_primary).map(category => category.id)
       });
     } else {
       setEditingVideo(null);
@@ -136,7 +162,9 @@ const VideoManagement: React.FC<VideoManagementProps> = ({ getAuthHeaders, token
         description: '',
         channel_id: channels.length > 0 ? channels[0].id : '',
         category_id: categories.length > 0 ? categories[0].id : '',
-        is_featured: false
+        is_featured: false,
+        tags: '',
+        category_ids: []
       });
     }
     
@@ -149,6 +177,7 @@ const VideoManagement: React.FC<VideoManagementProps> = ({ getAuthHeaders, token
     setUploadedVideoFile(null);
     setUploadedThumbnail(null);
     setUploadedDiskId(null);
+    setUploadedDuration(null);
     setUploadError(null);
   };
 
@@ -168,9 +197,12 @@ const VideoManagement: React.FC<VideoManagementProps> = ({ getAuthHeaders, token
     try {
       const requestData = {
         ...formData,
+        tags: formData.tags.split(',').map(tag => tag.trim()).filter(Boolean),
+        category_ids: formData.category_ids.filter(id => id !== formData.category_id),
         ...(uploadedVideoFile && { video_file: uploadedVideoFile }),
         ...(uploadedThumbnail && { thumbnail: uploadedThumbnail }),
-        ...(uploadedDiskId && { disk_id: uploadedDiskId })
+        ...(uploadedDiskId && { disk_id: uploadedDiskId }),
+        ...(uploadedDuration && { duration: uploadedDuration })
       };
 
       const url = editingVideo 
@@ -440,6 +472,45 @@ const VideoManagement: React.FC<VideoManagementProps> = ({ getAuthHeaders, token
                     </div>
                   </div>
 
+                  {categories.length > 1 && (
+                    <div>
+                      <label className="block text-sm font-medium text-gray-700 mb-1">
+                        Ek Kategoriler
+                      </label>
+                      <div className="flex flex-wrap gap-3">
+                        {categories.filter(category => category.id !== formData.category_id).map((category) => (
+                          <label key={category.id} className="flex items-center text-sm text-gray-700">
+                            <input
+                              type="checkbox"
+                              checked={formData.category_ids.includes(category.id)}
+                              onChange={(e) => setFormData({
+                                ...formData,
+                                category_ids: e.target.checked
+                                  ? [...formData.category_ids, category.id]
+                                  : formData.category_ids.filter(id => id !== category.id)
+                              })}
+                              className="mr-1"
+                            />
+                            {category.name}
+                          </label>
+                        ))}
+                      </div>
+                    </div>
+                  )}
+
+                  <div>
+                    <label className="block text-sm font-medium text-gray-700 mb-1">
+                      Etiketler
+                    </label>
+                    <input
+                      type="text"
+                      value={formData.tags}
+                      onChange={(e) => setFormData({ ...formData, tags: e.target.value })}
+                      placeholder="Virgülle ayırın, ör. oyun, rehber"
+                      className="w-full px-3 py-2 border border-gray-300 rounded-lg focus:outline-none focus:ring-2 focus:ring-blue-500"
+                    />
+                  </div>
+
                   <div className="flex items-center">
                     <input
                       type="checkbox"
@@ -463,16 +534,19 @@ const VideoManagement: React.FC<VideoManagementProps> = ({ getAuthHeaders, token
                       <ChunkedUpload
                         token={token}
                         uploadType="video"
-                        onUploadComplete={(filename, responseData) => {
+                        onUploadComplete={(filename, responseData, file) => {
                           setUploadedVideoFile(filename);
                           if (responseData?.disk_id) {
                             setUploadedDiskId(responseData.disk_id);
                           }
+                          if (file) {
+                            readVideoDuration(file).then(setUploadedDuration);
+                          }
                           setUploadError(null);
                         }}
                         onUploadError={(error) => setUploadError(error)}
                         maxFileSize={2 * 1024 * 1024 * 1024} // 2GB
-                        acceptedTypes="video/*"
+                        acceptedTypes="video/mp4,video/quicktime,.mov,video/webm,video/x-matroska,.mkv"
                         chunkSize={2 * 1024 * 1024} // 2MB chunks
                       />
                     </div>
@@ -491,7 +565,7 @@ const VideoManagement: React.FC<VideoManagementProps> = ({ getAuthHeaders, token
                       }}
                       onUploadError={(error) => setUploadError(error)}
                       maxFileSize={5 * 1024 * 1024} // 5MB
-                      acceptedTypes="image/*"
+                      acceptedTypes="image/jpeg,image/png,image/webp"
                       chunkSize={1 * 1024 * 1024} // 1MB chunks
                     />
                   </div>
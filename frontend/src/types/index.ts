@@ -3,10 +3,23 @@ export interface Channel {
   name: string;
   slug: string;
   image?: string;
+  position?: number;
+  description?: string | null;
+  color?: string | null;
+  banner?: string | null;
+  seo_title?: string | null;
+  seo_description?: string | null;
   created_at: string;
   video_count?: number;
 }
 
+export interface ImageSource {
+  url: string;
+  width: number;
+  height: number;
+  mime_type: string;
+}
+
 export interface Video {
   id: string;
   title: string;
@@ -26,16 +39,58 @@ export interface Video {
   category_id: string;
   category_slug: string;
   is_featured: boolean;
+  thumbnail_variants?: ImageSource[];
+  tags?: TagSummary[];
+  categories?: CategorySummary[];
+}
+
+export interface CategorySummary {
+  id: string;
+  name: string;
+  slug: string;
+  is_primary: boolean;
+}
+
+export interface TagSummary {
+  name: string;
+  slug: string;
+}
+
+export interface Tag extends TagSummary {
+  id: string;
+  video_count: number;
 }
 
 export interface Category {
   id: string;
   name: string;
   slug: string;
+  parent_id?: string | null;
+  position?: number;
+  description?: string | null;
+  icon?: string | null;
+  color?: string | null;
+  banner?: string | null;
+  seo_title?: string | null;
+  seo_description?: string | null;
   created_at?: string;
   video_count?: number;
 }
 
+export interface CategoryNode extends Category {
+  children: CategoryNode[];
+}
+
+export interface CategoryCrumb {
+  id: string;
+  name: string;
+  slug: string;
+}
+
+export interface CategoryDetails extends Category {
+  breadcrumbs: CategoryCrumb[];
+}
+
 export interface ApiResponse<T> {
   success: boolean;
   data: T;
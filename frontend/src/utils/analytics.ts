@@ -0,0 +1,82 @@
+import { getApiUrl } from '../config/env';
+
+const CLIENT_ID_KEY = 'portalyus_client_id';
+
+/** Returns a random id kept in local storage, so views are deduplicated per browser. */
+export const getClientId = (): string | undefined => {
+  try {
+    let id = localStorage.getItem(CLIENT_ID_KEY);
+    if (!id) {
+      id = crypto.randomUUID();
+      localStorage.setItem(CLIENT_ID_KEY, id);
+    }
+    return id;
+  } catch {
+    return undefined;
+  }
+};
+
+/** Sends a JSON payload without delaying navigation; the server accepts it as plain text. */
+export const sendBeacon = (endpoint: string, payload: object) => {
+  const url = getApiUrl(endpoint);
+  const body = JSON.stringify(payload);
+  if (navigator.sendBeacon?.(url, body)) return;
+  fetch(url, { method: 'POST', body, keepalive: true }).catch(() => {});
+};
+
+export const sendViewBeacon = (slug: string) => {
+  sendBeacon(`/videos/${encodeURIComponent(slug)}/view`, { client_id: getClientId() });
+};
+
+export const HEARTBEAT_INTERVAL_MS = 15000;
+/** Position jumps larger than this between time updates are seeks, not playback. */
+const MAX_PLAYBACK_STEP_SECONDS = 2;
+
+/**
+ * Collects continuous playback spans of one viewing and reports them as heartbeats, which the
+ * server turns into watch time and retention.
+ */
+export class PlaybackTracker {
+  private readonly sessionId = crypto.randomUUID();
+  private spanStart: number | null = null;
+  private position = 0;
+  private duration = 0;
+  private watched = 0;
+
+  constructor(private readonly slug: string) {}
+
+  progress(position: number, duration: number) {
+    if (Number.isFinite(duration) && duration > 0) this.duration = duration;
+    if (this.spanStart === null) {
+      this.spanStart = position;
+    } else {
+      const step = position - this.position;
+      if (step >= 0 && step <= MAX_PLAYBACK_STEP_SECONDS) {
+        this.watched += step;
+      } else {
+        this.seek();
+        this.spanStart = position;
+      }
+    }
+    this.position = position;
+  }
+
+  /** Ends the current span; the next time update starts a new one. */
+  seek() {
+    this.flush();
+    this.spanStart = null;
+  }
+
+  flush() {
+    if (this.spanStart === null || this.watched <= 0 || this.duration <= 0) return;
+    sendBeacon(`/videos/${encodeURIComponent(this.slug)}/heartbeat`, {
+      session_id: this.sessionId,
+      client_id: getClientId(),
+      start: this.spanStart,
+      position: this.position,
+      watched: this.watched
+    });
+    this.spanStart = this.position;
+    this.watched = 0;
+  }
+}